@@ -4,4 +4,4 @@ import type { RankExpression } from "./RankExpression";
 import type { SuitExpression } from "./SuitExpression";
 import type { ZoneExpression } from "./ZoneExpression";
 
-export type CardCollectionExpression = { "Single": CardExpression } | { "GetVariable": string } | { "AllInZone": ZoneExpression } | { "TopInZone": ZoneExpression } | { "BottomInZone": ZoneExpression } | { "InZoneMatchingSuit": { zone: ZoneExpression, suit: SuitExpression, } } | { "InZoneMatchingRank": { zone: ZoneExpression, rank: RankExpression, } };
+export type CardCollectionExpression = { "Single": CardExpression } | { "GetVariable": string } | { "AllInZone": ZoneExpression } | { "TopInZone": ZoneExpression } | { "BottomInZone": ZoneExpression } | { "InZoneMatchingSuit": { zone: ZoneExpression, suit: SuitExpression, } } | { "InZoneMatchingRank": { zone: ZoneExpression, rank: RankExpression, } } | { "InZoneMatchingPattern": { zone: ZoneExpression, pattern: string, } };
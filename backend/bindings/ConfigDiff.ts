@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeclarationChange } from "./DeclarationChange";
+import type { PhaseDiff } from "./PhaseDiff";
+
+export type ConfigDiff = { declarations: Array<DeclarationChange>, phases: Array<PhaseDiff>, };
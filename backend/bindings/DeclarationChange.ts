@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Change } from "./Change";
+
+export type DeclarationChange = { declaration: string, change: Change, before: string | null, after: string | null, };
@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { GameChat } from "./GameChat";
+import type { JoinAccepted } from "./JoinAccepted";
 
-export type GameAction = "SetCards" | "Layout" | "Private" | { "JoinResult": { Ok : string } | { Err : string } } | { "ChatMsg": GameChat };
+export type GameAction = "SetCards" | "Layout" | "Private" | { "JoinResult": { Ok : JoinAccepted } | { Err : string } } | { "ChatMsg": GameChat } | { "OfferResult": { Ok : null } | { Err : string } };
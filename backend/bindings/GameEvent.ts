@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OfferResponse } from "./OfferResponse";
+
+export type GameEvent = { "Started": { players: number, } } | { "CardsMoved": { cards: Array<bigint>, to: bigint, } } | { "ZoneShuffled": { zone: bigint, } } | { "PhaseEntered": { phase: string, } } | { "OfferAnswered": { player: bigint, response: OfferResponse, } } | { "Finished": { winners: Array<bigint>, } } | { "Crashed": { error: string, } };
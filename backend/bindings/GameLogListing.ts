@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GameLogPreview } from "./GameLogPreview";
+import type { Pagination } from "./Pagination";
+
+export type GameLogListing = { total: number, pagination: Pagination, contents: Array<GameLogPreview>, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GameLogPreview = { game_log_id: string, played_at: string, revision: number | null, config_hash: string, seed: string, error: string | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ReplayStep } from "./ReplayStep";
+
+export type GameReplay = { game_log_id: string, ruleset_id: string, revision: number | null, config_hash: string, seed: string, error: string | null, steps: Array<ReplayStep>, };
@@ -2,7 +2,8 @@
 import type { Card } from "./Card";
 import type { GameAction } from "./GameAction";
 import type { GameStatus } from "./GameStatus";
+import type { OfferSnapshot } from "./OfferSnapshot";
 import type { PlayerSnapshot } from "./PlayerSnapshot";
 import type { ZoneSnapshot } from "./ZoneSnapshot";
 
-export type GameSnapshot = { actions: Array<GameAction>, private_actions: Array<GameAction>, players: PlayerSnapshot | null, status: GameStatus, public_cards: { [key in number]?: Card }, zones: Array<ZoneSnapshot>, };
+export type GameSnapshot = { actions: Array<GameAction>, private_actions: Array<GameAction>, players: PlayerSnapshot | null, status: GameStatus, public_cards: { [key in number]?: Card }, zones: Array<ZoneSnapshot>, offer: OfferSnapshot | null, };
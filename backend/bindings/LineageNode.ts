@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type LineageNode = { ruleset_id: string, based_on: string | null, author_id: string, author_name: string, title: string | null, depth: number, };
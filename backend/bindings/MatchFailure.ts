@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type MatchFailure = { "MissingCard": bigint } | { "MissingOrder": string } | { "NotConsecutive": { pattern: number, card: bigint, } } | { "Unmatched": { pattern: number, card: bigint, } } | { "TooFewCards": { pattern: number, } };
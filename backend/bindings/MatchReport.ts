@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MatchFailure } from "./MatchFailure";
+import type { PieceMatch } from "./PieceMatch";
+
+export type MatchReport = { pieces: Array<PieceMatch>, failure: MatchFailure | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type OfferChoiceSnapshot = { "Player": { name: string, options: Array<bigint> | null, } } | { "Players": { name: string, options: Array<bigint> | null, } } | { "Card": { name: string, options: Array<number> | null, } } | { "Cards": { name: string, options: Array<number> | null, } } | { "MoveCards": { from: number | null, to: number | null, options: Array<number> | null, } };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChoiceValue } from "./ChoiceValue";
+
+export type OfferResponse = { case: number, choices: Array<ChoiceValue>, };
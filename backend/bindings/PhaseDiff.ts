@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Change } from "./Change";
+import type { StatementChange } from "./StatementChange";
+
+export type PhaseDiff = { phase: string, change: Change, statements: Array<StatementChange>, };
@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OfferAnswer } from "./OfferAnswer";
 
-export type PlayerCommand = "StartGame" | "Heartbeat" | { "SendMsg": string };
+export type PlayerCommand = "StartGame" | "Heartbeat" | { "SendMsg": string } | { "RespondToOffer": OfferAnswer };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GameEvent } from "./GameEvent";
+import type { ReplayZone } from "./ReplayZone";
+
+export type ReplayStep = { events: Array<GameEvent>, zones: Array<ReplayZone>, error: string | null, };
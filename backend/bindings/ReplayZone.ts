@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ReplayCard } from "./ReplayCard";
+
+export type ReplayZone = { zone_id: bigint, owner: bigint | null, display_name: string | null, cards: Array<ReplayCard>, };
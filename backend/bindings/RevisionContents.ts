@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GameConfig } from "./GameConfig";
+
+export type RevisionContents = { revision: number, created_at: string, config: GameConfig, title: string, description: string, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Pagination } from "./Pagination";
+import type { RevisionPreview } from "./RevisionPreview";
+
+export type RevisionListing = { total: number, pagination: Pagination, contents: Array<RevisionPreview>, };
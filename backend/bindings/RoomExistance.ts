@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RoomInfo } from "./RoomInfo";
 
-export type RoomExistance = { exists: boolean, };
+export type RoomExistance = { exists: boolean, room: RoomInfo | null, };
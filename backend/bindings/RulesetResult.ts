@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConfigDiagnostic } from "./ConfigDiagnostic";
 
-export type RulesetResult = { ruleset_id: string, };
+export type RulesetResult = { ruleset_id: string, diagnostics: Array<ConfigDiagnostic>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConfigDiff } from "./ConfigDiff";
+
+export type UpstreamChanges = { based_on: string, upstream_revision: number, diff: ConfigDiff, };
@@ -0,0 +1,18 @@
+create table "ruleset_revision"
+(
+	ruleset_id 		uuid references "ruleset" (ruleset_id) not null,
+	revision 		integer 				not null,
+	created_at 		timestamptz 			not null default now(),
+
+	config 			text 					not null,
+	config_version 	integer 				not null,
+	title 			text 					not null,
+	description 	text 					not null,
+
+	primary key (ruleset_id, revision)
+);
+
+-- Whatever each ruleset holds now becomes its first revision
+insert into "ruleset_revision" (ruleset_id, revision, created_at, config, config_version, title, description)
+select ruleset_id, 1, coalesce(updated_at, created_at), config, config_version, title, description
+from "ruleset";
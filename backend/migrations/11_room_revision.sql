@@ -0,0 +1,17 @@
+alter table "room"
+add column revision integer;
+
+alter table "game_log"
+add column revision integer;
+
+-- Anything saved before revisions were pinned gets the revision holding its config, if one does
+-- Configs are compared as json so key order and whitespace from different serializers don't matter
+update "room" set revision = (
+	select max(revision) from "ruleset_revision" r
+	where r.ruleset_id = "room".ruleset_id and r.config::jsonb = "room".config::jsonb
+);
+
+update "game_log" set revision = (
+	select max(revision) from "ruleset_revision" r
+	where r.ruleset_id = "game_log".ruleset_id and r.config::jsonb = "game_log".config::jsonb
+);
@@ -0,0 +1,13 @@
+alter table "ruleset"
+add column published_revision integer,
+add column published_at timestamptz,
+add foreign key (ruleset_id, published_revision) references "ruleset_revision" (ruleset_id, revision);
+
+-- Rulesets already marked published go public as they are now, the server unpublishes any with
+-- errors when it starts since validation can't run from here
+update "ruleset" r
+set published_revision = (select max(revision) from "ruleset_revision" where ruleset_id = r.ruleset_id),
+	published_at = coalesce(r.updated_at, r.created_at)
+where r.published;
+
+update "ruleset" set published = false where published and published_revision is null;
@@ -0,0 +1,13 @@
+create table "game_log"
+(
+	game_log_id 	uuid primary key 		default uuid_generate_v1mc(),
+	created_at 		timestamptz 			not null default now(),
+
+	ruleset_id 		uuid references "ruleset" (ruleset_id) not null,
+	config 			text 					not null,
+	config_hash 	text 					not null,
+	seed 			bigint 					not null,
+	events 			text 					not null
+);
+
+create index on "game_log" (ruleset_id, created_at);
@@ -0,0 +1,14 @@
+create table "room"
+(
+	room_code 		bigint primary key,
+	created_at 		timestamptz 			not null default now(),
+	updated_at 		timestamptz,
+
+	ruleset_id 		uuid references "ruleset" (ruleset_id) not null,
+	name 			text 					not null,
+	config 			text 					not null,
+	config_hash 	text 					not null,
+	checkpoint 		text 					not null
+);
+
+SELECT trigger_updated_at('"room"');
@@ -0,0 +1,5 @@
+alter table "game_log"
+add column config_version integer not null default 0;
+
+alter table "room"
+add column config_version integer not null default 0;
@@ -4,6 +4,7 @@ use tracing::info;
 pub struct Config {
     pub database_url: String,
     pub hmac_key: String,
+    pub upgrade_stored_configs: bool, // Rewrite rulesets and revisions saved with an old config version on startup
 }
 
 impl Config {
@@ -16,12 +17,16 @@ impl Config {
 
         let database_url: String = env_variables["DATABASE_URL"].clone();
         let hmac_key: String = env_variables["HMAC_KEY"].clone();
+        let upgrade_stored_configs = env_variables
+            .get("UPGRADE_STORED_CONFIGS")
+            .is_some_and(|value| value == "true");
 
         info!("Configuration read");
 
         Config {
             database_url,
             hmac_key,
+            upgrade_stored_configs,
         }
     }
 }
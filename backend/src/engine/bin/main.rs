@@ -1,8 +1,356 @@
-use backend::engine::core::interpreter::game;
+use backend::engine::core::interpreter::{
+    config, game, simulation,
+    state::{
+        execution_state::{ChoiceValue, ChoiceView, OfferResponse, OfferView},
+        game_state::{CardID, PlayerOrderIndex, ZoneView},
+    },
+    validation,
+};
+use backend::engine::core::{dsl, types::cards::Card};
+use game::{EngineStatus, Game, GameError, RecoverableGameError};
 
-fn main() {
-    //let go_fish = ruleset::Ruleset { player_range: 1..4 };
-    //let new_game = game::Game::new(go_fish);
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
 
-    println!("Hello, world!");
+const USAGE: &str = "Usage: engine <config.json|ruleset> [--players N] [--seed SEED] \
+                     [--simulate GAMES [--max-answers N]]";
+
+struct Options {
+    config_path: String,
+    players: Option<u32>,
+    seed: Option<u64>,
+    simulate: Option<u32>, // Play this many random games instead of reading stdin
+    max_answers: u32,
+}
+
+// Runs a ruleset in the terminal, every seat is answered from stdin
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        config_path: String::new(),
+        players: None,
+        seed: None,
+        simulate: None,
+        max_answers: 1000,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--players" => {
+                let count = args.next().ok_or("--players needs a number")?;
+                options.players = Some(count.parse().map_err(|_| "--players needs a number")?);
+            }
+            "--seed" => {
+                let seed = args.next().ok_or("--seed needs a number")?;
+                options.seed = Some(seed.parse().map_err(|_| "--seed needs a number")?);
+            }
+            "--simulate" => {
+                let games = args.next().ok_or("--simulate needs a number of games")?;
+                options.simulate = Some(
+                    games
+                        .parse()
+                        .map_err(|_| "--simulate needs a number of games")?,
+                );
+            }
+            "--max-answers" => {
+                let answers = args.next().ok_or("--max-answers needs a number")?;
+                options.max_answers = answers
+                    .parse()
+                    .map_err(|_| "--max-answers needs a number")?;
+            }
+            "-h" | "--help" => return Err("".into()),
+            _ if options.config_path.is_empty() => options.config_path = arg,
+            _ => return Err(format!("Unexpected argument '{arg}'")),
+        }
+    }
+    if options.config_path.is_empty() {
+        return Err("No config given".into());
+    }
+    Ok(options)
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let text = std::fs::read_to_string(&options.config_path)
+        .map_err(|e| format!("Couldn't read {}: {e}", options.config_path))?;
+    // JSON is what the site stores, anything else is read as the text form
+    let config: config::GameConfig = if options.config_path.ends_with(".json") {
+        serde_json::from_str(&text).map_err(|e| format!("Config is not valid: {e}"))?
+    } else {
+        dsl::parse_config(&text).map_err(|e| format!("{}: {e}", options.config_path))?
+    };
+
+    let problems = validation::validate_config(&config);
+    for problem in problems.iter() {
+        println!("{problem}");
+    }
+    if problems.iter().any(|p| p.is_error()) {
+        return Err("Ruleset has errors, not running it".into());
+    }
+
+    let players = options.players.unwrap_or(config.player_range.start.max(1));
+    let seed = options.seed.unwrap_or_else(rand::random);
+    if let Some(games) = options.simulate {
+        return simulate(&config, games, players, seed, options.max_answers);
+    }
+    println!("Playing with {players} players, seed {seed}");
+
+    let mut game = Game::new(config, seed).map_err(|e| e.to_string())?;
+    game.update_players(players)?;
+    game.init()
+        .map_err(|e| format!("Couldn't start game: {}", describe_error(&e)))?;
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        match game.eval_statement() {
+            Ok(EngineStatus::Ready) => (),
+            Ok(EngineStatus::Sleep(seconds)) => println!("(game waits {seconds}s)"),
+            Ok(EngineStatus::Broadcast { msg, to }) => println!("[to {to:?}] {msg}"),
+            Ok(EngineStatus::Blocked) => {
+                print_state(&game, players);
+                if !answer_offer(&mut game, &mut lines)? {
+                    println!("Quitting");
+                    return Ok(());
+                }
+            }
+            Ok(EngineStatus::Finished) => {
+                print_state(&game, players);
+                println!("Game over, won by {:?}", game.get_winners());
+                return Ok(());
+            }
+            Err(GameError::Recoverable(RecoverableGameError::BrokenZoneRule {
+                player,
+                violation,
+            })) => println!("Player {player}'s move was undone: {violation}"),
+            Err(e) => return Err(format!("Game crashed: {}", describe_error(&e))),
+        }
+    }
+}
+
+fn simulate(
+    config: &config::GameConfig,
+    games: u32,
+    players: u32,
+    first_seed: u64,
+    max_answers: u32,
+) -> Result<(), String> {
+    println!("Simulating seeds {first_seed} and up");
+    let report = simulation::simulate(
+        config,
+        &simulation::SimulationOptions {
+            games,
+            players,
+            first_seed,
+            max_answers,
+        },
+    );
+    print!("{report}");
+    match report.failures().count() {
+        0 => Ok(()),
+        failed => Err(format!("{failed} games didn't finish cleanly")),
+    }
+}
+
+fn describe_error(error: &GameError) -> String {
+    match error {
+        GameError::Fatal(e) => e.to_string(),
+        GameError::Recoverable(e) => e.to_string(),
+    }
+}
+
+fn print_state(game: &Game, players: u32) {
+    let roles = game.get_roles();
+    for player in 0..players as PlayerOrderIndex {
+        let role = roles.get(player as usize).cloned().unwrap_or_default();
+        println!("--- Player {player} ({role}) sees:");
+        for zone in game.visible_zones(Some(player)) {
+            println!("  {}", describe_zone(&zone));
+        }
+    }
+}
+
+fn describe_zone(zone: &ZoneView) -> String {
+    let name = zone.name.clone().unwrap_or_else(|| "unnamed".into());
+    let owner = match zone.owner {
+        Some(owner) => format!(" of player {owner}"),
+        None => String::new(),
+    };
+    let cards: Vec<String> = zone
+        .cards
+        .iter()
+        .map(|card| match card {
+            Some((card_id, card)) => format!("{} #{card_id}", describe_card(card)),
+            None => "??".into(),
+        })
+        .collect();
+    format!(
+        "zone {} '{name}'{owner}: [{}]",
+        zone.zone_id,
+        cards.join(", ")
+    )
+}
+
+fn describe_card(card: &Card) -> String {
+    format!("{:?} of {:?}", card.rank, card.suit)
+}
+
+// Prompts until someone answers the pending offer, false if stdin ran out or they quit
+fn answer_offer(
+    game: &mut Game,
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> Result<bool, String> {
+    let offered = match game.pending_offer() {
+        Some(pending) => pending.players.clone(),
+        None => return Ok(true),
+    };
+    let mut views: HashMap<PlayerOrderIndex, OfferView> = HashMap::new();
+    for player in offered.iter() {
+        if let Some(view) = game.describe_offer(*player) {
+            print_offer(game, *player, &view);
+            views.insert(*player, view);
+        }
+    }
+    println!("Answer with: <player> <case> <choice>... (lists are comma separated, '-' is empty)");
+
+    loop {
+        print!("> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => return Ok(false),
+        };
+        if line.trim() == "quit" {
+            return Ok(false);
+        }
+
+        let (player, response) = match parse_answer(&line, &views) {
+            Ok(answer) => answer,
+            Err(e) => {
+                println!("{e}");
+                continue;
+            }
+        };
+        match game.respond_to_offer(player, &response) {
+            Ok(()) => return Ok(true),
+            Err(e) => println!("Answer rejected: {}", describe_error(&e)),
+        }
+    }
+}
+
+fn print_offer(game: &Game, player: PlayerOrderIndex, view: &OfferView) {
+    let known: HashMap<CardID, Card> = game
+        .visible_zones(Some(player))
+        .into_iter()
+        .flat_map(|zone| zone.cards.into_iter().flatten())
+        .collect();
+    let describe_cards = |options: &Option<Vec<CardID>>| match options {
+        Some(options) => options
+            .iter()
+            .map(|card_id| match known.get(card_id) {
+                Some(card) => format!("#{card_id} {}", describe_card(card)),
+                None => format!("#{card_id}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => "depends on earlier choices".into(),
+    };
+
+    println!("=== Player {player} is offered:");
+    for case in view.cases.iter() {
+        println!("  case {}: {}", case.index, case.message);
+        for choice in case.choices.iter() {
+            match choice {
+                ChoiceView::Player { name, options } => {
+                    println!("    one player '{name}' from {options:?}")
+                }
+                ChoiceView::Players { name, options } => {
+                    println!("    players '{name}' from {options:?}")
+                }
+                ChoiceView::Card { name, options } => {
+                    println!("    one card '{name}' from {}", describe_cards(options))
+                }
+                ChoiceView::Cards { name, options } => {
+                    println!("    cards '{name}' from {}", describe_cards(options))
+                }
+                ChoiceView::MoveCards { from, to, options } => {
+                    println!(
+                        "    cards to move from zone {from:?} to zone {to:?}, out of {}",
+                        describe_cards(options)
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn parse_answer(
+    line: &str,
+    views: &HashMap<PlayerOrderIndex, OfferView>,
+) -> Result<(PlayerOrderIndex, OfferResponse), String> {
+    let mut words = line.split_whitespace();
+    let player: PlayerOrderIndex = parse_number(words.next().ok_or("Missing player")?)?;
+    let view = views
+        .get(&player)
+        .ok_or(format!("Player {player} isn't being offered anything"))?;
+    let case_index: usize = parse_number(words.next().ok_or("Missing case")?)?;
+    let case = view
+        .cases
+        .iter()
+        .find(|case| case.index == case_index)
+        .ok_or(format!("Case {case_index} isn't available"))?;
+
+    let mut choices = Vec::with_capacity(case.choices.len());
+    for choice in case.choices.iter() {
+        let word = words.next().ok_or("Not every choice was answered")?;
+        choices.push(match choice {
+            ChoiceView::Player { .. } => ChoiceValue::Player(parse_number(word)?),
+            ChoiceView::Players { .. } => ChoiceValue::Players(parse_list(word)?),
+            ChoiceView::Card { .. } => ChoiceValue::Card(parse_number(word)?),
+            ChoiceView::Cards { .. } | ChoiceView::MoveCards { .. } => {
+                ChoiceValue::Cards(parse_list(word)?)
+            }
+        });
+    }
+    if words.next().is_some() {
+        return Err("Too many choices".into());
+    }
+    Ok((
+        player,
+        OfferResponse {
+            case: case_index,
+            choices,
+        },
+    ))
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.trim_start_matches('#')
+        .parse()
+        .map_err(|_| format!("'{word}' is not a number"))
+}
+
+fn parse_list<T: std::str::FromStr>(word: &str) -> Result<Vec<T>, String> {
+    if word == "-" {
+        return Ok(Vec::new());
+    }
+    word.split(',').map(parse_number).collect()
 }
@@ -0,0 +1,119 @@
+use crate::engine::core::interpreter::lang::{expressions, phases, statements::*};
+use crate::engine::core::types::{
+    identifiers::*, patterns, players, ranks::Rank, suits::Suit, zones,
+};
+
+use std::ops::Range;
+use std::sync::Arc;
+
+// Top level items of a ruleset file, these get folded into a GameConfig once it's parsed
+#[derive(Debug, Clone)]
+pub enum Declaration {
+    Players(Range<u32>),
+    Ranks(Vec<Rank>),
+    Suits(Vec<Suit>),
+    Numbers(Vec<VariableIdentifier>),
+    Order(OrderIdentifier, Vec<Rank>),
+    Pattern(patterns::PatternIdentifier, Vec<patterns::Pattern>),
+    ZoneClass(ZoneClassIdentifier, zones::ZoneClass),
+    PlayerClass(PlayerClassIdentifier, players::PlayerClass),
+    PlayerZone(VariableIdentifier, ZoneClassIdentifier),
+    SharedZone(VariableIdentifier, ZoneClassIdentifier),
+    Assignment(Vec<PlayerClassIdentifier>),
+    Start(PhaseIdentifier),
+    Phase(PhaseIdentifier, phases::Phase),
+}
+
+// Byte offset of where the declaration starts, for pointing at duplicates
+#[derive(Debug, Clone)]
+pub struct Located<T> {
+    pub start: usize,
+    pub item: T,
+}
+
+// Lines inside a zone class block, anything left out keeps its default
+#[derive(Debug, Clone)]
+pub enum ZoneSetting {
+    Owner(zones::ZoneVisibilityRule),
+    Others(zones::ZoneVisibilityRule),
+    Cleanup(zones::ZoneCleanupBehavior),
+    Rules(Vec<patterns::PatternIdentifier>),
+}
+
+#[derive(Debug, Clone)]
+pub enum PlayerSetting {
+    Zones(Vec<VariableIdentifier>),
+    Seat(players::PlayerAssignmentRule),
+}
+
+pub fn zone_class(settings: Vec<ZoneSetting>) -> zones::ZoneClass {
+    let mut class = zones::ZoneClass {
+        visibility: zones::ZoneVisibility {
+            owner: zones::ZoneVisibilityRule::Hidden,
+            others: zones::ZoneVisibilityRule::Hidden,
+        },
+        cleanup: zones::ZoneCleanupBehavior::Never,
+        rules: Vec::new(),
+    };
+    for setting in settings {
+        match setting {
+            ZoneSetting::Owner(rule) => class.visibility.owner = rule,
+            ZoneSetting::Others(rule) => class.visibility.others = rule,
+            ZoneSetting::Cleanup(cleanup) => class.cleanup = cleanup,
+            ZoneSetting::Rules(rules) => class.rules = rules,
+        }
+    }
+    class
+}
+
+pub fn player_class(settings: Vec<PlayerSetting>) -> players::PlayerClass {
+    let mut class = players::PlayerClass {
+        active_zones: Default::default(),
+        assignment_rule: players::PlayerAssignmentRule::All,
+    };
+    for setting in settings {
+        match setting {
+            PlayerSetting::Zones(zones) => class.active_zones = zones.into_iter().collect(),
+            PlayerSetting::Seat(rule) => class.assignment_rule = rule,
+        }
+    }
+    class
+}
+
+pub fn conditional(
+    condition: expressions::BooleanExpression,
+    go_true: Statement,
+    go_false: Statement,
+) -> Statement {
+    Statement::Conditional(ConditionalStatement {
+        condition: Box::new(condition),
+        go_true: Arc::new(go_true),
+        go_false: Arc::new(go_false),
+    })
+}
+
+pub fn while_loop(condition: expressions::BooleanExpression, body: Statement) -> Statement {
+    Statement::While {
+        condition: Box::new(condition),
+        r#do: Arc::new(body),
+    }
+}
+
+// Strips the quotes off a string literal or quoted name and resolves its escapes
+pub fn unquote(literal: &str) -> String {
+    let mut out = String::with_capacity(literal.len());
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => (),
+        }
+    }
+    out
+}
@@ -0,0 +1,312 @@
+use super::printer;
+use crate::engine::core::interpreter::config::GameConfig;
+use crate::engine::core::interpreter::lang::statements::{Offer, Statement};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::mem::discriminant;
+use ts_rs::TS;
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[ts(export)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+// Everything outside the phases, before and after are the declaration as ruleset text
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct DeclarationChange {
+    pub declaration: String,
+    pub change: Change,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+// Path steps are "statement 2", "then", "else", "do" and "case \"<message>\""
+// Statement numbers count from 1 in the version the statement is in
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct StatementChange {
+    pub path: Vec<String>,
+    pub change: Change,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+// Added and removed phases have no statement changes, the whole phase is in or out
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct PhaseDiff {
+    pub phase: String,
+    pub change: Change,
+    pub statements: Vec<StatementChange>,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct ConfigDiff {
+    pub declarations: Vec<DeclarationChange>,
+    pub phases: Vec<PhaseDiff>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.declarations.is_empty() && self.phases.is_empty()
+    }
+}
+
+// Compares two configs through their text form, so anything that prints the same is unchanged
+pub fn diff_configs(before: &GameConfig, after: &GameConfig) -> ConfigDiff {
+    let old: Vec<_> = printer::declarations(before)
+        .into_iter()
+        .filter(|d| !d.is_phase())
+        .collect();
+    let new: Vec<_> = printer::declarations(after)
+        .into_iter()
+        .filter(|d| !d.is_phase())
+        .collect();
+
+    let mut declarations = Vec::new();
+    for declaration in old.iter() {
+        let change = match new.iter().find(|d| d.key == declaration.key) {
+            None => Change::Removed,
+            Some(now) if now.text != declaration.text => Change::Changed,
+            Some(_) => continue,
+        };
+        declarations.push(DeclarationChange {
+            declaration: declaration.key.clone(),
+            change,
+            before: Some(declaration.text.clone()),
+            after: new
+                .iter()
+                .find(|d| d.key == declaration.key)
+                .map(|d| d.text.clone()),
+        });
+    }
+    for declaration in new.iter() {
+        if !old.iter().any(|d| d.key == declaration.key) {
+            declarations.push(DeclarationChange {
+                declaration: declaration.key.clone(),
+                change: Change::Added,
+                before: None,
+                after: Some(declaration.text.clone()),
+            });
+        }
+    }
+
+    let names: BTreeSet<&String> = before.phases.keys().chain(after.phases.keys()).collect();
+    let mut phases = Vec::new();
+    for name in names {
+        let (change, statements) = match (before.phases.get(name), after.phases.get(name)) {
+            (Some(old), Some(new)) => {
+                let mut statements = Vec::new();
+                diff_statement(
+                    &mut Vec::new(),
+                    &old.evaluate,
+                    &new.evaluate,
+                    &mut statements,
+                );
+                if statements.is_empty() {
+                    continue;
+                }
+                (Change::Changed, statements)
+            }
+            (Some(_), None) => (Change::Removed, Vec::new()),
+            (None, _) => (Change::Added, Vec::new()),
+        };
+        phases.push(PhaseDiff {
+            phase: name.clone(),
+            change,
+            statements,
+        });
+    }
+
+    ConfigDiff {
+        declarations,
+        phases,
+    }
+}
+
+fn diff_statement(
+    path: &mut Vec<String>,
+    before: &Statement,
+    after: &Statement,
+    out: &mut Vec<StatementChange>,
+) {
+    let (old_text, new_text) = (
+        printer::print_statement(before),
+        printer::print_statement(after),
+    );
+    if old_text == new_text {
+        return;
+    }
+
+    // Compound statements whose own parts match are descended into, the rest change as a whole
+    match (before, after) {
+        (Statement::Block(old), Statement::Block(new)) => {
+            let old: Vec<&Statement> = old.iter().map(|s| s.as_ref()).collect();
+            let new: Vec<&Statement> = new.iter().map(|s| s.as_ref()).collect();
+            return diff_block(path, &old, &new, out);
+        }
+        (Statement::Conditional(old), Statement::Conditional(new))
+            if same(&old.condition, &new.condition) =>
+        {
+            within(path, "then", |path| {
+                diff_statement(path, &old.go_true, &new.go_true, out)
+            });
+            within(path, "else", |path| {
+                diff_statement(path, &old.go_false, &new.go_false, out)
+            });
+            return;
+        }
+        (
+            Statement::While {
+                condition: old_condition,
+                r#do: old_body,
+            },
+            Statement::While {
+                condition: new_condition,
+                r#do: new_body,
+            },
+        ) if same(old_condition, new_condition) => {
+            within(path, "do", |path| {
+                diff_statement(path, old_body, new_body, out)
+            });
+            return;
+        }
+        (Statement::Offer(old), Statement::Offer(new)) if same_offer_shape(old, new) => {
+            for (old_case, new_case) in old.cases.iter().zip(new.cases.iter()) {
+                let step = format!("case \"{}\"", new_case.message);
+                within(path, &step, |path| {
+                    diff_statement(path, &old_case.handle, &new_case.handle, out)
+                });
+            }
+            return;
+        }
+        _ => (),
+    }
+    out.push(StatementChange {
+        path: path.clone(),
+        change: Change::Changed,
+        before: Some(old_text),
+        after: Some(new_text),
+    });
+}
+
+// Lines up the statements both versions share, then pairs up what's left in between
+fn diff_block(
+    path: &mut Vec<String>,
+    before: &[&Statement],
+    after: &[&Statement],
+    out: &mut Vec<StatementChange>,
+) {
+    let old: Vec<String> = before.iter().map(|s| printer::print_statement(s)).collect();
+    let new: Vec<String> = after.iter().map(|s| printer::print_statement(s)).collect();
+
+    // common[i][j] is the longest common run of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            pair_up(path, before, after, &removed, &added, out);
+            removed.clear();
+            added.clear();
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    pair_up(path, before, after, &removed, &added, out);
+}
+
+// Statements replaced by the same kind of statement count as changed, anything else is a removal
+// and an addition
+fn pair_up(
+    path: &mut Vec<String>,
+    before: &[&Statement],
+    after: &[&Statement],
+    removed: &[usize],
+    added: &[usize],
+    out: &mut Vec<StatementChange>,
+) {
+    let paired = removed.len().min(added.len());
+    for (old, new) in removed.iter().zip(added.iter()) {
+        let (old_statement, new_statement) = (before[*old], after[*new]);
+        let step = format!("statement {}", new + 1);
+        if discriminant(old_statement) == discriminant(new_statement) {
+            within(path, &step, |path| {
+                diff_statement(path, old_statement, new_statement, out)
+            });
+        } else {
+            within(path, &step, |path| {
+                out.push(StatementChange {
+                    path: path.clone(),
+                    change: Change::Changed,
+                    before: Some(printer::print_statement(old_statement)),
+                    after: Some(printer::print_statement(new_statement)),
+                })
+            });
+        }
+    }
+    for old in removed.iter().skip(paired) {
+        within(path, &format!("statement {}", old + 1), |path| {
+            out.push(StatementChange {
+                path: path.clone(),
+                change: Change::Removed,
+                before: Some(printer::print_statement(before[*old])),
+                after: None,
+            })
+        });
+    }
+    for new in added.iter().skip(paired) {
+        within(path, &format!("statement {}", new + 1), |path| {
+            out.push(StatementChange {
+                path: path.clone(),
+                change: Change::Added,
+                before: None,
+                after: Some(printer::print_statement(after[*new])),
+            })
+        });
+    }
+}
+
+fn within(path: &mut Vec<String>, step: &str, f: impl FnOnce(&mut Vec<String>)) {
+    path.push(step.to_string());
+    f(path);
+    path.pop();
+}
+
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+// Offers with the same cases apart from what the handlers do
+fn same_offer_shape(old: &Offer, new: &Offer) -> bool {
+    old.player_name == new.player_name
+        && same(&old.offer_to, &new.offer_to)
+        && old.cases.len() == new.cases.len()
+        && old.cases.iter().zip(new.cases.iter()).all(|(a, b)| {
+            a.message == b.message
+                && same(&a.condition, &b.condition)
+                && same(&a.choices, &b.choices)
+        })
+}
@@ -0,0 +1,235 @@
+// Text form of a ruleset, parsed by the lalrpop grammar in ast_raw.lalrpop
+pub mod ast;
+pub mod diff;
+pub mod printer;
+
+use super::interpreter::config::GameConfig;
+use crate::ast_raw;
+use ast::Declaration;
+
+use lalrpop_util::ParseError;
+use lalrpop_util::lexer::Token;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+#[error("Line {line}, column {column}: {message}")]
+pub struct DslError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl DslError {
+    fn at(text: &str, offset: usize, message: String) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        Self {
+            line,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+}
+
+pub fn parse_config(text: &str) -> Result<GameConfig, DslError> {
+    let declarations = ast_raw::FileParser::new()
+        .parse(text)
+        .map_err(|e| describe_parse_error(text, e))?;
+
+    let mut config = GameConfig::blank();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut start = None;
+    for declaration in declarations {
+        let offset = declaration.start;
+        // Settings can only be given once and names only declared once per kind
+        let key = match &declaration.item {
+            Declaration::Players(_) => "players".to_string(),
+            Declaration::Ranks(_) => "ranks".to_string(),
+            Declaration::Suits(_) => "suits".to_string(),
+            Declaration::Numbers(_) => "numbers".to_string(),
+            Declaration::Assignment(_) => "assignment".to_string(),
+            Declaration::Start(_) => "start".to_string(),
+            Declaration::Order(name, _) => format!("order '{name}'"),
+            Declaration::Pattern(name, _) => format!("pattern '{name}'"),
+            Declaration::ZoneClass(name, _) => format!("zone class '{name}'"),
+            Declaration::PlayerClass(name, _) => format!("player class '{name}'"),
+            Declaration::PlayerZone(name, _) => format!("player zone '{name}'"),
+            Declaration::SharedZone(name, _) => format!("zone '{name}'"),
+            Declaration::Phase(name, _) => format!("phase '{name}'"),
+        };
+        if let Some(first) = seen.get(&key) {
+            let first = DslError::at(text, *first, String::new());
+            return Err(DslError::at(
+                text,
+                offset,
+                format!("{key} was already given on line {}", first.line),
+            ));
+        }
+        seen.insert(key, offset);
+
+        match declaration.item {
+            Declaration::Players(range) => config.player_range = range,
+            Declaration::Ranks(ranks) => config.allowed_ranks = ranks.into_iter().collect(),
+            Declaration::Suits(suits) => config.allowed_suits = suits.into_iter().collect(),
+            Declaration::Numbers(numbers) => config.numbers = numbers.into_iter().collect(),
+            Declaration::Assignment(classes) => config.player_assignment = classes,
+            Declaration::Start(phase) => start = Some(phase),
+            Declaration::Order(name, ranks) => {
+                config.orders.insert(
+                    name,
+                    crate::engine::core::types::rank_order::RankOrder::new(ranks),
+                );
+            }
+            Declaration::Pattern(name, patterns) => {
+                config.patterns.insert(name, patterns);
+            }
+            Declaration::ZoneClass(name, class) => {
+                config.zone_classes.insert(name, class);
+            }
+            Declaration::PlayerClass(name, class) => {
+                config.player_classes.insert(name, class);
+            }
+            Declaration::PlayerZone(name, class) => {
+                config.player_zones.insert(name, class);
+            }
+            Declaration::SharedZone(name, class) => {
+                config.initial_zones.insert(name, class);
+            }
+            Declaration::Phase(name, phase) => {
+                config.phases.insert(name, phase);
+            }
+        }
+    }
+
+    config.initial_phase = start.ok_or_else(|| {
+        DslError::at(
+            text,
+            text.len(),
+            "No starting phase, add 'start <phase>;'".into(),
+        )
+    })?;
+    Ok(config)
+}
+
+fn describe_parse_error(
+    text: &str,
+    error: ParseError<usize, Token<'_>, (usize, &'static str)>,
+) -> DslError {
+    match error {
+        ParseError::InvalidToken { location } => {
+            DslError::at(text, location, "Unrecognised character".into())
+        }
+        ParseError::UnrecognizedEof { location, expected } => DslError::at(
+            text,
+            location,
+            format!(
+                "File ended early, expected {}",
+                describe_expected(&expected)
+            ),
+        ),
+        ParseError::UnrecognizedToken {
+            token: (start, token, _),
+            expected,
+        } => DslError::at(
+            text,
+            start,
+            format!(
+                "Unexpected '{}', expected {}",
+                token.1,
+                describe_expected(&expected)
+            ),
+        ),
+        ParseError::ExtraToken {
+            token: (start, token, _),
+        } => DslError::at(text, start, format!("Unexpected '{}'", token.1)),
+        ParseError::User {
+            error: (location, message),
+        } => DslError::at(text, location, message.into()),
+    }
+}
+
+// lalrpop lists terminals as they're written in the grammar, quotes and all
+fn describe_expected(expected: &[String]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for terminal in expected {
+        let name = match terminal.trim_matches('"') {
+            "NAME" | "QUOTED_NAME" => "a name".to_string(),
+            "NUMBER" => "a number".to_string(),
+            "STRING" => "a quoted message".to_string(),
+            word => format!("'{word}'"),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.len() {
+        0 => "nothing more".into(),
+        1 => names.remove(0),
+        _ => {
+            let last = names.pop().unwrap_or_default();
+            format!("one of {} or {last}", names.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> DslError {
+        parse_config(text).expect_err("ruleset should be rejected")
+    }
+
+    fn at(error: &DslError) -> (usize, usize) {
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn unexpected_token_points_at_the_token() {
+        let e = error("start main;\nphase main {\n    deal 3 to deck;\n}\n");
+        assert_eq!(at(&e), (3, 12));
+        assert!(e.message.starts_with("Unexpected 'to'"), "{}", e.message);
+    }
+
+    #[test]
+    fn unrecognised_character_is_reported_where_it_is() {
+        let e = error("start main;\nphase main pass;\n  # oops\n");
+        assert_eq!(at(&e), (3, 3));
+        assert_eq!(e.message, "Unrecognised character");
+    }
+
+    #[test]
+    fn early_end_is_reported_after_the_last_token() {
+        let e = error("start main;\nphase main {\n    pass;\n");
+        assert_eq!(at(&e), (3, 10));
+        assert!(e.message.starts_with("File ended early"), "{}", e.message);
+    }
+
+    #[test]
+    fn oversized_number_points_at_the_number() {
+        let e = error("players 2..99999999999;\n");
+        assert_eq!(at(&e), (1, 12));
+        assert_eq!(e.message, "Number is too big");
+    }
+
+    #[test]
+    fn repeated_setting_points_at_the_second_one() {
+        let e = error("start main;\nphase main pass;\n\n  start other;\n");
+        assert_eq!(at(&e), (4, 3));
+        assert_eq!(e.message, "start was already given on line 1");
+    }
+
+    #[test]
+    fn missing_start_is_reported_at_the_end() {
+        let e = error("phase main pass;\nphase other pass;");
+        assert_eq!(at(&e), (2, 18));
+    }
+
+    #[test]
+    fn columns_count_characters_not_bytes() {
+        let e = error("start `ünï`; phase `ünï` { pass }");
+        assert_eq!(at(&e), (1, 33));
+    }
+}
@@ -0,0 +1,773 @@
+use crate::engine::core::interpreter::config::GameConfig;
+use crate::engine::core::interpreter::lang::{expressions::*, statements::*};
+use crate::engine::core::types::{patterns, players, ranks::Rank, suits::Suit, zones};
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+
+// Words the grammar uses, names spelled like these are written in backticks
+// Keep in sync with the literals in ast_raw.lalrpop
+const KEYWORDS: &[&str] = &[
+    "Ace",
+    "Clubs",
+    "Diamonds",
+    "Eight",
+    "Five",
+    "Four",
+    "Hearts",
+    "Jack",
+    "King",
+    "Nine",
+    "Queen",
+    "Seven",
+    "Six",
+    "Spades",
+    "Ten",
+    "Three",
+    "Two",
+    "advance",
+    "all",
+    "among",
+    "any",
+    "as",
+    "assignment",
+    "bottom",
+    "broadcast",
+    "card",
+    "cards",
+    "case",
+    "choices",
+    "class",
+    "cleanup",
+    "consecutive",
+    "count",
+    "current",
+    "deal",
+    "declare",
+    "else",
+    "empty",
+    "enter",
+    "every",
+    "false",
+    "for",
+    "from",
+    "generate",
+    "handle",
+    "hidden",
+    "if",
+    "in",
+    "into",
+    "is",
+    "matches",
+    "move",
+    "never",
+    "numbers",
+    "of",
+    "offer",
+    "only",
+    "order",
+    "others",
+    "owner",
+    "pass",
+    "pattern",
+    "phase",
+    "player",
+    "players",
+    "rank",
+    "ranks",
+    "rules",
+    "set",
+    "shuffle",
+    "start",
+    "suit",
+    "suits",
+    "to",
+    "top",
+    "true",
+    "visible",
+    "when",
+    "while",
+    "winner",
+    "zone",
+    "zones",
+];
+
+const INDENT: &str = "    ";
+
+// One top level item of the text, key names what it declares so two configs can be lined up
+pub struct PrintedDeclaration {
+    pub key: String,
+    pub text: String,
+    section: Section,
+}
+
+impl PrintedDeclaration {
+    pub fn is_phase(&self) -> bool {
+        self.section == Section::Phases
+    }
+}
+
+// Declarations in different sections, and each multi line block, get a blank line between them
+#[derive(PartialEq, Clone, Copy)]
+enum Section {
+    Settings,
+    Orders,
+    Patterns,
+    ZoneClasses,
+    PlayerClasses,
+    Zones,
+    Phases,
+}
+
+// Renders a config as ruleset text, parse_config gives back the same config
+// Everything kept in maps and sets is written sorted so the same config always prints the same
+pub fn print_config(config: &GameConfig) -> String {
+    let mut out = String::new();
+    let mut previous = None;
+    for declaration in declarations(config) {
+        let block = matches!(
+            declaration.section,
+            Section::ZoneClasses | Section::PlayerClasses | Section::Phases
+        );
+        if previous.is_some_and(|section| section != declaration.section || block) {
+            out.push('\n');
+        }
+        out.push_str(&declaration.text);
+        out.push('\n');
+        previous = Some(declaration.section);
+    }
+    out
+}
+
+pub fn declarations(config: &GameConfig) -> Vec<PrintedDeclaration> {
+    let mut printed = Vec::new();
+    let mut add = |section: Section, key: String, text: String| {
+        printed.push(PrintedDeclaration { key, text, section })
+    };
+
+    let range = &config.player_range;
+    let players = format!("players {}..{};", range.start, range.end);
+    add(Section::Settings, "players".into(), players);
+    if !config.allowed_ranks.is_empty() {
+        let ranks = Rank::all()
+            .into_iter()
+            .filter(|r| config.allowed_ranks.contains(r));
+        let ranks = format!("ranks {};", list(ranks.map(|r| word(&r))));
+        add(Section::Settings, "ranks".into(), ranks);
+    }
+    if !config.allowed_suits.is_empty() {
+        let suits = Suit::all()
+            .into_iter()
+            .filter(|s| config.allowed_suits.contains(s));
+        let suits = format!("suits {};", list(suits.map(|s| word(&s))));
+        add(Section::Settings, "suits".into(), suits);
+    }
+    if !config.numbers.is_empty() {
+        let mut numbers: Vec<&String> = config.numbers.iter().collect();
+        numbers.sort();
+        let numbers = format!("numbers {};", list(numbers.into_iter().map(|n| name(n))));
+        add(Section::Settings, "numbers".into(), numbers);
+    }
+
+    for (order_name, order) in sorted(&config.orders) {
+        let ranks = order.ranks().iter().map(word);
+        let text = format!("order {} = {};", name(order_name), list(ranks));
+        add(Section::Orders, format!("order {order_name}"), text);
+    }
+    for (pattern_name, patterns) in sorted(&config.patterns) {
+        let patterns = patterns.iter().map(pattern);
+        let text = format!("pattern {} = {};", name(pattern_name), list(patterns));
+        add(Section::Patterns, format!("pattern {pattern_name}"), text);
+    }
+
+    for (class_name, class) in sorted(&config.zone_classes) {
+        let mut text = format!("zone class {} {{\n", name(class_name));
+        let _ = writeln!(
+            text,
+            "{INDENT}owner {};",
+            visibility(&class.visibility.owner)
+        );
+        let _ = writeln!(
+            text,
+            "{INDENT}others {};",
+            visibility(&class.visibility.others)
+        );
+        let cleanup = match class.cleanup {
+            zones::ZoneCleanupBehavior::Never => "never",
+            zones::ZoneCleanupBehavior::OnEmpty => "when empty",
+        };
+        let _ = writeln!(text, "{INDENT}cleanup {cleanup};");
+        if !class.rules.is_empty() {
+            let rules = class.rules.iter().map(|r| name(r));
+            let _ = writeln!(text, "{INDENT}rules {};", list(rules));
+        }
+        text.push('}');
+        add(
+            Section::ZoneClasses,
+            format!("zone class {class_name}"),
+            text,
+        );
+    }
+    for (class_name, class) in sorted(&config.player_classes) {
+        let mut text = format!("player class {} {{\n", name(class_name));
+        if !class.active_zones.is_empty() {
+            let mut zones: Vec<&String> = class.active_zones.iter().collect();
+            zones.sort();
+            let zones = zones.into_iter().map(|z| name(z));
+            let _ = writeln!(text, "{INDENT}zones {};", list(zones));
+        }
+        match class.assignment_rule {
+            players::PlayerAssignmentRule::All => {
+                let _ = writeln!(text, "{INDENT}for all players;");
+            }
+            players::PlayerAssignmentRule::Index(idx) => {
+                let _ = writeln!(text, "{INDENT}for player {idx};");
+            }
+        }
+        text.push('}');
+        add(
+            Section::PlayerClasses,
+            format!("player class {class_name}"),
+            text,
+        );
+    }
+
+    for (zone_name, class) in sorted(&config.player_zones) {
+        let text = format!("player zone {}: {};", name(zone_name), name(class));
+        add(Section::Zones, format!("player zone {zone_name}"), text);
+    }
+    for (zone_name, class) in sorted(&config.initial_zones) {
+        let text = format!("zone {}: {};", name(zone_name), name(class));
+        add(Section::Zones, format!("zone {zone_name}"), text);
+    }
+    if !config.player_assignment.is_empty() {
+        let classes = config.player_assignment.iter().map(|c| name(c));
+        let text = format!("assignment {};", list(classes));
+        add(Section::Zones, "assignment".into(), text);
+    }
+    let start = format!("start {};", name(&config.initial_phase));
+    add(Section::Zones, "start".into(), start);
+
+    for (phase_name, phase) in sorted(&config.phases) {
+        let mut text = format!("phase {}", name(phase_name));
+        body(&mut text, &phase.evaluate, 0, false);
+        add(Section::Phases, format!("phase {phase_name}"), text);
+    }
+    printed
+}
+
+// A statement on its own, as it would be written at the top level of a phase
+pub fn print_statement(statement: &Statement) -> String {
+    let mut out = String::new();
+    write_statement(&mut out, statement, 0, false);
+    out
+}
+
+fn sorted<T>(map: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut entries: Vec<(&String, &T)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+// Ranks and suits are written the way they're spelled in the enums
+fn word<T: Debug>(value: &T) -> String {
+    format!("{value:?}")
+}
+
+fn name(name: &str) -> String {
+    let mut chars = name.chars();
+    let plain = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    };
+    if plain && !KEYWORDS.contains(&name) {
+        name.to_string()
+    } else {
+        quoted(name, '`')
+    }
+}
+
+fn string(text: &str) -> String {
+    quoted(text, '"')
+}
+
+// Names and strings escape the same way, unquote undoes it
+fn quoted(text: &str, quote: char) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push(quote);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+fn visibility(rule: &zones::ZoneVisibilityRule) -> &'static str {
+    match rule {
+        zones::ZoneVisibilityRule::Visible => "visible",
+        zones::ZoneVisibilityRule::Hidden => "hidden",
+        zones::ZoneVisibilityRule::Top => "top",
+        zones::ZoneVisibilityRule::Bottom => "bottom",
+    }
+}
+
+fn pattern(pattern: &patterns::Pattern) -> String {
+    match pattern {
+        patterns::Pattern::Relation(patterns::Relation::Consecutive(order)) => {
+            format!("consecutive {}", name(order))
+        }
+        patterns::Pattern::Suit(pieces) => format!("suits({})", list(pieces.iter().map(piece))),
+        patterns::Pattern::Rank(pieces) => format!("ranks({})", list(pieces.iter().map(piece))),
+    }
+}
+
+fn piece<T: Debug>(piece: &patterns::PatternPiece<Option<T>>) -> String {
+    let count = if piece.match_min == piece.match_max {
+        piece.match_min.to_string()
+    } else {
+        format!("{}..={}", piece.match_min, piece.match_max)
+    };
+    match &piece.pattern {
+        Some(value) => format!("{count} {}", word(value)),
+        None => format!("{count} any"),
+    }
+}
+
+// Writes a statement that follows a keyword, blocks open on the same line and anything else
+// goes on its own line one level in. closed is set when an else is about to follow
+fn body(out: &mut String, statement: &Statement, depth: usize, closed: bool) {
+    if let Statement::Block(_) = statement {
+        out.push(' ');
+    } else {
+        out.push('\n');
+        indent(out, depth + 1);
+    }
+    let depth = match statement {
+        Statement::Block(_) => depth,
+        _ => depth + 1,
+    };
+    write_statement(out, statement, depth, closed);
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+// Ends right after the statement, the caller writes whatever comes next
+fn write_statement(out: &mut String, statement: &Statement, depth: usize, closed: bool) {
+    match statement {
+        Statement::Block(statements) => {
+            out.push('{');
+            for statement in statements {
+                out.push('\n');
+                indent(out, depth + 1);
+                write_statement(out, statement, depth + 1, false);
+            }
+            out.push('\n');
+            indent(out, depth);
+            out.push('}');
+        }
+        Statement::Conditional(conditional) => {
+            let _ = write!(out, "if {}", boolean(&conditional.condition));
+            let has_else = closed || !matches!(*conditional.go_false, Statement::Empty);
+            body(out, &conditional.go_true, depth, has_else);
+            if !has_else {
+                return;
+            }
+            if let Statement::Block(_) = *conditional.go_true {
+                out.push(' ');
+            } else {
+                out.push('\n');
+                indent(out, depth);
+            }
+            out.push_str("else");
+            if let Statement::Conditional(_) = *conditional.go_false {
+                out.push(' ');
+                write_statement(out, &conditional.go_false, depth, closed);
+            } else {
+                body(out, &conditional.go_false, depth, closed);
+            }
+        }
+        Statement::While { condition, r#do } => {
+            let _ = write!(out, "while {}", boolean(condition));
+            body(out, r#do, depth, closed);
+        }
+        Statement::Offer(offer) => write_offer(out, offer, depth),
+        simple => {
+            out.push_str(&simple_statement(simple));
+            out.push(';');
+        }
+    }
+}
+
+fn simple_statement(statement: &Statement) -> String {
+    match statement {
+        Statement::Empty => "pass".into(),
+        Statement::Broadcast { msg, to } => {
+            format!("broadcast {} to {}", string(msg), player_collection(to))
+        }
+        Statement::DeclareWinner(players) => {
+            format!("declare winner {}", player_collection(players))
+        }
+        Statement::SetNumber { name: var, value } => {
+            format!("set {} = {}", name(var), number(value))
+        }
+        Statement::AdvancePlayerStateByType {
+            to_advance,
+            type_name,
+        } => format!("advance {} among {}", number(to_advance), name(type_name)),
+        Statement::AdvancePlayerState(to_advance) => format!("advance {}", number(to_advance)),
+        Statement::MoveCardsTo { source, dest } => {
+            format!("move {} to {}", card_collection(source), zone(dest))
+        }
+        Statement::GenerateCards { cards, dest } => match **cards {
+            CardSetExpression::AllAllowed => format!("generate all cards into {}", zone(dest)),
+        },
+        Statement::Deal {
+            num_cards,
+            source,
+            dest,
+        } => format!(
+            "deal {} from {} to {}",
+            number(num_cards),
+            zone(source),
+            zone_collection(dest)
+        ),
+        Statement::Shuffle(zones) => format!("shuffle {}", zone_collection(zones)),
+        Statement::EnterPhase(phase) => format!("enter {}", name(phase)),
+        // Compound statements are written by write_statement
+        Statement::Block(_)
+        | Statement::Conditional(_)
+        | Statement::While { .. }
+        | Statement::Offer(_) => String::new(),
+    }
+}
+
+fn write_offer(out: &mut String, offer: &Offer, depth: usize) {
+    let _ = write!(out, "offer to {}", player_collection(&offer.offer_to));
+    if let Some(player_name) = &offer.player_name {
+        let _ = write!(out, " as {}", name(player_name));
+    }
+    out.push_str(" {");
+    for case in offer.cases.iter() {
+        out.push('\n');
+        indent(out, depth + 1);
+        let _ = write!(out, "case {}", string(&case.message));
+        if let Some(condition) = &case.condition {
+            let _ = write!(out, " only if {}", boolean(condition));
+        }
+        if !case.choices.is_empty() {
+            let choices = case.choices.iter().map(choice);
+            let _ = write!(out, " choices {}", list(choices));
+        }
+        out.push_str(" handle");
+        body(out, &case.handle, depth + 1, false);
+    }
+    out.push('\n');
+    indent(out, depth);
+    out.push('}');
+}
+
+fn choice(choice: &OfferChoice) -> String {
+    match choice {
+        OfferChoice::Selection(selection) => {
+            let var = name(&selection.name);
+            match &selection.choice_type {
+                ChoiceSelectionEnum::Player(from) => {
+                    format!("player {var} from {}", player_collection(from))
+                }
+                ChoiceSelectionEnum::PlayerSelection(from) => {
+                    format!("players {var} from {}", player_collection(from))
+                }
+                ChoiceSelectionEnum::Card(from) => {
+                    format!("card {var} from {}", card_collection(from))
+                }
+                ChoiceSelectionEnum::CardSelection(from) => {
+                    format!("cards {var} from {}", card_collection(from))
+                }
+            }
+        }
+        OfferChoice::Action(ChoiceAction::MoveCards { from, to }) => {
+            format!("move from {} to {}", zone(from), zone(to))
+        }
+    }
+}
+
+fn boolean(expr: &BooleanExpression) -> String {
+    match expr {
+        BooleanExpression::Literal(value) => value.to_string(),
+        BooleanExpression::Comparison { a, compared_to, b } => {
+            let op = match compared_to {
+                Comparison::LT => "<",
+                Comparison::GT => ">",
+                Comparison::LTE => "<=",
+                Comparison::GTE => ">=",
+                Comparison::EQ => "==",
+                Comparison::NEQ => "!=",
+            };
+            format!("{} {op} {}", number(a), number(b))
+        }
+        BooleanExpression::PlayerIsType {
+            player: p,
+            type_name,
+        } => {
+            format!("{} is {}", player(p), name(type_name))
+        }
+        BooleanExpression::MatchesPattern { cards, pattern } => {
+            format!("{} matches {}", card_collection(cards), name(pattern))
+        }
+    }
+}
+
+fn number(expr: &NumberExpression) -> String {
+    match expr {
+        NumberExpression::Literal(value) => value.to_string(),
+        NumberExpression::GetVariable(var) => name(var),
+        NumberExpression::CardsIn(cards) => format!("count {}", card_collection(cards)),
+    }
+}
+
+fn player(expr: &PlayerExpression) -> String {
+    match expr {
+        PlayerExpression::CurrentPlayer => "current player".into(),
+        PlayerExpression::GetVariable(var) => name(var),
+    }
+}
+
+fn player_collection(expr: &PlayerCollectionExpression) -> String {
+    match expr {
+        PlayerCollectionExpression::AllPlayers => "all players".into(),
+        PlayerCollectionExpression::Single(single) => match **single {
+            PlayerExpression::CurrentPlayer => "current player".into(),
+            PlayerExpression::GetVariable(ref var) => format!("player {}", name(var)),
+        },
+        PlayerCollectionExpression::GetVariable(var) => name(var),
+    }
+}
+
+fn zone(expr: &ZoneExpression) -> String {
+    match expr {
+        ZoneExpression::GetVariable(var) => name(var),
+        ZoneExpression::OwnedByPlayer {
+            player: owner,
+            zone_name,
+        } => format!("{} of {}", name(zone_name), player(owner)),
+    }
+}
+
+fn zone_collection(expr: &ZoneCollectionExpression) -> String {
+    match expr {
+        ZoneCollectionExpression::Single(single) => format!("zone {}", zone(single)),
+        ZoneCollectionExpression::OfType(class) => format!("every {}", name(class)),
+        ZoneCollectionExpression::GetVariable(var) => name(var),
+    }
+}
+
+fn card_collection(expr: &CardCollectionExpression) -> String {
+    match expr {
+        CardCollectionExpression::GetVariable(var) => name(var),
+        CardCollectionExpression::Single(single) => format!("card {}", card(single)),
+        CardCollectionExpression::AllInZone(z) => format!("all in {}", zone(z)),
+        CardCollectionExpression::TopInZone(z) => format!("top of {}", zone(z)),
+        CardCollectionExpression::BottomInZone(z) => format!("bottom of {}", zone(z)),
+        CardCollectionExpression::InZoneMatchingSuit { zone: z, suit: s } => {
+            format!("{} in {}", suit(s), zone(z))
+        }
+        CardCollectionExpression::InZoneMatchingRank { zone: z, rank: r } => {
+            format!("{} in {}", rank(r), zone(z))
+        }
+        CardCollectionExpression::InZoneMatchingPattern { zone: z, pattern } => {
+            format!("pattern {} in {}", name(pattern), zone(z))
+        }
+    }
+}
+
+fn card(expr: &CardExpression) -> String {
+    match expr {
+        CardExpression::GetVariable(var) => name(var),
+        CardExpression::Create(s, r) => format!("{} of {}", rank(r), suit(s)),
+    }
+}
+
+// Cards after "rank of" and "suit of" are bracketed unless they're a variable
+fn card_atom(expr: &CardExpression) -> String {
+    match expr {
+        CardExpression::GetVariable(var) => name(var),
+        CardExpression::Create(..) => format!("({})", card(expr)),
+    }
+}
+
+fn suit(expr: &SuitExpression) -> String {
+    match expr {
+        SuitExpression::Literal(s) => word(s),
+        SuitExpression::FromCard(c) => format!("suit of {}", card_atom(c)),
+    }
+}
+
+fn rank(expr: &RankExpression) -> String {
+    match expr {
+        RankExpression::Literal(r) => word(r),
+        RankExpression::FromCard(c) => format!("rank of {}", card_atom(c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl::parse_config;
+    use crate::engine::core::interpreter::lang::phases::Phase;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    // Every declaration, statement and expression the grammar has, at least once
+    const EVERYTHING: &str = r#"
+        players 2..5;
+        ranks Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        suits Hearts, Diamonds, Spades, Clubs;
+        numbers score, `round number`, `odd \` name\nsplit`;
+        order high = Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        pattern run = consecutive high;
+        pattern mixed = suits(2 Hearts, 1..=3 any), ranks(1 Ace, 0..=2 any);
+        zone class Hand { owner visible; others hidden; cleanup never; }
+        zone class Pile { owner top; others bottom; cleanup when empty; rules run, mixed; }
+        player class Dealer { zones hand; for player 0; }
+        player class Other { zones hand, tricks; for all players; }
+        player zone hand: Hand;
+        player zone tricks: Pile;
+        zone deck: Pile;
+        zone `discard pile`: Pile;
+        assignment Dealer, Other;
+        start setup;
+
+        phase setup {
+            pass;
+            generate all cards into deck;
+            shuffle zone deck;
+            shuffle every Pile;
+            shuffle piles;
+            deal 5 from deck to every Hand;
+            set score = -3;
+            set `round number` = count all in deck;
+            set `odd \` name\nsplit` = score;
+            broadcast "Dealt, \"good\" luck\n\tall" to all players;
+            enter `main loop`;
+        }
+
+        phase `main loop` {
+            if true enter setup;
+            if false pass; else {
+                advance 1;
+                advance score among Other;
+            }
+            while score < 3 set score = 1;
+            while score > 3 if score <= 1 pass; else pass;
+            if score >= 2 if score == 2 pass;
+            if score != 4 pass;
+            if current player is Dealer pass;
+            if champ is Other pass;
+            if all in hand of current player matches run pass;
+            move card Ace of Spades to deck;
+            move card picked to hand of champ;
+            move top of deck to `discard pile`;
+            move bottom of deck to deck;
+            move Hearts in deck to deck;
+            move suit of picked in deck to deck;
+            move suit of (King of Clubs) in deck to deck;
+            move Queen in deck to deck;
+            move rank of picked in deck to deck;
+            move rank of (rank of picked of suit of picked) in deck to deck;
+            move pattern mixed in deck to deck;
+            move chosen to deck;
+            declare winner current player;
+            declare winner player champ;
+            declare winner everyone;
+            offer to current player as chooser {
+                case "Pick" only if count chosen > 0 choices
+                    player target from all players,
+                    players targets from everyone,
+                    card picked from all in hand of chooser,
+                    cards chosen from top of deck,
+                    move from deck to hand of target
+                handle {
+                    broadcast "Picked" to player target;
+                }
+                case "Pass" handle pass;
+            }
+            offer to all players {
+                case "Anyone" handle enter setup;
+            }
+        }
+    "#;
+
+    // Sets are serialized in whatever order they hash, sort them so equal configs compare equal
+    fn normalized(config: &GameConfig) -> Value {
+        fn sort(value: &mut Value) {
+            if let Value::Array(items) = value {
+                items.sort_by_key(|item| item.to_string());
+            }
+        }
+        let mut value = serde_json::to_value(config).unwrap();
+        for set in ["allowed_ranks", "allowed_suits", "numbers"] {
+            sort(&mut value[set]);
+        }
+        if let Value::Object(classes) = &mut value["player_classes"] {
+            for class in classes.values_mut() {
+                sort(&mut class["active_zones"]);
+            }
+        }
+        value
+    }
+
+    fn assert_round_trips(config: &GameConfig) {
+        let text = print_config(config);
+        let parsed = parse_config(&text).unwrap_or_else(|e| panic!("{e}\n{text}"));
+        assert_eq!(normalized(&parsed), normalized(config), "\n{text}");
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        let config = parse_config(EVERYTHING).unwrap();
+        assert_round_trips(&config);
+    }
+
+    #[test]
+    fn printing_is_stable() {
+        let config = parse_config(EVERYTHING).unwrap();
+        let text = print_config(&config);
+        assert_eq!(print_config(&parse_config(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn awkward_names_are_escaped() {
+        let mut config = parse_config(EVERYTHING).unwrap();
+        let awkward = "`tick`, back\\slash\nnew line\ttab";
+        config.phases.insert(
+            awkward.to_string(),
+            Phase {
+                evaluate: Arc::new(Statement::EnterPhase(awkward.to_string())),
+            },
+        );
+        config
+            .initial_zones
+            .insert("if".to_string(), "Pile".to_string());
+        assert_round_trips(&config);
+    }
+}
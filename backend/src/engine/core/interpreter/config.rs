@@ -73,8 +73,30 @@ impl GameConfig {
     }
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum ConfigError {
     #[error("The phase '{0}' does not exist")]
     NonexistantPhase(String),
+    #[error("The player class '{0}' does not exist")]
+    NonexistantPlayerClass(String),
+    #[error("The zone class '{0}' does not exist")]
+    NonexistantZoneClass(String),
+    #[error("No player zone named '{0}' exists")]
+    NonexistantPlayerZone(String),
+    #[error("The pattern '{0}' does not exist")]
+    NonexistantPattern(String),
+    #[error("The order '{0}' does not exist")]
+    NonexistantOrder(String),
+    #[error("The number '{0}' was never declared")]
+    UndeclaredNumber(String),
+    #[error("The phase '{0}' is never entered")]
+    UnreachablePhase(String),
+    #[error("No {1} variable named '{0}' is in scope")]
+    UndefinedVariable(String, String),
+    #[error("Variable '{name}' is a {found}, expected a {expected}")]
+    MistypedVariable {
+        name: String,
+        expected: String,
+        found: String,
+    },
 }
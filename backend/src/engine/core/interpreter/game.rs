@@ -1,14 +1,22 @@
 use super::{
     config,
     lang::{expressions, phases, statements, types_instances},
-    state::{execution_state, game_state},
+    state::{event_log, execution_state, game_state, var_state},
 };
 
 use crate::engine::core::types::*;
-use execution_state::ExecutionState;
+use event_log::GameEvent;
+use execution_state::{
+    CaseView, ChoiceValue, ChoiceView, ExecutionState, OfferResponse, OfferView, PendingOffer,
+};
+use game_state::{CardID, GameZoneID, PlayerOrderIndex};
 use identifiers::*;
 use statements::Statement;
+use types_instances::TypeInstance;
 
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -26,12 +34,92 @@ pub enum FatalGameError {
     StateRuntime(#[from] game_state::StateModifyError),
     #[error("Error occured in executor")]
     ExecutorError(#[from] execution_state::ExecutionStateError),
+    #[error("Ruleset failed while running: {0}")]
+    Runtime(#[from] RuntimeError),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum RuntimeError {
+    #[error("Variable '{0}' is not defined")]
+    UndefinedVariable(String),
+    #[error("The phase '{0}' does not exist")]
+    MissingPhase(PhaseIdentifier),
+    #[error("Player {0} has no zone named '{1}'")]
+    MissingPlayerZone(PlayerOrderIndex, VariableIdentifier),
+    #[error("The order '{0}' does not exist")]
+    MissingOrder(OrderIdentifier),
+    #[error("The pattern '{0}' does not exist")]
+    MissingPattern(patterns::PatternIdentifier),
+    #[error("Couldn't look for the pattern '{0}' in so many cards: {1}")]
+    PatternSearch(patterns::PatternIdentifier, patterns::SearchExhausted),
+    #[error("Cards can only be used once they are generated into a zone")]
+    CardNotInPlay,
+    #[error("Ruleset looped forever: ran {0} statements without waiting on a player")]
+    StatementLimit(u32),
+    #[error("Ruleset looped forever: a while loop repeated {0} times without waiting on a player")]
+    RunawayWhile(u32),
+    #[error("Ruleset moved cards against a zone's rules")]
+    BrokenZoneRule(game_state::ZoneRuleViolation),
 }
 
 #[derive(Error, Debug, Clone)]
 pub enum RecoverableGameError {
     #[error("Game in wrong status")]
     WrongStatus,
+    #[error("No offer is waiting on an answer")]
+    NoPendingOffer,
+    #[error("Player {0} was not offered anything")]
+    NotOffered(PlayerOrderIndex),
+    #[error("Chosen case is not available")]
+    CaseUnavailable,
+    #[error("Invalid response to offer: {0}")]
+    InvalidResponse(String),
+    #[error("Player {player}'s move was rejected: {violation}")]
+    BrokenZoneRule {
+        player: PlayerOrderIndex,
+        violation: game_state::ZoneRuleViolation,
+    },
+}
+
+impl From<game_state::StateModifyError> for GameError {
+    fn from(e: game_state::StateModifyError) -> Self {
+        match e {
+            game_state::StateModifyError::ZoneRule(violation) => GameError::Fatal(
+                FatalGameError::Runtime(RuntimeError::BrokenZoneRule(violation)),
+            ),
+            e => GameError::Fatal(FatalGameError::StateRuntime(e)),
+        }
+    }
+}
+
+// A broken zone rule is only the player's fault while their offer's handler is running
+fn blame_player(error: GameError, player: PlayerOrderIndex) -> GameError {
+    match error {
+        GameError::Fatal(FatalGameError::Runtime(RuntimeError::BrokenZoneRule(violation))) => {
+            RecoverableGameError::BrokenZoneRule { player, violation }.into()
+        }
+        e => e,
+    }
+}
+
+impl From<execution_state::ExecutionStateError> for GameError {
+    fn from(e: execution_state::ExecutionStateError) -> Self {
+        GameError::Fatal(FatalGameError::ExecutorError(e))
+    }
+}
+
+impl From<RuntimeError> for GameError {
+    fn from(e: RuntimeError) -> Self {
+        GameError::Fatal(FatalGameError::Runtime(e))
+    }
+}
+
+fn undefined_variable(name: &str) -> GameError {
+    RuntimeError::UndefinedVariable(name.to_string()).into()
+}
+
+fn invalid_response(reason: &str) -> GameError {
+    RecoverableGameError::InvalidResponse(reason.to_string()).into()
 }
 
 pub fn state_error_to_game(state_error: game_state::StateError) -> GameError {
@@ -53,104 +141,568 @@ pub enum EngineStatus {
     Finished,
     Ready,
     Sleep(u32),
-    Broadcast(String),
+    Broadcast {
+        msg: String,
+        to: Vec<PlayerOrderIndex>,
+    },
+    Blocked, // Waiting on players to answer an offer
 }
 
-#[derive(Error, Debug)]
-pub enum ExecutionError {
-    #[error("Executor instruction state became corrupted")]
-    ExecutionState(#[from] execution_state::ExecutionStateError),
+#[derive(Debug, Clone)]
+pub struct Game {
+    config: Arc<config::GameConfig>,
+    state: game_state::GameState,
+    ex_state: ExecutionState,
+    answered: Option<Box<AnsweredOffer>>,
+    seed: u64,
+    rng: ChaCha12Rng, // Every random decision goes through this so a seed replays the same game
+    events: Vec<GameEvent>,
 }
 
-pub fn wrap_exec_state_error(e: execution_state::ExecutionStateError) -> ExecutionError {
-    ExecutionError::ExecutionState(e)
+// Kept while the handler of an answered offer runs, if the handler breaks a zone rule the game
+// goes back to waiting on the offer as if it was never answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AnsweredOffer {
+    player: PlayerOrderIndex,
+    depth: usize, // Handler is done once the stack is smaller than this
+    state: game_state::GameState,
+    ex_state: ExecutionState,
+    rng: ChaCha12Rng,
+    events: usize, // Log length before the answer
 }
 
-#[derive(Debug, Clone)]
-pub struct Game {
-    config: Arc<config::GameConfig>,
+// Everything needed to pick a running game back up except its config, which is stored with the
+// room anyway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    seed: u64,
+    rng: ChaCha12Rng,
     state: game_state::GameState,
     ex_state: ExecutionState,
+    answered: Option<Box<AnsweredOffer>>,
+    events: Vec<GameEvent>,
 }
 
 impl Game {
-    pub fn new(config: config::GameConfig) -> Self {
+    pub fn new(config: config::GameConfig, seed: u64) -> Result<Self, config::ConfigError> {
         let config_rc = Arc::new(config);
-        //TODO: return error
         let root_phase = config_rc
-            .clone()
             .phases
-            .get(&config_rc.clone().initial_phase)
-            .unwrap()
+            .get(&config_rc.initial_phase)
+            .ok_or(config::ConfigError::NonexistantPhase(
+                config_rc.initial_phase.clone(),
+            ))?
             .evaluate
             .clone();
-        Self {
+        Ok(Self {
             config: config_rc.clone(),
             state: game_state::GameState::new(config_rc),
             ex_state: ExecutionState::new(root_phase),
+            answered: None,
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            seed: self.seed,
+            rng: self.rng.clone(),
+            state: self.state.clone(),
+            ex_state: self.ex_state.clone(),
+            answered: self.answered.clone(),
+            events: self.events.clone(),
         }
     }
 
-    pub fn eval_statement(&mut self) -> Result<EngineStatus, ExecutionError> {
-        match self.ex_state.get_current_statement() {
-            None => Ok(EngineStatus::Finished),
-            Some(statement) => {
-                let mut status = EngineStatus::Ready;
-                match &*statement {
-                    Statement::Empty => status = EngineStatus::Ready,
-                    Statement::Block(_stmts) => {
-                        self.ex_state
-                            .upgrade_statement()
-                            .map_err(wrap_exec_state_error)?;
-                    }
-                    Statement::Conditional(cond_statement) => {
-                        let condition = self.evaluate_bool(&cond_statement.condition);
-                        let statement_to_execute = match condition {
-                            true => cond_statement.go_true.clone(),
-                            false => cond_statement.go_false.clone(),
-                        };
-                        self.ex_state
-                            .incr_and_push(statement_to_execute, 1)
-                            .map_err(wrap_exec_state_error)?;
+    // Config has to be the one the checkpoint was taken with
+    pub fn restore(config: config::GameConfig, checkpoint: Checkpoint) -> Self {
+        let config = Arc::new(config);
+        let mut state = checkpoint.state;
+        state.attach_config(config.clone());
+        let mut answered = checkpoint.answered;
+        if let Some(answered) = answered.as_mut() {
+            answered.state.attach_config(config.clone());
+        }
+        Self {
+            config,
+            state,
+            ex_state: checkpoint.ex_state,
+            answered,
+            seed: checkpoint.seed,
+            rng: checkpoint.rng,
+            events: checkpoint.events,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config(&self) -> &config::GameConfig {
+        &self.config
+    }
+
+    // Everything that happened so far, oldest first
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    // Whatever is running the game stopped it, keeps why in the log
+    pub fn record_crash(&mut self, error: String) {
+        self.events.push(GameEvent::Crashed { error });
+    }
+
+    pub fn eval_statement(&mut self) -> Result<EngineStatus, GameError> {
+        self.ex_state.statements_evaluated += 1;
+        if self.ex_state.statements_evaluated > self.ex_state.statement_limit {
+            return Err(self.looped_forever().into());
+        }
+
+        let result = self.step();
+        if let Ok(EngineStatus::Blocked) = result {
+            self.ex_state.reset_budget();
+        }
+        let Some(answered) = self.answered.take() else {
+            return result;
+        };
+
+        if let Err(error) = result {
+            let error = blame_player(error, answered.player);
+            if let GameError::Recoverable(RecoverableGameError::BrokenZoneRule { .. }) = error {
+                self.state = answered.state;
+                self.ex_state = answered.ex_state;
+                self.rng = answered.rng;
+                self.events.truncate(answered.events);
+            } else {
+                self.answered = Some(answered);
+            }
+            return Err(error);
+        }
+
+        // A new offer inside the handler is a new point to go back to
+        if self.ex_state.statement_stack.len() >= answered.depth
+            && self.ex_state.pending_offer.is_none()
+        {
+            self.answered = Some(answered);
+        }
+        result
+    }
+
+    // Ran out of budget without waiting on anyone, a while loop is only blamed if it's still
+    // going and most of the budget went on it, otherwise the phases are going round in circles
+    fn looped_forever(&self) -> RuntimeError {
+        let limit = self.ex_state.statement_limit;
+        let culprit = self
+            .ex_state
+            .running_loops
+            .iter()
+            .find(|running| limit - running.started_at > limit / 2);
+        match culprit {
+            Some(running) => RuntimeError::RunawayWhile(running.iterations),
+            None => RuntimeError::StatementLimit(limit),
+        }
+    }
+
+    fn step(&mut self) -> Result<EngineStatus, GameError> {
+        let statement = match self.ex_state.get_current_statement() {
+            None => {
+                if !matches!(self.state.status, game_state::GameStatus::Finished) {
+                    self.state.status = game_state::GameStatus::Finished;
+                    self.events.push(GameEvent::Finished {
+                        winners: self.state.winners.clone(),
+                    });
+                }
+                return Ok(EngineStatus::Finished);
+            }
+            Some(statement) => statement,
+        };
+
+        let mut status = EngineStatus::Ready;
+        match &*statement {
+            Statement::Empty => (),
+            Statement::Block(_stmts) => {
+                self.ex_state.enter_block()?;
+                return Ok(status);
+            }
+            Statement::Conditional(cond_statement) => {
+                let condition = self.evaluate_bool(&cond_statement.condition)?;
+                let statement_to_execute = match condition {
+                    true => cond_statement.go_true.clone(),
+                    false => cond_statement.go_false.clone(),
+                };
+                self.ex_state.incr_and_push(statement_to_execute, 1)?;
+                return Ok(status);
+            }
+            Statement::While { condition, r#do } => {
+                // Loop statement stays current until the condition fails
+                if self.evaluate_bool(condition)? {
+                    self.ex_state.loop_again();
+                    self.ex_state.push_statement(r#do.clone());
+                    return Ok(status);
+                }
+                self.ex_state.loop_finished();
+            }
+            Statement::Broadcast { msg, to } => {
+                status = EngineStatus::Broadcast {
+                    msg: msg.clone(),
+                    to: self.evaluate_player_collection(to)?,
+                };
+            }
+            Statement::DeclareWinner(players) => {
+                let winners = self.evaluate_player_collection(players)?;
+                self.state.declare_winners(winners.clone());
+                self.events.push(GameEvent::Finished { winners });
+                self.ex_state.clear();
+                return Ok(EngineStatus::Finished);
+            }
+            Statement::SetNumber { name, value } => {
+                let value = self.evaluate_number(value)?;
+                match self.ex_state.root_vars.number.get_mut(name) {
+                    Some(number) => *number = value,
+                    None => return Err(undefined_variable(name)),
+                }
+            }
+            Statement::AdvancePlayerStateByType {
+                to_advance,
+                type_name,
+            } => {
+                let delta = self.evaluate_number(to_advance)?;
+                self.state.advance_player_by_class(delta, type_name)?;
+            }
+            Statement::AdvancePlayerState(to_advance) => {
+                let delta = self.evaluate_number(to_advance)?;
+                self.state.advance_player(delta)?;
+            }
+            Statement::MoveCardsTo { source, dest } => {
+                let cards = self.evaluate_card_collection(source)?;
+                let zone = self.evaluate_zone(dest)?;
+                self.state.cards.move_cards(&cards, zone)?;
+                self.events.push(GameEvent::CardsMoved { cards, to: zone });
+            }
+            Statement::GenerateCards { cards, dest } => {
+                let zone = self.evaluate_zone(dest)?;
+                let set = self.evaluate_card_set(cards);
+                let created = self.state.cards.new_cardset(&set);
+                self.state.cards.move_cards(&created, zone)?;
+                self.events.push(GameEvent::CardsMoved {
+                    cards: created,
+                    to: zone,
+                });
+            }
+            Statement::Deal {
+                num_cards,
+                source,
+                dest,
+            } => {
+                let count = self.evaluate_number(num_cards)?;
+                let source = self.evaluate_zone(source)?;
+                let dest = self.evaluate_zone_collection(dest)?;
+                self.deal(count, source, &dest)?;
+            }
+            Statement::Shuffle(zones) => {
+                for zone in self.evaluate_zone_collection(zones)? {
+                    self.state.cards.shuffle_zone(zone, &mut self.rng)?;
+                    self.events.push(GameEvent::ZoneShuffled { zone });
+                }
+            }
+            Statement::EnterPhase(pname) => {
+                let phase = self
+                    .config
+                    .phases
+                    .get(pname)
+                    .ok_or(RuntimeError::MissingPhase(pname.clone()))?;
+                self.ex_state.enter_root(phase.evaluate.clone());
+                self.events.push(GameEvent::PhaseEntered {
+                    phase: pname.clone(),
+                });
+                return Ok(status);
+            }
+            Statement::Offer(offer) => {
+                if self.ex_state.pending_offer.is_none() {
+                    let players = self.evaluate_player_collection(&offer.offer_to)?;
+                    // No one to answer, don't wait forever
+                    if !players.is_empty() {
+                        self.ex_state.pending_offer = Some(PendingOffer {
+                            offer: offer.clone(),
+                            players,
+                        });
                     }
-                    Statement::While { condition, r#do } => {
-                        let valid = self.evaluate_bool(&condition);
-                        if valid {
-                            self.ex_state.push_statement(r#do.clone());
-                        }
+                }
+                if self.ex_state.pending_offer.is_some() {
+                    return Ok(EngineStatus::Blocked);
+                }
+            }
+        }
+
+        self.ex_state.incr_current(1)?;
+        Ok(status)
+    }
+
+    // Hand out cards one at a time to each zone, stopping early if the source runs out
+    // Dealt on a copy so a broken zone rule part way through leaves every zone untouched
+    fn deal(
+        &mut self,
+        count: types_instances::BaseNumberType,
+        source: GameZoneID,
+        dest: &[GameZoneID],
+    ) -> Result<(), GameError> {
+        let mut cards = self.state.cards.clone();
+        let mut dealt = Vec::new();
+        'dealing: for _ in 0..count.max(0) {
+            for zone in dest {
+                match cards.top_card(source)? {
+                    Some(card) => {
+                        cards.move_cards(&[card], *zone)?;
+                        dealt.push(GameEvent::CardsMoved {
+                            cards: vec![card],
+                            to: *zone,
+                        });
                     }
-                    //TODO
-                    Statement::Broadcast { msg, to } => {
-                        status = EngineStatus::Broadcast(msg.clone());
+                    None => break 'dealing,
+                }
+            }
+        }
+        self.state.cards = cards;
+        self.events.extend(dealt);
+        Ok(())
+    }
+
+    pub fn pending_offer(&self) -> Option<&PendingOffer> {
+        self.ex_state.pending_offer.as_ref()
+    }
+
+    // What the pending offer looks like to one of the players it was made to
+    pub fn describe_offer(&mut self, player: PlayerOrderIndex) -> Option<OfferView> {
+        let pending = self.ex_state.pending_offer.clone()?;
+        if !pending.players.contains(&player) {
+            return None;
+        }
+
+        let mut scope = var_state::VarMapping::new();
+        if let Some(name) = &pending.offer.player_name {
+            scope.player.insert(name.clone(), player);
+        }
+        self.ex_state.push_scope(Arc::new(Statement::Empty), scope);
+
+        let mut cases = Vec::new();
+        for (index, case) in pending.offer.cases.iter().enumerate() {
+            let available = match &case.condition {
+                Some(condition) => self.evaluate_bool(condition).unwrap_or(false),
+                None => true,
+            };
+            if available {
+                cases.push(CaseView {
+                    index,
+                    message: case.message.clone(),
+                    choices: case
+                        .choices
+                        .iter()
+                        .map(|c| self.describe_choice(c))
+                        .collect(),
+                });
+            }
+        }
+
+        self.ex_state.statement_stack.pop();
+        Some(OfferView { cases })
+    }
+
+    // Anything that fails to evaluate here needs an earlier choice bound first
+    fn describe_choice(&self, choice: &statements::OfferChoice) -> ChoiceView {
+        use statements::{ChoiceAction, ChoiceSelectionEnum, OfferChoice};
+        match choice {
+            OfferChoice::Selection(selection) => {
+                let name = selection.name.clone();
+                match &selection.choice_type {
+                    ChoiceSelectionEnum::Player(from) => ChoiceView::Player {
+                        name,
+                        options: self.evaluate_player_collection(from).ok(),
+                    },
+                    ChoiceSelectionEnum::PlayerSelection(from) => ChoiceView::Players {
+                        name,
+                        options: self.evaluate_player_collection(from).ok(),
+                    },
+                    ChoiceSelectionEnum::Card(from) => ChoiceView::Card {
+                        name,
+                        options: self.evaluate_card_collection(from).ok(),
+                    },
+                    ChoiceSelectionEnum::CardSelection(from) => ChoiceView::Cards {
+                        name,
+                        options: self.evaluate_card_collection(from).ok(),
+                    },
+                }
+            }
+            OfferChoice::Action(ChoiceAction::MoveCards { from, to }) => {
+                let from = self.evaluate_zone(from).ok();
+                ChoiceView::MoveCards {
+                    from,
+                    to: self.evaluate_zone(to).ok(),
+                    options: from.map(|zone| self.cards_in_zone(zone)),
+                }
+            }
+        }
+    }
+
+    // Runs the chosen case of the pending offer, nothing changes if the response is rejected
+    pub fn respond_to_offer(
+        &mut self,
+        player: PlayerOrderIndex,
+        response: &OfferResponse,
+    ) -> Result<(), GameError> {
+        let pending = self
+            .ex_state
+            .pending_offer
+            .clone()
+            .ok_or(RecoverableGameError::NoPendingOffer)?;
+        if !pending.players.contains(&player) {
+            return Err(RecoverableGameError::NotOffered(player).into());
+        }
+        let case = pending
+            .offer
+            .cases
+            .get(response.case)
+            .ok_or(invalid_response("case does not exist"))?;
+        if case.choices.len() != response.choices.len() {
+            return Err(invalid_response("wrong number of choices"));
+        }
+
+        let mut scope = var_state::VarMapping::new();
+        if let Some(name) = &pending.offer.player_name {
+            scope.player.insert(name.clone(), player);
+        }
+
+        // Choices are evaluated inside the handler's scope so they can see each other
+        self.ex_state.push_scope(case.handle.clone(), scope);
+        let bound = self.bind_offer_case(case, response);
+        let handler = self.ex_state.statement_stack.pop();
+        let moves = bound?;
+
+        let mut cards = self.state.cards.clone();
+        for (to_move, dest) in moves.iter() {
+            cards
+                .move_cards(to_move, *dest)
+                .map_err(|e| blame_player(e.into(), player))?;
+        }
+
+        let mut answered = AnsweredOffer {
+            player,
+            depth: 0,
+            state: self.state.clone(),
+            ex_state: self.ex_state.clone(),
+            rng: self.rng.clone(),
+            events: self.events.len(),
+        };
+        self.state.cards = cards;
+        self.events.push(GameEvent::OfferAnswered {
+            player,
+            response: response.clone(),
+        });
+        self.events.extend(
+            moves
+                .into_iter()
+                .map(|(cards, to)| GameEvent::CardsMoved { cards, to }),
+        );
+        self.ex_state.pending_offer = None;
+        self.ex_state.reset_budget();
+        self.ex_state.incr_current(1)?;
+        if let Some(handler) = handler {
+            self.ex_state.statement_stack.push(handler);
+            answered.depth = self.ex_state.statement_stack.len();
+            self.answered = Some(Box::new(answered));
+        }
+        Ok(())
+    }
+
+    // Checks and binds every choice of a case, returning card moves that still need to happen
+    fn bind_offer_case(
+        &mut self,
+        case: &statements::OfferCase,
+        response: &OfferResponse,
+    ) -> Result<Vec<(Vec<CardID>, GameZoneID)>, GameError> {
+        if let Some(condition) = &case.condition
+            && !self.evaluate_bool(condition)?
+        {
+            return Err(RecoverableGameError::CaseUnavailable.into());
+        }
+
+        let mut moves = Vec::new();
+        for (choice, value) in case.choices.iter().zip(response.choices.iter()) {
+            use statements::{ChoiceAction, OfferChoice};
+            match (choice, value) {
+                (OfferChoice::Selection(selection), value) => {
+                    self.bind_selection(selection, value)?
+                }
+                (
+                    OfferChoice::Action(ChoiceAction::MoveCards { from, to }),
+                    ChoiceValue::Cards(cards),
+                ) => {
+                    let from = self.evaluate_zone(from)?;
+                    let to = self.evaluate_zone(to)?;
+                    let available = &self.state.cards.get_zone(from)?.cards;
+                    if !cards.iter().all(|c| available.contains(c)) {
+                        return Err(invalid_response("cards are not in the source zone"));
                     }
-                    Statement::DeclareWinner(player) => todo!("impl"),
-                    Statement::SetNumber { name, value } => todo!("impl"),
-                    Statement::AdvancePlayerStateByType {
-                        to_advance,
-                        type_name,
-                    } => todo!("impl"),
-                    Statement::AdvancePlayerState(to_advance) => todo!("impl"),
-                    Statement::MoveCardsTo { source, dest } => todo!("impl"),
-
-                    Statement::GenerateCards { cards, dest } => todo!("impl"),
-                    Statement::Deal {
-                        num_cards,
-                        source,
-                        dest,
-                    } => todo!("impl"),
-
-                    Statement::Shuffle(zones) => todo!("impl"),
-                    Statement::EnterPhase(pname) => todo!("impl"),
-                    Statement::Offer(offer) => todo!("impl"),
-                    _ => todo!("impl"),
+                    moves.push((cards.clone(), to));
                 }
+                _ => return Err(invalid_response("choice answered with the wrong type")),
+            }
+        }
+        Ok(moves)
+    }
+
+    fn bind_selection(
+        &mut self,
+        selection: &statements::ChoiceSelection,
+        value: &ChoiceValue,
+    ) -> Result<(), GameError> {
+        use statements::ChoiceSelectionEnum;
+        let name = selection.name.clone();
 
-                self.ex_state
-                    .incr_current(1)
-                    .map_err(wrap_exec_state_error)?;
-                Ok(status)
+        match (&selection.choice_type, value) {
+            (ChoiceSelectionEnum::Player(from), ChoiceValue::Player(p)) => {
+                if !self.evaluate_player_collection(from)?.contains(p) {
+                    return Err(invalid_response("player can't be chosen"));
+                }
+                self.current_scope()?.player.insert(name, *p);
+            }
+            (ChoiceSelectionEnum::PlayerSelection(from), ChoiceValue::Players(ps)) => {
+                let allowed = self.evaluate_player_collection(from)?;
+                if !ps.iter().all(|p| allowed.contains(p)) {
+                    return Err(invalid_response("player can't be chosen"));
+                }
+                self.current_scope()?
+                    .player_collection
+                    .insert(name, ps.clone());
+            }
+            (ChoiceSelectionEnum::Card(from), ChoiceValue::Card(c)) => {
+                if !self.evaluate_card_collection(from)?.contains(c) {
+                    return Err(invalid_response("card can't be chosen"));
+                }
+                self.current_scope()?.card.insert(name, *c);
             }
+            (ChoiceSelectionEnum::CardSelection(from), ChoiceValue::Cards(cs)) => {
+                let allowed = self.evaluate_card_collection(from)?;
+                if !cs.iter().all(|c| allowed.contains(c)) {
+                    return Err(invalid_response("card can't be chosen"));
+                }
+                self.current_scope()?
+                    .card_collection
+                    .insert(name, cs.clone());
+            }
+            _ => return Err(invalid_response("choice answered with the wrong type")),
         }
+        Ok(())
+    }
+
+    fn current_scope(&mut self) -> Result<&mut var_state::VarMapping, GameError> {
+        Ok(self
+            .ex_state
+            .statement_stack
+            .last_mut()
+            .map(|ctx| &mut ctx.variables)
+            .ok_or(execution_state::ExecutionStateError::OutOfStatements)?)
     }
 
     pub fn get_status(&self) -> game_state::GameStatus {
@@ -172,10 +724,49 @@ impl Game {
         self.state.create_players(player_count)
     }
 
+    pub fn get_winners(&self) -> Vec<PlayerOrderIndex> {
+        self.state.winners.clone()
+    }
+
     pub fn get_roles(&self) -> Vec<PlayerClassIdentifier> {
         self.state.players.clone()
     }
 
+    pub fn visible_zones(&self, viewer: Option<PlayerOrderIndex>) -> Vec<game_state::ZoneView> {
+        self.state.cards.visible_zones(viewer)
+    }
+
+    pub fn revealed_zones(&self) -> Vec<game_state::ZoneView> {
+        self.state.cards.revealed_zones()
+    }
+
+    pub fn zone_visibility(
+        &self,
+        zone_id: GameZoneID,
+        viewer: Option<PlayerOrderIndex>,
+    ) -> zones::ZoneVisibilityRule {
+        self.state.cards.zone_visibility(zone_id, viewer)
+    }
+
+    pub fn check_card_count(&self) -> Result<(), game_state::CardCountError> {
+        self.state.cards.check_card_count()
+    }
+
+    pub fn cards_in_zone(&self, zone_id: GameZoneID) -> Vec<CardID> {
+        match self.state.cards.get_zone(zone_id) {
+            Ok(zone) => zone.cards.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn zone_of_card(&self, card_id: CardID) -> Option<GameZoneID> {
+        self.state.cards.zone_of_card(card_id)
+    }
+
+    pub fn take_card_movements(&mut self) -> game_state::CardMovements {
+        self.state.cards.take_movements()
+    }
+
     pub fn init(&mut self) -> Result<(), GameError> {
         if !self.is_ready() {
             return Err(GameError::Recoverable(RecoverableGameError::WrongStatus));
@@ -184,6 +775,9 @@ impl Game {
         self.ex_state
             .init(&*self.config, &self.state)
             .map_err(exec_error_to_game)?;
+        self.events.push(GameEvent::Started {
+            players: self.state.players.len() as u32,
+        });
 
         return Ok(());
     }
@@ -221,81 +815,1125 @@ impl Game {
         }
     }
 
-    pub fn on_reached_eval_limit(&mut self) {
-        self.throw_error("Evaluation limit reached".into());
-    }
-
-    pub fn throw_error(&mut self, reason: String) {}
-
-    pub fn evaluate_statement(&mut self, statement: &Statement) {
-        self.ex_state.statements_evaluated += 1;
-        if self.ex_state.statements_evaluated > self.ex_state.statement_limit {
-            self.on_reached_eval_limit();
-            return;
-        }
+    pub fn evaluate(&self, expr: &expressions::Expression) -> Result<TypeInstance, GameError> {
+        use expressions::Expression::*;
+        Ok(match expr {
+            CardSet(e) => TypeInstance::CardSet(self.evaluate_card_set(e)),
+            Order(e) => TypeInstance::Order(self.evaluate_order(e)?),
+            Number(e) => TypeInstance::Number(self.evaluate_number(e)?),
+            Boolean(e) => TypeInstance::Boolean(self.evaluate_bool(e)?),
+            Zone(e) => TypeInstance::Zone(self.evaluate_zone(e)?),
+            ZoneCollection(e) => TypeInstance::ZoneCollection(self.evaluate_zone_collection(e)?),
+            Player(e) => TypeInstance::Player(self.evaluate_player(e)?),
+            PlayerCollection(e) => {
+                TypeInstance::PlayerCollection(self.evaluate_player_collection(e)?)
+            }
+            Suit(e) => TypeInstance::Suit(self.evaluate_suit(e)?),
+            Rank(e) => TypeInstance::Rank(self.evaluate_rank(e)?),
+            Card(e) => TypeInstance::Card(self.evaluate_card(e)?),
+            CardCollection(e) => TypeInstance::CardCollection(self.evaluate_card_collection(e)?),
+            CardSelector(e) => TypeInstance::CardSelector(self.evaluate_card_selector(e)?),
+        })
     }
 
     pub fn evaluate_number(
         &self,
         expr: &expressions::NumberExpression,
-    ) -> types_instances::BaseNumberType {
+    ) -> Result<types_instances::BaseNumberType, GameError> {
         use expressions::NumberExpression::*;
         match expr {
-            _ => todo!("uh oh"),
+            Literal(n) => Ok(*n),
+            GetVariable(name) => self
+                .ex_state
+                .root_vars
+                .number
+                .get(name)
+                .copied()
+                .ok_or(undefined_variable(name)),
+            CardsIn(cards) => {
+                Ok(self.evaluate_card_collection(cards)?.len() as types_instances::BaseNumberType)
+            }
         }
     }
 
-    pub fn evaluate_bool(&self, expr: &expressions::BooleanExpression) -> bool {
+    pub fn evaluate_bool(&self, expr: &expressions::BooleanExpression) -> Result<bool, GameError> {
         use expressions::BooleanExpression::*;
         match expr {
-            Literal(b) => *b,
+            Literal(b) => Ok(*b),
             Comparison { a, compared_to, b } => {
-                let va = self.evaluate_number(a);
-                let vb = self.evaluate_number(b);
+                let va = self.evaluate_number(a)?;
+                let vb = self.evaluate_number(b)?;
                 use expressions::Comparison::*;
-                match compared_to {
+                Ok(match compared_to {
                     GT => va > vb,
                     LT => va < vb,
                     GTE => va >= vb,
                     LTE => va <= vb,
-                    Eq => va == vb,
+                    EQ => va == vb,
+                    NEQ => va != vb,
+                })
+            }
+            PlayerIsType { player, type_name } => {
+                let player = self.evaluate_player(player)?;
+                self.state.check_player(player)?;
+                Ok(&self.state.players[player as usize] == type_name)
+            }
+            MatchesPattern { cards, pattern } => {
+                let cards = self.evaluate_card_collection(cards)?;
+                let report = self
+                    .state
+                    .cards
+                    .match_pattern(pattern, &cards)
+                    .ok_or(RuntimeError::MissingPattern(pattern.clone()))?;
+                Ok(report.is_match())
+            }
+        }
+    }
+
+    pub fn evaluate_card_set(&self, expr: &expressions::CardSetExpression) -> cards::CardSet {
+        use expressions::CardSetExpression::*;
+        match expr {
+            // Fixed order so generated decks don't depend on hash set iteration
+            AllAllowed => cards::CardSet {
+                ranks: ranks::Rank::all()
+                    .into_iter()
+                    .filter(|r| self.config.allowed_ranks.contains(r))
+                    .collect(),
+                suits: suits::Suit::all()
+                    .into_iter()
+                    .filter(|s| self.config.allowed_suits.contains(s))
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn evaluate_order(
+        &self,
+        expr: &expressions::OrderExpression,
+    ) -> Result<OrderIdentifier, GameError> {
+        use expressions::OrderExpression::*;
+        match expr {
+            GetVariable(name) => {
+                if self.config.orders.contains_key(name) {
+                    Ok(name.clone())
+                } else {
+                    Err(RuntimeError::MissingOrder(name.clone()).into())
                 }
             }
-            _ => todo!("uh oh"),
         }
     }
 
-    pub fn evaluate_zone(&self, expr: &expressions::ZoneExpression) -> VariableIdentifier {
+    pub fn evaluate_card(&self, expr: &expressions::CardExpression) -> Result<CardID, GameError> {
+        use expressions::CardExpression::*;
+        match expr {
+            // Only makes sense where the suit or rank is taken straight back out of it
+            Create(_, _) => Err(RuntimeError::CardNotInPlay.into()),
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.card.get(name))
+                .copied()
+                .ok_or(undefined_variable(name)),
+        }
+    }
+
+    pub fn evaluate_suit(
+        &self,
+        expr: &expressions::SuitExpression,
+    ) -> Result<suits::Suit, GameError> {
+        use expressions::{CardExpression, SuitExpression::*};
+        match expr {
+            Literal(suit) => Ok(*suit),
+            FromCard(CardExpression::Create(suit, _)) => self.evaluate_suit(suit),
+            FromCard(card) => Ok(self.state.cards.get_card(self.evaluate_card(card)?)?.suit),
+        }
+    }
+
+    pub fn evaluate_rank(
+        &self,
+        expr: &expressions::RankExpression,
+    ) -> Result<ranks::Rank, GameError> {
+        use expressions::{CardExpression, RankExpression::*};
+        match expr {
+            Literal(rank) => Ok(*rank),
+            FromCard(CardExpression::Create(_, rank)) => self.evaluate_rank(rank),
+            FromCard(card) => Ok(self.state.cards.get_card(self.evaluate_card(card)?)?.rank),
+        }
+    }
+
+    pub fn evaluate_card_selector(
+        &self,
+        expr: &expressions::CardSelectorExpression,
+    ) -> Result<zones::CardSelector, GameError> {
+        use expressions::CardSelectorExpression::*;
+        Ok(match expr {
+            Top => zones::CardSelector::Top,
+            Bottom => zones::CardSelector::Bottom,
+            Suit(suit) => zones::CardSelector::Suit(self.evaluate_suit(suit)?),
+            Rank(rank) => zones::CardSelector::Rank(self.evaluate_rank(rank)?),
+        })
+    }
+
+    pub fn evaluate_card_collection(
+        &self,
+        expr: &expressions::CardCollectionExpression,
+    ) -> Result<Vec<CardID>, GameError> {
+        use expressions::CardCollectionExpression::*;
+        match expr {
+            Single(card) => Ok(vec![self.evaluate_card(card)?]),
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.card_collection.get(name))
+                .cloned()
+                .ok_or(undefined_variable(name)),
+            AllInZone(zone) => {
+                let zone = self.evaluate_zone(zone)?;
+                Ok(self.state.cards.get_zone(zone)?.cards.clone())
+            }
+            TopInZone(zone) => {
+                let zone = self.evaluate_zone(zone)?;
+                Ok(self
+                    .state
+                    .cards
+                    .get_zone(zone)?
+                    .cards
+                    .last()
+                    .into_iter()
+                    .copied()
+                    .collect())
+            }
+            BottomInZone(zone) => {
+                let zone = self.evaluate_zone(zone)?;
+                Ok(self
+                    .state
+                    .cards
+                    .get_zone(zone)?
+                    .cards
+                    .first()
+                    .into_iter()
+                    .copied()
+                    .collect())
+            }
+            InZoneMatchingSuit { zone, suit } => {
+                let suit = self.evaluate_suit(suit)?;
+                self.cards_in_zone_where(zone, |card| card.suit == suit)
+            }
+            InZoneMatchingRank { zone, rank } => {
+                let rank = self.evaluate_rank(rank)?;
+                self.cards_in_zone_where(zone, |card| card.rank == rank)
+            }
+            InZoneMatchingPattern { zone, pattern } => {
+                let zone = self.evaluate_zone(zone)?;
+                let cards = &self.state.cards.get_zone(zone)?.cards;
+                let found = self
+                    .state
+                    .cards
+                    .find_pattern(pattern, cards)
+                    .ok_or(RuntimeError::MissingPattern(pattern.clone()))?;
+                Ok(found.map_err(|e| RuntimeError::PatternSearch(pattern.clone(), e))?)
+            }
+        }
+    }
+
+    fn cards_in_zone_where(
+        &self,
+        zone: &expressions::ZoneExpression,
+        filter: impl Fn(&cards::Card) -> bool,
+    ) -> Result<Vec<CardID>, GameError> {
+        let zone = self.evaluate_zone(zone)?;
+        let mut result = Vec::new();
+        for card_id in self.state.cards.get_zone(zone)?.cards.iter() {
+            if filter(&self.state.cards.get_card(*card_id)?) {
+                result.push(*card_id);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn evaluate_zone(
+        &self,
+        expr: &expressions::ZoneExpression,
+    ) -> Result<GameZoneID, GameError> {
         use expressions::ZoneExpression::*;
         match expr {
-            _ => todo!("uh oh"),
+            OwnedByPlayer { player, zone_name } => {
+                let player = self.evaluate_player(player)?;
+                self.state
+                    .cards
+                    .zone_owned_by(player, zone_name)
+                    .ok_or(RuntimeError::MissingPlayerZone(player, zone_name.clone()).into())
+            }
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.zone.get(name))
+                .or(self.ex_state.root_vars.zone.get(name))
+                .copied()
+                .ok_or(undefined_variable(name)),
         }
     }
 
     pub fn evaluate_zone_collection(
         &self,
         expr: &expressions::ZoneCollectionExpression,
-    ) -> Vec<VariableIdentifier> {
+    ) -> Result<Vec<GameZoneID>, GameError> {
         use expressions::ZoneCollectionExpression::*;
         match expr {
-            _ => todo!("uh oh"),
+            Single(zone) => Ok(vec![self.evaluate_zone(zone)?]),
+            OfType(class) => Ok(self.state.cards.zones_of_class(class)),
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.zone_collection.get(name))
+                .cloned()
+                .ok_or(undefined_variable(name)),
         }
     }
 
-    pub fn evaluate_player(&self, expr: &expressions::PlayerExpression) -> VariableIdentifier {
+    pub fn evaluate_player(
+        &self,
+        expr: &expressions::PlayerExpression,
+    ) -> Result<PlayerOrderIndex, GameError> {
         use expressions::PlayerExpression::*;
         match expr {
-            _ => todo!("uh oh"),
+            CurrentPlayer => Ok(self.state.current_player),
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.player.get(name))
+                .copied()
+                .ok_or(undefined_variable(name)),
         }
     }
 
     pub fn evaluate_player_collection(
         &self,
         expr: &expressions::PlayerCollectionExpression,
-    ) -> Vec<VariableIdentifier> {
+    ) -> Result<Vec<PlayerOrderIndex>, GameError> {
         use expressions::PlayerCollectionExpression::*;
         match expr {
-            _ => todo!("uh oh"),
+            Single(player) => Ok(vec![self.evaluate_player(player)?]),
+            AllPlayers => Ok((0..self.state.player_count()).collect()),
+            GetVariable(name) => self
+                .ex_state
+                .lookup(|vars| vars.player_collection.get(name))
+                .cloned()
+                .ok_or(undefined_variable(name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+
+    // Player 0 is the only Dealer, everything else is declared per test in the phases
+    const HEADER: &str = r#"
+        players 2..4;
+        ranks Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        suits Hearts, Diamonds, Spades, Clubs;
+        numbers score;
+        order high = Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        pattern pair = ranks(2 any);
+        assignment Dealer, Player;
+        player class Dealer { zones hand; for player 0; }
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        zone class Pile { owner hidden; others hidden; }
+        zone class Meld { owner visible; others visible; rules pair; }
+        player zone hand: Hand;
+        zone deck: Pile;
+        zone table: Pile;
+        zone meld: Meld;
+        start setup;
+    "#;
+
+    fn started(phases: &str, players: u32) -> Game {
+        let config = dsl::parse_config(&format!("{HEADER}{phases}")).expect("test ruleset parses");
+        let mut game = Game::new(config, 1).unwrap();
+        game.update_players(players).unwrap();
+        game.init().unwrap();
+        game
+    }
+
+    // Runs until the game waits on an offer or ends
+    fn run(game: &mut Game) -> Result<EngineStatus, GameError> {
+        loop {
+            match game.eval_statement()? {
+                EngineStatus::Ready | EngineStatus::Broadcast { .. } | EngineStatus::Sleep(_) => (),
+                status => return Ok(status),
+            }
+        }
+    }
+
+    fn cards_in(game: &Game, zone: &str) -> Vec<CardID> {
+        game.cards_in_zone(game.state.get_zone_by_name(zone).unwrap())
+    }
+
+    fn hand(game: &Game, player: PlayerOrderIndex) -> Vec<CardID> {
+        game.cards_in_zone(game.state.cards.zone_owned_by(player, "hand").unwrap())
+    }
+
+    fn score(game: &Game) -> i32 {
+        game.ex_state.root_vars.number["score"]
+    }
+
+    #[test]
+    fn generates_shuffles_deals_and_moves_cards() {
+        let mut game = started(
+            r#"phase setup {
+                generate all cards into deck;
+                shuffle zone deck;
+                deal 5 from deck to every Hand;
+                move top of deck to table;
+            }"#,
+            3,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+
+        assert_eq!(cards_in(&game, "deck").len(), 52 - 3 * 5 - 1);
+        assert_eq!(cards_in(&game, "table").len(), 1);
+        for player in 0..3 {
+            assert_eq!(hand(&game, player).len(), 5);
+        }
+        game.check_card_count().unwrap();
+
+        let generated: Vec<CardID> = (1..=52).collect();
+        let mut deck = cards_in(&game, "deck");
+        deck.sort();
+        assert_ne!(cards_in(&game, "deck"), deck, "deck was shuffled");
+        assert!(deck.iter().all(|card| generated.contains(card)));
+        assert!(
+            game.events()
+                .iter()
+                .any(|e| matches!(e, GameEvent::ZoneShuffled { .. }))
+        );
+    }
+
+    #[test]
+    fn deal_goes_round_the_zones_one_card_at_a_time() {
+        let mut game = started(
+            r#"phase setup {
+                generate all cards into deck;
+                deal 2 from deck to every Hand;
+            }"#,
+            2,
+        );
+        run(&mut game).unwrap();
+        // Top of the deck is the last card generated
+        assert_eq!(hand(&game, 0), vec![52, 50]);
+        assert_eq!(hand(&game, 1), vec![51, 49]);
+    }
+
+    #[test]
+    fn set_number_then_declare_winner_ends_the_game() {
+        let mut game = started(
+            r#"phase setup {
+                set score = 2;
+                if score == 2 declare winner current player; else declare winner everyone;
+                set score = 5;
+            }"#,
+            2,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+        assert_eq!(score(&game), 2, "nothing runs after a winner is declared");
+        assert_eq!(game.get_winners(), vec![0]);
+        assert!(matches!(
+            game.get_status(),
+            game_state::GameStatus::Finished
+        ));
+        assert!(matches!(
+            game.events().last(),
+            Some(GameEvent::Finished { winners }) if winners == &vec![0]
+        ));
+    }
+
+    #[test]
+    fn advance_wraps_around_both_ways() {
+        let mut game = started("phase setup { advance 2; advance -3; }", 3);
+        run(&mut game).unwrap();
+        assert_eq!(game.state.current_player, 2);
+    }
+
+    #[test]
+    fn advance_among_skips_other_classes() {
+        let mut game = started("phase setup { advance 1; advance 1 among Dealer; }", 3);
+        run(&mut game).unwrap();
+        assert_eq!(game.state.current_player, 0);
+    }
+
+    #[test]
+    fn enter_phase_leaves_the_current_phase() {
+        let mut game = started(
+            r#"phase setup { enter second; set score = 9; }
+            phase second { set score = 1; }"#,
+            2,
+        );
+        run(&mut game).unwrap();
+        assert_eq!(score(&game), 1);
+        assert!(
+            game.events()
+                .iter()
+                .any(|e| matches!(e, GameEvent::PhaseEntered { phase } if phase == "second"))
+        );
+    }
+
+    #[test]
+    fn offer_waits_for_an_answer_then_runs_the_handler() {
+        let mut game = started(
+            r#"phase setup {
+                offer to current player {
+                    case "Low" handle set score = 1;
+                    case "High" handle set score = 3;
+                }
+                set score = score;
+            }"#,
+            2,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+        assert_eq!(game.pending_offer().unwrap().players, vec![0]);
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+
+        let answer = OfferResponse {
+            case: 1,
+            choices: Vec::new(),
+        };
+        assert!(matches!(
+            game.respond_to_offer(1, &answer),
+            Err(GameError::Recoverable(RecoverableGameError::NotOffered(1)))
+        ));
+        game.respond_to_offer(0, &answer).unwrap();
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+        assert_eq!(score(&game), 3);
+    }
+
+    #[test]
+    fn dealing_from_an_empty_zone_deals_nothing() {
+        let mut game = started("phase setup { deal 3 from deck to every Hand; }", 2);
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+        assert!(hand(&game, 0).is_empty());
+        assert!(
+            !game
+                .events()
+                .iter()
+                .any(|e| matches!(e, GameEvent::CardsMoved { .. }))
+        );
+    }
+
+    #[test]
+    fn bad_player_index_is_an_error() {
+        let mut game = started("phase setup { advance 1; }", 2);
+        game.state.current_player = 7;
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::StateRuntime(
+                game_state::StateModifyError::MissingResource(
+                    game_state::ResourceReferenceError::Player(7)
+                )
+            )))
+        ));
+    }
+
+    #[test]
+    fn advancing_among_a_missing_class_is_an_error() {
+        let mut game = started("phase setup { advance 1 among Ghost; }", 2);
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::StateRuntime(
+                game_state::StateModifyError::MissingResource(
+                    game_state::ResourceReferenceError::PlayerClass(_)
+                )
+            )))
+        ));
+    }
+
+    #[test]
+    fn entering_a_missing_phase_is_an_error() {
+        let mut game = started("phase setup { enter nowhere; }", 2);
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(RuntimeError::MissingPhase(p)))) if p == "nowhere"
+        ));
+    }
+
+    #[test]
+    fn setting_an_undeclared_number_is_an_error() {
+        let mut game = started("phase setup { set missing = 1; }", 2);
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(RuntimeError::UndefinedVariable(name)))) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn moving_cards_to_a_missing_player_zone_is_an_error() {
+        let mut game = started(
+            r#"phase setup {
+                generate all cards into deck;
+                move top of deck to tricks of current player;
+            }"#,
+            2,
+        );
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(
+                RuntimeError::MissingPlayerZone(0, _)
+            )))
+        ));
+    }
+
+    // Player 0 answers the only case of an offer made after the deck is generated, a single card
+    // in the meld never makes a pair
+    fn rejected_answer(choices: &str, handler: &str, answer: impl Fn(&Game) -> Vec<ChoiceValue>) {
+        let mut game = started(
+            &format!(
+                r#"phase setup {{
+                    generate all cards into deck;
+                    offer to current player {{ case "Go" {choices} handle {{ {handler} }} }}
+                }}"#
+            ),
+            2,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+        let cards = serde_json::to_value(&game.state.cards).unwrap();
+        let events = game.events().len();
+
+        let response = OfferResponse {
+            case: 0,
+            choices: answer(&game),
+        };
+        let result = game
+            .respond_to_offer(0, &response)
+            .and_then(|_| run(&mut game));
+        assert!(
+            matches!(
+                &result,
+                Err(GameError::Recoverable(RecoverableGameError::BrokenZoneRule { player: 0, violation }))
+                    if violation.zone == game.state.get_zone_by_name("meld").unwrap()
+            ),
+            "{:?}",
+            result.err()
+        );
+
+        assert_eq!(serde_json::to_value(&game.state.cards).unwrap(), cards);
+        assert_eq!(game.events().len(), events);
+        assert_eq!(game.pending_offer().unwrap().players, vec![0]);
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+    }
+
+    #[test]
+    fn handler_move_breaking_a_zone_rule_is_rolled_back() {
+        rejected_answer(
+            "",
+            "move top of deck to table; move top of deck to meld;",
+            |_| Vec::new(),
+        );
+    }
+
+    #[test]
+    fn handler_deal_breaking_a_zone_rule_is_rolled_back() {
+        rejected_answer(
+            "",
+            "move top of deck to table; deal 2 from deck to every Meld;",
+            |_| Vec::new(),
+        );
+    }
+
+    #[test]
+    fn offered_move_breaking_a_zone_rule_is_rejected() {
+        rejected_answer("choices move from deck to meld", "pass;", |game| {
+            vec![ChoiceValue::Cards(vec![cards_in(game, "deck")[0]])]
+        });
+    }
+
+    #[test]
+    fn zone_rules_outside_an_offer_crash_the_game() {
+        let mut game = started(
+            "phase setup { generate all cards into deck; move top of deck to meld; }",
+            2,
+        );
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(
+                RuntimeError::BrokenZoneRule(_)
+            )))
+        ));
+        assert_eq!(cards_in(&game, "deck").len(), 52);
+        assert!(cards_in(&game, "meld").is_empty());
+    }
+
+    #[test]
+    fn moves_that_keep_zone_rules_go_through() {
+        let mut game = started(
+            "phase setup { generate all cards into deck; move pattern pair in deck to meld; }",
+            2,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+        assert_eq!(cards_in(&game, "meld").len(), 2);
+    }
+
+    fn statements(count: usize) -> String {
+        "set score = 1; ".repeat(count)
+    }
+
+    #[test]
+    fn runaway_while_is_blamed() {
+        let mut game = started("phase setup { while true set score = 1; }", 2);
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(
+                RuntimeError::RunawayWhile(_)
+            )))
+        ));
+    }
+
+    #[test]
+    fn long_program_runs_out_of_statements() {
+        let limit = execution_state::STATEMENT_LIMIT;
+        let mut game = started(
+            &format!("phase setup {{ {} }}", statements(limit as usize + 1)),
+            2,
+        );
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(RuntimeError::StatementLimit(l)))) if l == limit
+        ));
+    }
+
+    #[test]
+    fn phases_going_round_in_circles_run_out_of_statements() {
+        let mut game = started(
+            "phase setup { enter again; } phase again { enter setup; }",
+            2,
+        );
+        assert!(matches!(
+            run(&mut game),
+            Err(GameError::Fatal(FatalGameError::Runtime(
+                RuntimeError::StatementLimit(_)
+            )))
+        ));
+    }
+
+    #[test]
+    fn budget_resets_once_players_answer() {
+        let share = execution_state::STATEMENT_LIMIT as usize * 3 / 4;
+        let mut game = started(
+            &format!(
+                r#"phase setup {{
+                    {before}
+                    offer to current player {{ case "Go" handle pass; }}
+                    {after}
+                }}"#,
+                before = statements(share),
+                after = statements(share),
+            ),
+            2,
+        );
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+        assert_eq!(game.ex_state.statements_evaluated, 0);
+
+        let answer = OfferResponse {
+            case: 0,
+            choices: Vec::new(),
+        };
+        game.respond_to_offer(0, &answer).unwrap();
+        assert!(matches!(run(&mut game), Ok(EngineStatus::Finished)));
+    }
+
+    #[test]
+    fn while_waiting_on_players_is_not_runaway() {
+        let mut game = started(
+            r#"phase setup {
+                while true offer to current player { case "Again" handle pass; }
+            }"#,
+            2,
+        );
+        let answer = OfferResponse {
+            case: 0,
+            choices: Vec::new(),
+        };
+        for _ in 0..execution_state::STATEMENT_LIMIT {
+            assert!(matches!(run(&mut game), Ok(EngineStatus::Blocked)));
+            game.respond_to_offer(0, &answer).unwrap();
+        }
+    }
+
+    mod evaluation {
+        use super::*;
+        use expressions::*;
+
+        // Three players with two cards each, card picked is the top of player 0's hand and
+        // target is player 1
+        fn evaluating() -> Game {
+            let mut game = started(
+                r#"phase setup {
+                    generate all cards into deck;
+                    deal 2 from deck to every Hand;
+                }"#,
+                3,
+            );
+            run(&mut game).unwrap();
+
+            let mut scope = var_state::VarMapping::new();
+            scope
+                .card
+                .insert("picked".into(), *hand(&game, 0).last().unwrap());
+            scope
+                .card_collection
+                .insert("chosen".into(), hand(&game, 1));
+            scope.player.insert("target".into(), 1);
+            scope.player.insert("ghost".into(), 9);
+            scope.player_collection.insert("targets".into(), vec![1, 2]);
+            scope
+                .zone
+                .insert("spot".into(), game.state.get_zone_by_name("table").unwrap());
+            scope.zone_collection.insert("piles".into(), vec![1, 2]);
+            game.ex_state.push_scope(Arc::new(Statement::Empty), scope);
+            game
+        }
+
+        fn zone(name: &str) -> Box<ZoneExpression> {
+            Box::new(ZoneExpression::GetVariable(name.into()))
+        }
+
+        fn hand_of(player: PlayerExpression) -> Box<ZoneExpression> {
+            Box::new(ZoneExpression::OwnedByPlayer {
+                player: Box::new(player),
+                zone_name: "hand".into(),
+            })
+        }
+
+        fn picked() -> CardExpression {
+            CardExpression::GetVariable("picked".into())
+        }
+
+        fn undefined(result: Result<TypeInstance, GameError>, variable: &str) -> bool {
+            matches!(
+                result,
+                Err(GameError::Fatal(FatalGameError::Runtime(RuntimeError::UndefinedVariable(name)))) if name == variable
+            )
+        }
+
+        #[test]
+        fn numbers() {
+            let game = evaluating();
+            let number = |e| game.evaluate(&Expression::Number(e));
+            assert!(matches!(
+                number(NumberExpression::Literal(-4)),
+                Ok(TypeInstance::Number(-4))
+            ));
+            assert!(matches!(
+                number(NumberExpression::GetVariable("score".into())),
+                Ok(TypeInstance::Number(0))
+            ));
+            assert!(matches!(
+                number(NumberExpression::CardsIn(Box::new(
+                    CardCollectionExpression::AllInZone(zone("deck"))
+                ))),
+                Ok(TypeInstance::Number(46))
+            ));
+            assert!(undefined(
+                number(NumberExpression::GetVariable("missing".into())),
+                "missing"
+            ));
+        }
+
+        #[test]
+        fn comparisons() {
+            let game = evaluating();
+            let compare = |a, compared_to, b| {
+                game.evaluate_bool(&BooleanExpression::Comparison {
+                    a: Box::new(NumberExpression::Literal(a)),
+                    compared_to,
+                    b: Box::new(NumberExpression::Literal(b)),
+                })
+                .unwrap()
+            };
+            let table = [
+                (1, Comparison::GT, 2, false),
+                (1, Comparison::LT, 2, true),
+                (2, Comparison::GTE, 2, true),
+                (3, Comparison::LTE, 2, false),
+                (2, Comparison::EQ, 2, true),
+                (1, Comparison::EQ, 2, false),
+                (2, Comparison::NEQ, 2, false),
+                (1, Comparison::NEQ, 2, true),
+            ];
+            for (a, compared_to, b, expected) in table {
+                let shown = format!("{a} {compared_to:?} {b}");
+                assert_eq!(compare(a, compared_to, b), expected, "{shown}");
+            }
+        }
+
+        #[test]
+        fn booleans() {
+            let game = evaluating();
+            let boolean = |e| game.evaluate(&Expression::Boolean(e));
+            let is_type = |player, type_name: &str| BooleanExpression::PlayerIsType {
+                player: Box::new(player),
+                type_name: type_name.into(),
+            };
+            assert!(matches!(
+                boolean(BooleanExpression::Literal(true)),
+                Ok(TypeInstance::Boolean(true))
+            ));
+            assert!(matches!(
+                boolean(is_type(PlayerExpression::CurrentPlayer, "Dealer")),
+                Ok(TypeInstance::Boolean(true))
+            ));
+            assert!(matches!(
+                boolean(is_type(
+                    PlayerExpression::GetVariable("target".into()),
+                    "Dealer"
+                )),
+                Ok(TypeInstance::Boolean(false))
+            ));
+            assert!(matches!(
+                boolean(is_type(
+                    PlayerExpression::GetVariable("ghost".into()),
+                    "Dealer"
+                )),
+                Err(GameError::Fatal(FatalGameError::StateRuntime(_)))
+            ));
+
+            let matches = |pattern: &str| BooleanExpression::MatchesPattern {
+                cards: Box::new(CardCollectionExpression::AllInZone(hand_of(
+                    PlayerExpression::CurrentPlayer,
+                ))),
+                pattern: pattern.into(),
+            };
+            let pair = game
+                .state
+                .cards
+                .match_pattern(&"pair".into(), &hand(&game, 0))
+                .unwrap();
+            assert!(matches!(
+                boolean(matches("pair")),
+                Ok(TypeInstance::Boolean(b)) if b == pair.is_match()
+            ));
+            assert!(matches!(
+                boolean(matches("missing")),
+                Err(GameError::Fatal(FatalGameError::Runtime(
+                    RuntimeError::MissingPattern(_)
+                )))
+            ));
+        }
+
+        #[test]
+        fn zones() {
+            let game = evaluating();
+            let hand_zone = game.state.cards.zone_owned_by(0, "hand").unwrap();
+            let table = game.state.get_zone_by_name("table").unwrap();
+            assert!(matches!(
+                game.evaluate(&Expression::Zone(*hand_of(PlayerExpression::CurrentPlayer))),
+                Ok(TypeInstance::Zone(z)) if z == hand_zone
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Zone(*zone("spot"))),
+                Ok(TypeInstance::Zone(z)) if z == table
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Zone(ZoneExpression::OwnedByPlayer {
+                    player: Box::new(PlayerExpression::CurrentPlayer),
+                    zone_name: "tricks".into(),
+                })),
+                Err(GameError::Fatal(FatalGameError::Runtime(
+                    RuntimeError::MissingPlayerZone(0, _)
+                )))
+            ));
+            // A zone collection isn't a zone
+            assert!(undefined(
+                game.evaluate(&Expression::Zone(*zone("piles"))),
+                "piles"
+            ));
+
+            let collection = |e| game.evaluate(&Expression::ZoneCollection(e));
+            assert!(matches!(
+                collection(ZoneCollectionExpression::OfType("Hand".into())),
+                Ok(TypeInstance::ZoneCollection(zones)) if zones.len() == 3
+            ));
+            assert!(matches!(
+                collection(ZoneCollectionExpression::Single(zone("spot"))),
+                Ok(TypeInstance::ZoneCollection(zones)) if zones == vec![table]
+            ));
+            assert!(matches!(
+                collection(ZoneCollectionExpression::GetVariable("piles".into())),
+                Ok(TypeInstance::ZoneCollection(zones)) if zones == vec![1, 2]
+            ));
+            assert!(undefined(
+                collection(ZoneCollectionExpression::GetVariable("spot".into())),
+                "spot"
+            ));
+        }
+
+        #[test]
+        fn players() {
+            let game = evaluating();
+            let player = |e| game.evaluate(&Expression::Player(e));
+            assert!(matches!(
+                player(PlayerExpression::CurrentPlayer),
+                Ok(TypeInstance::Player(0))
+            ));
+            assert!(matches!(
+                player(PlayerExpression::GetVariable("target".into())),
+                Ok(TypeInstance::Player(1))
+            ));
+            // A card isn't a player
+            assert!(undefined(
+                player(PlayerExpression::GetVariable("picked".into())),
+                "picked"
+            ));
+
+            let collection = |e| game.evaluate(&Expression::PlayerCollection(e));
+            assert!(matches!(
+                collection(PlayerCollectionExpression::AllPlayers),
+                Ok(TypeInstance::PlayerCollection(ps)) if ps == vec![0, 1, 2]
+            ));
+            assert!(matches!(
+                collection(PlayerCollectionExpression::Single(Box::new(PlayerExpression::CurrentPlayer))),
+                Ok(TypeInstance::PlayerCollection(ps)) if ps == vec![0]
+            ));
+            assert!(matches!(
+                collection(PlayerCollectionExpression::GetVariable("targets".into())),
+                Ok(TypeInstance::PlayerCollection(ps)) if ps == vec![1, 2]
+            ));
+            assert!(undefined(
+                collection(PlayerCollectionExpression::GetVariable("target".into())),
+                "target"
+            ));
+        }
+
+        #[test]
+        fn cards() {
+            let game = evaluating();
+            let in_hand = hand(&game, 0);
+            let picked_id = *in_hand.last().unwrap();
+            let card = game.state.cards.get_card(picked_id).unwrap();
+
+            assert!(matches!(
+                game.evaluate(&Expression::Card(picked())),
+                Ok(TypeInstance::Card(c)) if c == picked_id
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Card(CardExpression::Create(
+                    Box::new(SuitExpression::Literal(suits::Suit::Spades)),
+                    Box::new(RankExpression::Literal(ranks::Rank::Ace)),
+                ))),
+                Err(GameError::Fatal(FatalGameError::Runtime(
+                    RuntimeError::CardNotInPlay
+                )))
+            ));
+            assert!(undefined(
+                game.evaluate(&Expression::Card(CardExpression::GetVariable(
+                    "chosen".into()
+                ))),
+                "chosen"
+            ));
+
+            let collection = |e| game.evaluate(&Expression::CardCollection(e));
+            let current_hand = || hand_of(PlayerExpression::CurrentPlayer);
+            assert!(matches!(
+                collection(CardCollectionExpression::Single(Box::new(picked()))),
+                Ok(TypeInstance::CardCollection(cs)) if cs == vec![picked_id]
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::GetVariable("chosen".into())),
+                Ok(TypeInstance::CardCollection(cs)) if cs == hand(&game, 1)
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::AllInZone(current_hand())),
+                Ok(TypeInstance::CardCollection(cs)) if cs == in_hand
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::TopInZone(current_hand())),
+                Ok(TypeInstance::CardCollection(cs)) if cs == vec![picked_id]
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::BottomInZone(current_hand())),
+                Ok(TypeInstance::CardCollection(cs)) if cs == vec![in_hand[0]]
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::TopInZone(zone("spot"))),
+                Ok(TypeInstance::CardCollection(cs)) if cs.is_empty()
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::InZoneMatchingSuit {
+                    zone: current_hand(),
+                    suit: Box::new(SuitExpression::FromCard(picked())),
+                }),
+                Ok(TypeInstance::CardCollection(cs)) if cs.contains(&picked_id)
+            ));
+            assert!(matches!(
+                collection(CardCollectionExpression::InZoneMatchingRank {
+                    zone: zone("deck"),
+                    rank: Box::new(RankExpression::FromCard(picked())),
+                }),
+                Ok(TypeInstance::CardCollection(cs)) if cs.len() == 3
+            ));
+            assert!(undefined(
+                collection(CardCollectionExpression::GetVariable("picked".into())),
+                "picked"
+            ));
+
+            assert!(matches!(
+                game.evaluate(&Expression::Suit(SuitExpression::FromCard(picked()))),
+                Ok(TypeInstance::Suit(s)) if s == card.suit
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Suit(SuitExpression::FromCard(
+                    CardExpression::Create(
+                        Box::new(SuitExpression::Literal(suits::Suit::Clubs)),
+                        Box::new(RankExpression::Literal(ranks::Rank::Two)),
+                    )
+                ))),
+                Ok(TypeInstance::Suit(suits::Suit::Clubs))
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Rank(RankExpression::FromCard(picked()))),
+                Ok(TypeInstance::Rank(r)) if r == card.rank
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Rank(RankExpression::Literal(
+                    ranks::Rank::Queen
+                ))),
+                Ok(TypeInstance::Rank(ranks::Rank::Queen))
+            ));
+        }
+
+        #[test]
+        fn orders_sets_and_selectors() {
+            let game = evaluating();
+            assert!(matches!(
+                game.evaluate(&Expression::Order(OrderExpression::GetVariable("high".into()))),
+                Ok(TypeInstance::Order(o)) if o == "high"
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::Order(OrderExpression::GetVariable(
+                    "low".into()
+                ))),
+                Err(GameError::Fatal(FatalGameError::Runtime(
+                    RuntimeError::MissingOrder(_)
+                )))
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::CardSet(CardSetExpression::AllAllowed)),
+                Ok(TypeInstance::CardSet(set)) if set.ranks.len() == 13 && set.suits.len() == 4
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::CardSelector(CardSelectorExpression::Top)),
+                Ok(TypeInstance::CardSelector(zones::CardSelector::Top))
+            ));
+            assert!(matches!(
+                game.evaluate(&Expression::CardSelector(CardSelectorExpression::Suit(
+                    SuitExpression::Literal(suits::Suit::Hearts)
+                ))),
+                Ok(TypeInstance::CardSelector(zones::CardSelector::Suit(
+                    suits::Suit::Hearts
+                )))
+            ));
+            assert!(undefined(
+                game.evaluate(&Expression::CardSelector(CardSelectorExpression::Rank(
+                    RankExpression::FromCard(CardExpression::GetVariable("missing".into()))
+                ))),
+                "missing"
+            ));
         }
     }
 }
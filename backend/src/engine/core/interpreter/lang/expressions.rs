@@ -57,6 +57,10 @@ pub enum CardCollectionExpression {
         zone: Box<ZoneExpression>,
         rank: Box<RankExpression>,
     },
+    InZoneMatchingPattern {
+        zone: Box<ZoneExpression>,
+        pattern: patterns::PatternIdentifier,
+    }, // Largest group of cards in the zone that matches the pattern
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -94,6 +98,10 @@ pub enum BooleanExpression {
         player: Box<PlayerExpression>,
         type_name: String,
     },
+    MatchesPattern {
+        cards: Box<CardCollectionExpression>,
+        pattern: patterns::PatternIdentifier,
+    },
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -57,18 +57,18 @@ pub struct ConditionalStatement {
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
 #[ts(export)]
 pub struct Offer {
-    player_name: Option<String>,
-    offer_to: Box<expressions::PlayerCollectionExpression>,
-    cases: Vec<OfferCase>,
+    pub player_name: Option<String>,
+    pub offer_to: Box<expressions::PlayerCollectionExpression>,
+    pub cases: Vec<OfferCase>,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
 #[ts(export)]
 pub struct OfferCase {
-    condition: Option<Box<expressions::BooleanExpression>>,
-    choices: Vec<OfferChoice>,
-    handle: Arc<Statement>,
-    message: String,
+    pub condition: Option<Box<expressions::BooleanExpression>>,
+    pub choices: Vec<OfferChoice>,
+    pub handle: Arc<Statement>,
+    pub message: String,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -81,8 +81,8 @@ pub enum OfferChoice {
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
 #[ts(export)]
 pub struct ChoiceSelection {
-    name: String,
-    choice_type: ChoiceSelectionEnum,
+    pub name: String,
+    pub choice_type: ChoiceSelectionEnum,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -1,12 +1,23 @@
-use crate::engine::core::types;
+use crate::engine::core::interpreter::state::game_state::{CardID, GameZoneID, PlayerOrderIndex};
+use crate::engine::core::types::{cards, identifiers, ranks, suits, zones};
 pub type BaseNumberType = i32;
 
+// Result of evaluating an expression, one variant per expression family
+#[derive(Debug, Clone)]
 pub enum TypeInstance {
     Number(BaseNumberType),
     Boolean(bool),
 
-    Zone(types::identifiers::VariableIdentifier),
-    ZoneCollection(Vec<types::identifiers::VariableIdentifier>),
-    Player(types::identifiers::VariableIdentifier),
-    PlayerCollection(Vec<types::identifiers::VariableIdentifier>),
+    Zone(GameZoneID),
+    ZoneCollection(Vec<GameZoneID>),
+    Player(PlayerOrderIndex),
+    PlayerCollection(Vec<PlayerOrderIndex>),
+    Card(CardID),
+    CardCollection(Vec<CardID>),
+
+    Suit(suits::Suit),
+    Rank(ranks::Rank),
+    Order(identifiers::OrderIdentifier),
+    CardSet(cards::CardSet),
+    CardSelector(zones::CardSelector),
 }
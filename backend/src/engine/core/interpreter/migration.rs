@@ -0,0 +1,165 @@
+use super::config::GameConfig;
+
+use serde_json::Value;
+use thiserror::Error;
+
+// Shape of GameConfig that gets saved alongside every stored config
+// Bump this whenever a change to the language would stop saved configs deserializing, and add
+// the step that rewrites the old shape into the new one to UPGRADES
+pub const CONFIG_VERSION: i32 = 0;
+
+type Upgrade = fn(Value) -> Result<Value, String>;
+
+// UPGRADES[n] turns a version n config into version n + 1, so there's one per version so far
+const UPGRADES: [Upgrade; CONFIG_VERSION as usize] = [];
+
+#[derive(Error, Debug, Clone)]
+pub enum MigrationError {
+    #[error("Config is version {0} but this server only understands up to {CONFIG_VERSION}")]
+    TooNew(i32),
+    #[error("Config has an invalid version {0}")]
+    InvalidVersion(i32),
+    #[error("Couldn't upgrade config from version {from}: {reason}")]
+    Upgrade { from: i32, reason: String },
+    #[error("Config is unreadable: {0}")]
+    Unreadable(String),
+}
+
+// Reads a stored config of any known version into the current GameConfig
+pub fn load_config(text: &str, version: i32) -> Result<GameConfig, MigrationError> {
+    load_with(&UPGRADES, text, version)
+}
+
+// Runs every upgrade from version up to CONFIG_VERSION in order
+pub fn upgrade_config(value: Value, version: i32) -> Result<Value, MigrationError> {
+    upgrade_with(&UPGRADES, value, version)
+}
+
+// The current version is however many upgrades there are, tests bring their own chain
+fn load_with(upgrades: &[Upgrade], text: &str, version: i32) -> Result<GameConfig, MigrationError> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| MigrationError::Unreadable(e.to_string()))?;
+    let value = upgrade_with(upgrades, value, version)?;
+    serde_json::from_value(value).map_err(|e| MigrationError::Unreadable(e.to_string()))
+}
+
+fn upgrade_with(
+    upgrades: &[Upgrade],
+    mut value: Value,
+    version: i32,
+) -> Result<Value, MigrationError> {
+    if version > upgrades.len() as i32 {
+        return Err(MigrationError::TooNew(version));
+    }
+    let first = usize::try_from(version).map_err(|_| MigrationError::InvalidVersion(version))?;
+    for (from, upgrade) in upgrades.iter().enumerate().skip(first) {
+        value = upgrade(value).map_err(|reason| MigrationError::Upgrade {
+            from: from as i32,
+            reason,
+        })?;
+    }
+    Ok(value)
+}
+
+pub fn needs_upgrade(version: i32) -> bool {
+    version < CONFIG_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+
+    const RULESET: &str = r#"
+        players 2..4;
+        ranks Two, Three;
+        suits Hearts;
+        assignment Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        player zone hand: Hand;
+        start setup;
+        phase setup { pass; }
+    "#;
+
+    // Version 0 called the initial phase "start"
+    fn rename_start(mut value: Value) -> Result<Value, String> {
+        let config = value.as_object_mut().ok_or("config isn't an object")?;
+        let start = config.remove("start").ok_or("config has no start")?;
+        config.insert("initial_phase".into(), start);
+        Ok(value)
+    }
+
+    const TEST_UPGRADES: [Upgrade; 1] = [rename_start];
+
+    // The test ruleset the way version 0 would have saved it
+    fn version_zero() -> String {
+        let config = dsl::parse_config(RULESET).unwrap();
+        let mut value = serde_json::to_value(&config).unwrap();
+        let config = value.as_object_mut().unwrap();
+        let start = config.remove("initial_phase").unwrap();
+        config.insert("start".into(), start);
+        value.to_string()
+    }
+
+    #[test]
+    fn old_configs_are_upgraded() {
+        let config = load_with(&TEST_UPGRADES, &version_zero(), 0).unwrap();
+        assert_eq!(config.initial_phase, "setup");
+    }
+
+    #[test]
+    fn current_configs_are_left_alone() {
+        let current = serde_json::to_string(&dsl::parse_config(RULESET).unwrap()).unwrap();
+        let config = load_with(&TEST_UPGRADES, &current, 1).unwrap();
+        assert_eq!(config.initial_phase, "setup");
+        assert_eq!(
+            upgrade_config(serde_json::from_str(&current).unwrap(), CONFIG_VERSION).unwrap(),
+            serde_json::from_str::<Value>(&current).unwrap()
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        assert!(matches!(
+            load_with(&TEST_UPGRADES, &version_zero(), 2),
+            Err(MigrationError::TooNew(2))
+        ));
+        assert!(matches!(
+            load_config(&version_zero(), CONFIG_VERSION + 1),
+            Err(MigrationError::TooNew(_))
+        ));
+    }
+
+    #[test]
+    fn negative_versions_are_rejected() {
+        assert!(matches!(
+            load_with(&TEST_UPGRADES, &version_zero(), -1),
+            Err(MigrationError::InvalidVersion(-1))
+        ));
+    }
+
+    #[test]
+    fn failed_upgrades_say_which_step_failed() {
+        let missing_start = serde_json::to_string(&dsl::parse_config(RULESET).unwrap()).unwrap();
+        let error = load_with(&TEST_UPGRADES, &missing_start, 0).unwrap_err();
+        assert!(matches!(error, MigrationError::Upgrade { from: 0, .. }));
+        assert_eq!(
+            error.to_string(),
+            "Couldn't upgrade config from version 0: config has no start"
+        );
+    }
+
+    #[test]
+    fn unreadable_configs_are_reported_as_such() {
+        assert!(matches!(
+            load_with(&TEST_UPGRADES, "{ not json", 1),
+            Err(MigrationError::Unreadable(_))
+        ));
+        // Old shape claiming to be current doesn't deserialize
+        assert!(matches!(
+            load_with(&TEST_UPGRADES, &version_zero(), 1),
+            Err(MigrationError::Unreadable(_))
+        ));
+    }
+}
@@ -1,4 +1,9 @@
 pub mod config;
 pub mod game;
 pub mod lang;
+pub mod migration;
+pub mod replay;
+pub mod simulation;
 pub mod state;
+pub mod type_check;
+pub mod validation;
@@ -0,0 +1,242 @@
+use super::{
+    config,
+    game::{EngineStatus, Game, GameError, RecoverableGameError},
+    state::{event_log::GameEvent, game_state::ZoneView},
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum ReplayError {
+    #[error("Config was rejected: {0}")]
+    Config(#[from] config::ConfigError),
+    #[error("Couldn't seat the recorded players: {0}")]
+    Players(String),
+    #[error("Replay stopped matching the log at event {0}")]
+    Diverged(usize),
+}
+
+// The game right after one recorded input and everything it set off
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    pub events: Vec<GameEvent>, // Starts with the input itself
+    pub zones: Vec<ZoneView>,
+    pub error: Option<String>, // Set if the game crashed here
+}
+
+// Re-runs a game from its config, seed and the inputs in its log. Every event the replay
+// produces has to match the log, otherwise the engine no longer plays the ruleset the same way
+pub fn replay(
+    config: config::GameConfig,
+    seed: u64,
+    log: &[GameEvent],
+) -> Result<Vec<ReplayStep>, ReplayError> {
+    let mut game = Game::new(config, seed)?;
+    let mut steps = Vec::new();
+
+    for input in log.iter().filter(|event| event.is_input()) {
+        let start = game.events().len();
+        let result = match input {
+            GameEvent::Started { players } => {
+                game.update_players(*players)
+                    .map_err(ReplayError::Players)?;
+                game.init()
+            }
+            GameEvent::OfferAnswered { player, response } => {
+                game.respond_to_offer(*player, response)
+            }
+            _ => Ok(()),
+        };
+        let error = result.and_then(|_| run(&mut game)).err().map(|e| match e {
+            GameError::Fatal(e) => e.to_string(),
+            GameError::Recoverable(e) => e.to_string(),
+        });
+        // Logs from before crashes were recorded end without one
+        if let (Some(error), Some(GameEvent::Crashed { .. })) =
+            (&error, log.get(game.events().len()))
+        {
+            game.record_crash(error.clone());
+        }
+
+        let events = game.events();
+        if let Some(at) = divergence(events, log) {
+            return Err(ReplayError::Diverged(at));
+        }
+        steps.push(ReplayStep {
+            events: events[start..].to_vec(),
+            zones: game.revealed_zones(),
+            error,
+        });
+        if steps.last().is_some_and(|step| step.error.is_some()) {
+            break;
+        }
+    }
+
+    match game.events().len() == log.len() {
+        true => Ok(steps),
+        false => Err(ReplayError::Diverged(game.events().len())),
+    }
+}
+
+// Runs until the game needs an answer or ends, the same way a room does minus the waiting
+fn run(game: &mut Game) -> Result<(), GameError> {
+    loop {
+        match game.eval_statement() {
+            Ok(EngineStatus::Blocked | EngineStatus::Finished) => return Ok(()),
+            Ok(_) => (),
+            // The answer was rolled back and never made it into the log
+            Err(GameError::Recoverable(RecoverableGameError::BrokenZoneRule { .. })) => {
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Index of the first replayed event that isn't in the log
+fn divergence(replayed: &[GameEvent], log: &[GameEvent]) -> Option<usize> {
+    replayed
+        .iter()
+        .enumerate()
+        .find(|(idx, event)| log.get(*idx) != Some(*event))
+        .map(|(idx, _)| idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+    use crate::engine::core::interpreter::state::{
+        execution_state::{ChoiceValue, ChoiceView, OfferResponse},
+        game_state::{CardID, GameZoneID},
+    };
+
+    // Deals from a shuffled deck, then each player plays the first card they're offered until
+    // someone's hand is empty
+    const RULESET: &str = r#"
+        players 2..3;
+        ranks Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        suits Hearts, Diamonds, Spades, Clubs;
+        assignment Player, Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        zone class Pile { owner hidden; others hidden; }
+        zone class Table { owner visible; others visible; }
+        player zone hand: Hand;
+        zone deck: Pile;
+        zone table: Table;
+        start setup;
+
+        phase setup {
+            generate all cards into deck;
+            shuffle zone deck;
+            deal 3 from deck to every Hand;
+            enter turn;
+        }
+
+        phase turn offer to current player {
+            case "Play a card" choices card picked from all in hand of current player handle {
+                move card picked to table;
+                if count all in hand of current player == 0 {
+                    declare winner current player;
+                } else {
+                    advance 1;
+                    enter turn;
+                }
+            }
+        }
+    "#;
+
+    fn config() -> config::GameConfig {
+        dsl::parse_config(RULESET).expect("test ruleset parses")
+    }
+
+    // The cards in every zone in order, as everyone can see them once the game is replayed
+    fn layout(zones: &[ZoneView]) -> Vec<(GameZoneID, Vec<CardID>)> {
+        zones
+            .iter()
+            .map(|zone| {
+                let cards = zone.cards.iter().flatten().map(|(id, _)| *id).collect();
+                (zone.zone_id, cards)
+            })
+            .collect()
+    }
+
+    // Plays a whole game with the same choices every time, returning the log and final zones
+    fn play(seed: u64) -> (Vec<GameEvent>, Vec<(GameZoneID, Vec<CardID>)>) {
+        let mut game = Game::new(config(), seed).unwrap();
+        game.update_players(2).unwrap();
+        game.init().unwrap();
+        run(&mut game).unwrap();
+
+        while let Some(pending) = game.pending_offer() {
+            let player = pending.players[0];
+            let view = game.describe_offer(player).unwrap();
+            let Some(ChoiceView::Card {
+                options: Some(options),
+                ..
+            }) = view.cases[0].choices.first()
+            else {
+                panic!("test ruleset offers a card");
+            };
+            let response = OfferResponse {
+                case: view.cases[0].index,
+                choices: vec![ChoiceValue::Card(options[0])],
+            };
+            game.respond_to_offer(player, &response).unwrap();
+            run(&mut game).unwrap();
+        }
+
+        (game.events().to_vec(), layout(&game.revealed_zones()))
+    }
+
+    // Every card moved before anyone played, the hands come off the top of the shuffled deck
+    fn dealt_cards(events: &[GameEvent]) -> Vec<CardID> {
+        events
+            .iter()
+            .take_while(|event| !event.is_input() || matches!(event, GameEvent::Started { .. }))
+            .filter_map(|event| match event {
+                GameEvent::CardsMoved { cards, .. } => Some(cards.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_the_same_game() {
+        let (events, zones) = play(42);
+        let (again, again_zones) = play(42);
+        assert_eq!(events, again);
+        assert_eq!(zones, again_zones);
+        assert!(matches!(events.last(), Some(GameEvent::Finished { .. })));
+    }
+
+    #[test]
+    fn replaying_the_log_ends_in_the_same_state() {
+        let (events, zones) = play(42);
+        let steps = replay(config(), 42, &events).unwrap();
+        let last = steps.last().unwrap();
+        assert!(last.error.is_none());
+        assert_eq!(layout(&last.zones), zones);
+
+        let replayed: Vec<GameEvent> = steps.into_iter().flat_map(|step| step.events).collect();
+        assert_eq!(replayed, events);
+    }
+
+    #[test]
+    fn different_seed_shuffles_differently() {
+        let (events, _) = play(42);
+        let (other, _) = play(43);
+        assert_ne!(dealt_cards(&events), dealt_cards(&other));
+    }
+
+    #[test]
+    fn replay_with_wrong_seed_diverges() {
+        let (events, _) = play(42);
+        assert!(matches!(
+            replay(config(), 43, &events),
+            Err(ReplayError::Diverged(_))
+        ));
+    }
+}
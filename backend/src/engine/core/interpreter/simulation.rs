@@ -0,0 +1,420 @@
+use super::{
+    config,
+    game::{EngineStatus, FatalGameError, Game, GameError, RecoverableGameError, RuntimeError},
+    state::{
+        execution_state::{ChoiceValue, ChoiceView, OfferResponse},
+        game_state::PlayerOrderIndex,
+    },
+};
+
+use rand::{Rng, SeedableRng, seq::IndexedRandom};
+use rand_chacha::ChaCha12Rng;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+// Random answers tried on one offer before the game counts as stuck
+const ANSWER_ATTEMPTS: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct SimulationOptions {
+    pub games: u32,
+    pub players: u32,
+    pub first_seed: u64,  // Games use first_seed, first_seed + 1, ...
+    pub max_answers: u32, // Games still going after this many answers never finish
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Finished(Vec<PlayerOrderIndex>),
+    Crashed(String),
+    LoopedForever(String),
+    Stuck, // No random answer was accepted
+    NeverFinished,
+    BrokenCardCount(String),
+}
+
+impl Outcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Finished(_) => "finished",
+            Outcome::Crashed(_) => "crashed",
+            Outcome::LoopedForever(_) => "looped forever",
+            Outcome::Stuck => "stuck on an offer",
+            Outcome::NeverFinished => "never finished",
+            Outcome::BrokenCardCount(_) => "broke card count",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub seed: u64,
+    pub answers: u32,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub players: u32,
+    pub results: Vec<GameResult>,
+}
+
+// Plays every game with random answers to its offers, the same options always play the same games
+pub fn simulate(config: &config::GameConfig, options: &SimulationOptions) -> SimulationReport {
+    let results = (0..options.games as u64)
+        .map(|idx| {
+            play_random_game(
+                config,
+                options.players,
+                options.first_seed.wrapping_add(idx),
+                options.max_answers,
+            )
+        })
+        .collect();
+    SimulationReport {
+        players: options.players,
+        results,
+    }
+}
+
+pub fn play_random_game(
+    config: &config::GameConfig,
+    players: u32,
+    seed: u64,
+    max_answers: u32,
+) -> GameResult {
+    let mut result = GameResult {
+        seed,
+        answers: 0,
+        outcome: Outcome::NeverFinished,
+    };
+    let mut game = match Game::new(config.clone(), seed) {
+        Ok(game) => game,
+        Err(e) => {
+            result.outcome = Outcome::Crashed(e.to_string());
+            return result;
+        }
+    };
+    if let Err(e) = game.update_players(players) {
+        result.outcome = Outcome::Crashed(e);
+        return result;
+    }
+    if let Err(e) = game.init() {
+        result.outcome = Outcome::Crashed(describe_error(&e));
+        return result;
+    }
+
+    // Answers come from their own stream so they don't shift the game's shuffles
+    let mut rng = ChaCha12Rng::seed_from_u64(seed);
+    result.outcome = loop {
+        let status = game.eval_statement();
+        if let Err(e) = game.check_card_count() {
+            break Outcome::BrokenCardCount(e.to_string());
+        }
+        match status {
+            Ok(EngineStatus::Finished) => break Outcome::Finished(game.get_winners()),
+            Ok(EngineStatus::Blocked) => {
+                if result.answers >= max_answers {
+                    break Outcome::NeverFinished;
+                }
+                if !answer_randomly(&mut game, players, &mut rng) {
+                    break Outcome::Stuck;
+                }
+                result.answers += 1;
+            }
+            Ok(_) => (),
+            // Engine rolled the answer back, the offer gets answered again
+            Err(GameError::Recoverable(RecoverableGameError::BrokenZoneRule { .. })) => (),
+            Err(GameError::Fatal(FatalGameError::Runtime(
+                e @ (RuntimeError::StatementLimit(_) | RuntimeError::RunawayWhile(_)),
+            ))) => break Outcome::LoopedForever(e.to_string()),
+            Err(e) => break Outcome::Crashed(describe_error(&e)),
+        }
+    };
+    result
+}
+
+fn describe_error(error: &GameError) -> String {
+    match error {
+        GameError::Fatal(e) => e.to_string(),
+        GameError::Recoverable(e) => e.to_string(),
+    }
+}
+
+fn answer_randomly(game: &mut Game, players: u32, rng: &mut ChaCha12Rng) -> bool {
+    let offered = match game.pending_offer() {
+        Some(pending) => pending.players.clone(),
+        None => return true,
+    };
+    let mut views = Vec::new();
+    for player in offered {
+        if let Some(view) = game.describe_offer(player) {
+            views.push((player, view));
+        }
+    }
+
+    for _ in 0..ANSWER_ATTEMPTS {
+        let Some((player, view)) = views.choose(rng) else {
+            return false;
+        };
+        let Some(case) = view.cases.choose(rng) else {
+            continue;
+        };
+        let choices = case
+            .choices
+            .iter()
+            .map(|choice| random_choice(choice, players, rng))
+            .collect();
+        let response = OfferResponse {
+            case: case.index,
+            choices,
+        };
+        if game.respond_to_offer(*player, &response).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+// Options that depend on earlier choices aren't known up front, those get a guess
+fn random_choice(choice: &ChoiceView, players: u32, rng: &mut ChaCha12Rng) -> ChoiceValue {
+    let all_players: Vec<PlayerOrderIndex> = (0..players as PlayerOrderIndex).collect();
+    match choice {
+        ChoiceView::Player { options, .. } => {
+            let options = options.as_ref().unwrap_or(&all_players);
+            ChoiceValue::Player(options.choose(rng).copied().unwrap_or(0))
+        }
+        ChoiceView::Players { options, .. } => {
+            ChoiceValue::Players(random_subset(options.as_ref().unwrap_or(&all_players), rng))
+        }
+        ChoiceView::Card { options, .. } => {
+            let options = options.clone().unwrap_or_default();
+            ChoiceValue::Card(options.choose(rng).copied().unwrap_or(0))
+        }
+        ChoiceView::Cards { options, .. } => {
+            ChoiceValue::Cards(random_subset(&options.clone().unwrap_or_default(), rng))
+        }
+        ChoiceView::MoveCards { options, .. } => {
+            ChoiceValue::Cards(random_subset(&options.clone().unwrap_or_default(), rng))
+        }
+    }
+}
+
+fn random_subset<T: Copy>(options: &[T], rng: &mut ChaCha12Rng) -> Vec<T> {
+    options
+        .iter()
+        .filter(|_| rng.random_bool(0.5))
+        .copied()
+        .collect()
+}
+
+impl SimulationReport {
+    pub fn finished(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Finished(_)))
+    }
+
+    // Everything that isn't a finished game
+    pub fn failures(&self) -> impl Iterator<Item = &GameResult> {
+        self.results
+            .iter()
+            .filter(|result| !matches!(result.outcome, Outcome::Finished(_)))
+    }
+
+    // Games each player won, shared wins count for everyone involved
+    pub fn wins(&self) -> BTreeMap<PlayerOrderIndex, usize> {
+        let mut wins: BTreeMap<PlayerOrderIndex, usize> = (0..self.players as PlayerOrderIndex)
+            .map(|p| (p, 0))
+            .collect();
+        for result in self.results.iter() {
+            if let Outcome::Finished(winners) = &result.outcome {
+                for winner in winners {
+                    *wins.entry(*winner).or_default() += 1;
+                }
+            }
+        }
+        wins
+    }
+
+    fn count(&self, matches: impl Fn(&Outcome) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|result| matches(&result.outcome))
+            .count()
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.results.len();
+        let finished = self.finished();
+        let answers: u64 = self.results.iter().map(|r| r.answers as u64).sum();
+        writeln!(f, "Played {total} games with {} players", self.players)?;
+        writeln!(f, "  finished:            {finished}")?;
+        for label in [
+            "crashed",
+            "looped forever",
+            "stuck on an offer",
+            "never finished",
+            "broke card count",
+        ] {
+            let count = self.count(|outcome| outcome.label() == label);
+            writeln!(f, "  {:<21}{count}", format!("{label}:"))?;
+        }
+        if total > 0 {
+            writeln!(
+                f,
+                "  answers per game:    {:.1}",
+                answers as f64 / total as f64
+            )?;
+        }
+
+        writeln!(f, "Wins")?;
+        let no_winner = self.count(|o| matches!(o, Outcome::Finished(w) if w.is_empty()));
+        for (player, wins) in self.wins() {
+            let share = match finished {
+                0 => 0.0,
+                _ => wins as f64 * 100.0 / finished as f64,
+            };
+            writeln!(f, "  player {player}: {wins} ({share:.1}%)")?;
+        }
+        writeln!(f, "  nobody:   {no_winner}")?;
+
+        // Same problem tends to come up in many games, list each once with a seed to reproduce it
+        let mut problems: HashMap<String, (usize, u64)> = HashMap::new();
+        for result in self.failures() {
+            let description = match &result.outcome {
+                Outcome::Crashed(e) => format!("crashed: {e}"),
+                Outcome::LoopedForever(e) => e.clone(),
+                Outcome::Stuck => "no random answer was accepted".into(),
+                Outcome::NeverFinished => "still going after the answer limit".into(),
+                Outcome::BrokenCardCount(e) => format!("card count broken: {e}"),
+                Outcome::Finished(_) => continue,
+            };
+            let entry = problems.entry(description).or_insert((0, result.seed));
+            entry.0 += 1;
+        }
+        let mut problems: Vec<_> = problems.into_iter().collect();
+        problems.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(&b.0)));
+        if !problems.is_empty() {
+            writeln!(f, "Problems")?;
+        }
+        for (description, (count, seed)) in problems {
+            writeln!(f, "  {count}x {description} (e.g. seed {seed})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+
+    const HEADER: &str = r#"
+        players 2..4;
+        ranks Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        suits Hearts, Diamonds, Spades, Clubs;
+        assignment Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        zone class Pile { owner hidden; others hidden; }
+        player zone hand: Hand;
+        zone deck: Pile;
+        zone table: Pile;
+        start setup;
+    "#;
+
+    // First to empty their hand wins, drawing keeps it going so games vary in length
+    const SHEDDING: &str = r#"
+        phase setup {
+            generate all cards into deck;
+            shuffle zone deck;
+            deal 3 from deck to every Hand;
+            enter turn;
+        }
+        phase turn {
+            offer to current player {
+                case "Play" choices card picked from all in hand of current player
+                handle move card picked to table;
+                case "Draw" handle move top of deck to hand of current player;
+            }
+            if count all in hand of current player == 0 declare winner current player;
+            advance 1;
+            enter turn;
+        }
+    "#;
+
+    fn config(phases: &str) -> config::GameConfig {
+        dsl::parse_config(&format!("{HEADER}{phases}")).expect("test ruleset parses")
+    }
+
+    fn options(games: u32, max_answers: u32) -> SimulationOptions {
+        SimulationOptions {
+            games,
+            players: 3,
+            first_seed: 7,
+            max_answers,
+        }
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_games() {
+        let config = config(SHEDDING);
+        let first = simulate(&config, &options(20, 200));
+        let second = simulate(&config, &options(20, 200));
+        assert!(first.finished() > 0, "{first}");
+        assert_eq!(format!("{first:?}"), format!("{second:?}"));
+        assert_eq!(first.to_string(), second.to_string());
+
+        let again = play_random_game(&config, 3, first.results[4].seed, 200);
+        assert_eq!(format!("{again:?}"), format!("{:?}", first.results[4]));
+    }
+
+    #[test]
+    fn runaway_loop_is_looped_forever() {
+        let config = config("phase setup { while true pass; }");
+        let result = play_random_game(&config, 2, 1, 10);
+        assert!(
+            matches!(result.outcome, Outcome::LoopedForever(_)),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn phases_going_round_in_circles_are_looped_forever() {
+        let config = config("phase setup { enter again; } phase again { enter setup; }");
+        let result = play_random_game(&config, 2, 1, 10);
+        assert!(
+            matches!(result.outcome, Outcome::LoopedForever(_)),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn endless_offers_never_finish() {
+        let config = config(
+            r#"phase setup {
+                offer to current player { case "Again" handle enter setup; }
+            }"#,
+        );
+        let result = play_random_game(&config, 2, 1, 10);
+        assert!(
+            matches!(result.outcome, Outcome::NeverFinished),
+            "{result:?}"
+        );
+        assert_eq!(result.answers, 10);
+    }
+
+    #[test]
+    fn offer_nobody_can_answer_is_stuck() {
+        let config = config(
+            r#"phase setup {
+                offer to current player {
+                    case "Pick" choices card picked from all in deck handle pass;
+                }
+            }"#,
+        );
+        let result = play_random_game(&config, 2, 1, 10);
+        assert!(matches!(result.outcome, Outcome::Stuck), "{result:?}");
+    }
+}
@@ -0,0 +1,46 @@
+use super::execution_state::OfferResponse;
+use super::game_state::{CardID, GameZoneID, PlayerOrderIndex};
+use crate::engine::core::types::identifiers::PhaseIdentifier;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// Something that changed the game. Started and OfferAnswered are the only inputs, replaying
+// them against the same config and seed has to produce the rest of the log again
+#[derive(TS, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[ts(export)]
+pub enum GameEvent {
+    Started {
+        players: u32,
+    },
+    CardsMoved {
+        cards: Vec<CardID>,
+        to: GameZoneID,
+    },
+    ZoneShuffled {
+        zone: GameZoneID,
+    },
+    PhaseEntered {
+        phase: PhaseIdentifier,
+    },
+    OfferAnswered {
+        player: PlayerOrderIndex,
+        response: OfferResponse,
+    },
+    Finished {
+        winners: Vec<PlayerOrderIndex>,
+    },
+    // The room gave up on the game, nothing comes after this
+    Crashed {
+        error: String,
+    },
+}
+
+impl GameEvent {
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            GameEvent::Started { .. } | GameEvent::OfferAnswered { .. }
+        )
+    }
+}
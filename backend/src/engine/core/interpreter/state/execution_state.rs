@@ -1,22 +1,106 @@
 use super::{game_state, var_state};
 use crate::engine::core::interpreter::{config, lang::statements};
+use game_state::{CardID, GameZoneID, PlayerOrderIndex};
 use statements::Statement;
 use thiserror::Error;
 
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
 use std::sync::Arc;
-#[derive(Debug, Clone)]
+
+// Statements a ruleset can run between waits on players before it's treated as stuck
+pub const STATEMENT_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockContext {
     exec_idx: u32,                   //keep private
     statements: Vec<Arc<Statement>>, //keep private
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionState {
-    pub statements_evaluated: u32,
+    pub statements_evaluated: u32, // Since the game last waited on players
     pub statement_limit: u32,
+    #[serde(default)]
+    pub running_loops: Vec<RunningLoop>, // Outermost first
     pub statement_stack: Vec<ExecutionContext>,
     pub current_root: Arc<Statement>,
     pub root_vars: var_state::RootVarMapping,
+    pub pending_offer: Option<PendingOffer>,
+}
+
+// A while loop that hasn't finished, depth is the stack height its statement sits at
+// Iterations and started_at only count since the game last waited on players
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningLoop {
+    pub depth: usize,
+    pub iterations: u32,
+    pub started_at: u32,
+}
+
+// An offer the interpreter is suspended on, the offer statement stays on top of the stack until
+// one of the players answers it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOffer {
+    pub offer: statements::Offer,
+    pub players: Vec<PlayerOrderIndex>,
+}
+
+// Value picked by a player for a single OfferChoice
+#[derive(TS, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[ts(export)]
+pub enum ChoiceValue {
+    Player(PlayerOrderIndex),
+    Players(Vec<PlayerOrderIndex>),
+    Card(CardID),
+    Cards(Vec<CardID>),
+}
+
+#[derive(TS, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[ts(export)]
+pub struct OfferResponse {
+    pub case: usize,
+    pub choices: Vec<ChoiceValue>, // One for each choice of the picked case, in order
+}
+
+// What a single player is offered, only cases whose condition holds are listed
+#[derive(Debug, Clone)]
+pub struct OfferView {
+    pub cases: Vec<CaseView>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseView {
+    pub index: usize, // Index into the offer's cases, used when answering
+    pub message: String,
+    pub choices: Vec<ChoiceView>,
+}
+
+// Options are None when they depend on an earlier choice of the same case
+#[derive(Debug, Clone)]
+pub enum ChoiceView {
+    Player {
+        name: String,
+        options: Option<Vec<PlayerOrderIndex>>,
+    },
+    Players {
+        name: String,
+        options: Option<Vec<PlayerOrderIndex>>,
+    },
+    Card {
+        name: String,
+        options: Option<Vec<CardID>>,
+    },
+    Cards {
+        name: String,
+        options: Option<Vec<CardID>>,
+    },
+    MoveCards {
+        from: Option<GameZoneID>,
+        to: Option<GameZoneID>,
+        options: Option<Vec<CardID>>, // Whatever is in the from zone
+    },
 }
 
 impl BlockContext {
@@ -52,13 +136,13 @@ impl BlockContext {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StatementPointer {
     Single(Arc<Statement>),
     Block(BlockContext),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionContext {
     pub location: StatementPointer,
     pub variables: var_state::VarMapping,
@@ -89,10 +173,12 @@ impl ExecutionState {
     pub fn new(current_root: Arc<Statement>) -> Self {
         let mut r = Self {
             statements_evaluated: 0,
-            statement_limit: 1000,
+            statement_limit: STATEMENT_LIMIT,
+            running_loops: Vec::new(),
             statement_stack: Vec::new(),
             current_root,
             root_vars: var_state::RootVarMapping::new(),
+            pending_offer: None,
         };
         r.push_statement(r.current_root.clone());
         r
@@ -169,23 +255,33 @@ impl ExecutionState {
         }
     }
 
+    // Start executing the block on top of the stack, a block that is the root of its context
+    // takes over that context so any variables bound there stay visible
+    pub fn enter_block(&mut self) -> Result<(), ExecutionStateError> {
+        match self.statement_stack.last().map(|ctx| &ctx.location) {
+            None => Err(ExecutionStateError::OutOfStatements),
+            Some(StatementPointer::Single(_)) => self.upgrade_statement(),
+            Some(StatementPointer::Block(_)) => {
+                let block = self
+                    .get_current_statement()
+                    .ok_or(ExecutionStateError::OutOfStatements)?;
+                self.incr_and_push(block, 1)?;
+                self.upgrade_statement()
+            }
+        }
+    }
+
     //this is only really intended for incr = 0 and 1, effects the top of stack only
     pub fn incr_current(&mut self, incr: u32) -> Result<(), ExecutionStateError> {
         if incr == 0 {
             return Ok(());
         }
         let mut pop_stack: bool = false;
-        let _ = self.upgrade_statement();
         match self.statement_stack.last_mut() {
             None => return Ok(()),
             Some(sp) => {
                 match &mut sp.location {
-                    StatementPointer::Single(stmt) => {
-                        if let Statement::Block(_) = &*stmt.clone() {
-                            return Err(ExecutionStateError::WrongStatementPointer);
-                        }
-                        pop_stack = true;
-                    }
+                    StatementPointer::Single(_) => pop_stack = true,
                     StatementPointer::Block(blk) => blk.incr(incr),
                 };
             }
@@ -211,4 +307,64 @@ impl ExecutionState {
         self.statement_stack
             .push(ExecutionContext::new(StatementPointer::Single(statement)));
     }
+
+    // Push statements that should see the given variables for as long as they run
+    pub fn push_scope(&mut self, statement: Arc<Statement>, variables: var_state::VarMapping) {
+        self.statement_stack.push(ExecutionContext {
+            location: StatementPointer::Block(BlockContext::new(vec![statement])),
+            variables,
+        });
+    }
+
+    // Players got a say, the ruleset gets a fresh budget
+    pub fn reset_budget(&mut self) {
+        self.statements_evaluated = 0;
+        for running in self.running_loops.iter_mut() {
+            running.iterations = 0;
+            running.started_at = 0;
+        }
+    }
+
+    // The while loop on top of the stack is about to run its body again
+    pub fn loop_again(&mut self) {
+        let depth = self.statement_stack.len();
+        self.running_loops.retain(|running| running.depth <= depth);
+        match self.running_loops.last_mut() {
+            Some(running) if running.depth == depth => running.iterations += 1,
+            _ => self.running_loops.push(RunningLoop {
+                depth,
+                iterations: 1,
+                started_at: self.statements_evaluated,
+            }),
+        }
+    }
+
+    // The while loop on top of the stack is done
+    pub fn loop_finished(&mut self) {
+        let depth = self.statement_stack.len();
+        self.running_loops.retain(|running| running.depth < depth);
+    }
+
+    // Abandon everything currently running and start from a new root
+    pub fn enter_root(&mut self, root: Arc<Statement>) {
+        self.statement_stack.clear();
+        self.running_loops.clear();
+        self.pending_offer = None;
+        self.current_root = root;
+        self.push_statement(self.current_root.clone());
+    }
+
+    pub fn clear(&mut self) {
+        self.statement_stack.clear();
+        self.running_loops.clear();
+        self.pending_offer = None;
+    }
+
+    // Innermost binding wins
+    pub fn lookup<T>(&self, get: impl Fn(&var_state::VarMapping) -> Option<&T>) -> Option<&T> {
+        self.statement_stack
+            .iter()
+            .rev()
+            .find_map(|ctx| get(&ctx.variables))
+    }
 }
@@ -1,9 +1,9 @@
-use crate::engine::core::interpreter::config;
+use crate::engine::core::interpreter::{config, validation};
 use crate::engine::core::types::identifiers::*;
 use crate::engine::core::types::*;
 
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
@@ -15,13 +15,13 @@ pub type GameZoneID = u64;
 pub type CardID = u64;
 pub type PlayerOrderIndex = u64;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ZoneName {
     Variable(VariableIdentifier),
     Template(VariableIdentifier),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameActiveZone {
     pub zone_id: GameZoneID,
     pub cards: Vec<u64>,
@@ -48,11 +48,21 @@ impl GameActiveZone {
     }
 }
 
+// A zone as one player sees it, cards they can't see are None
+#[derive(Debug, Clone)]
+pub struct ZoneView {
+    pub zone_id: GameZoneID,
+    pub owner: Option<PlayerOrderIndex>,
+    pub name: Option<VariableIdentifier>,
+    pub cards: Vec<Option<(CardID, cards::Card)>>,
+}
+
 #[derive(TS, Debug, Clone, Deserialize, Serialize)]
 #[ts(export)]
 pub enum GameStatus {
     Waiting(GameWaitingStatus),
     Playing,
+    Finished,
     Invalid,
 }
 
@@ -63,13 +73,29 @@ pub enum GameWaitingStatus {
     Ready,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CardState {
+    #[serde(skip, default = "detached_config")]
     config: Arc<config::GameConfig>,
     zones: HashMap<GameZoneID, GameActiveZone>,
     cards: HashMap<CardID, cards::Card>,
     zones_created: GameZoneID,
     cards_created: u64,
+    #[serde(skip)]
+    movements: CardMovements,
+}
+
+// Checkpoints don't carry the config, whoever restores one puts the real one back
+fn detached_config() -> Arc<config::GameConfig> {
+    Arc::new(config::GameConfig::blank())
+}
+
+// Every zone a card entered and every card shuffled since the last time these were taken, lets
+// snapshot builders notice cards that passed through zones a viewer couldn't see
+#[derive(Debug, Clone, Default)]
+pub struct CardMovements {
+    pub entered: HashMap<CardID, HashSet<GameZoneID>>,
+    pub shuffled: HashSet<CardID>,
 }
 
 impl CardState {
@@ -80,6 +106,7 @@ impl CardState {
             cards: HashMap::new(),
             zones_created: 0,
             cards_created: 0,
+            movements: CardMovements::default(),
         }
     }
 }
@@ -126,6 +153,293 @@ impl CardState {
         card_id
     }
 
+    pub fn get_zone(&self, zone_id: GameZoneID) -> Result<&GameActiveZone, StateModifyError> {
+        self.zones
+            .get(&zone_id)
+            .ok_or(StateModifyError::MissingResource(
+                ResourceReferenceError::Zone(zone_id),
+            ))
+    }
+
+    pub fn get_card(&self, card_id: CardID) -> Result<cards::Card, StateModifyError> {
+        self.cards
+            .get(&card_id)
+            .copied()
+            .ok_or(StateModifyError::MissingResource(
+                ResourceReferenceError::Card(card_id),
+            ))
+    }
+
+    pub fn zone_of_card(&self, card_id: CardID) -> Option<GameZoneID> {
+        self.zones
+            .values()
+            .find(|zone| zone.cards.contains(&card_id))
+            .map(|zone| zone.zone_id)
+    }
+
+    // Every card that exists is in exactly one zone, and zones only hold cards that exist
+    pub fn check_card_count(&self) -> Result<(), CardCountError> {
+        let mut zone_ids: Vec<&GameZoneID> = self.zones.keys().collect();
+        zone_ids.sort();
+
+        let mut seen: HashMap<CardID, GameZoneID> = HashMap::new();
+        for zone in zone_ids.into_iter().map(|id| &self.zones[id]) {
+            for card_id in zone.cards.iter() {
+                if !self.cards.contains_key(card_id) {
+                    return Err(CardCountError::Unknown(*card_id, zone.zone_id));
+                }
+                if let Some(other) = seen.insert(*card_id, zone.zone_id) {
+                    return Err(CardCountError::Duplicated(*card_id, other, zone.zone_id));
+                }
+            }
+        }
+        let mut lost: Vec<&CardID> = self
+            .cards
+            .keys()
+            .filter(|c| !seen.contains_key(c))
+            .collect();
+        lost.sort();
+        match lost.first() {
+            Some(card_id) => Err(CardCountError::Lost(**card_id)),
+            None => Ok(()),
+        }
+    }
+
+    // Sorted so that anything iterating over the result stays deterministic
+    pub fn zones_of_class(&self, class: &ZoneClassIdentifier) -> Vec<GameZoneID> {
+        let mut result: Vec<GameZoneID> = self
+            .zones
+            .values()
+            .filter(|zone| &zone.class == class)
+            .map(|zone| zone.zone_id)
+            .collect();
+        result.sort();
+        result
+    }
+
+    pub fn zone_owned_by(&self, owner: PlayerOrderIndex, name: &str) -> Option<GameZoneID> {
+        self.zones
+            .values()
+            .find(|zone| {
+                zone.owner == Some(owner)
+                    && matches!(&zone.name, Some(ZoneName::Template(n)) if n == name)
+            })
+            .map(|zone| zone.zone_id)
+    }
+
+    // Moves cards (in the given order) onto the top of dest, taking them out of whatever zone
+    // they were in before. Nothing is moved if any zone involved would break its rules
+    pub fn move_cards(
+        &mut self,
+        cards: &[CardID],
+        dest: GameZoneID,
+    ) -> Result<(), StateModifyError> {
+        self.get_zone(dest)?;
+        for card_id in cards {
+            self.get_card(*card_id)?;
+        }
+
+        let mut changed: HashMap<GameZoneID, Vec<CardID>> = HashMap::new();
+        for (idx, card_id) in cards.iter().enumerate() {
+            if cards[..idx].contains(card_id) {
+                continue;
+            }
+            if let Some(source) = self.zone_of_card(*card_id) {
+                changed
+                    .entry(source)
+                    .or_insert_with(|| self.zones[&source].cards.clone())
+                    .retain(|c| c != card_id);
+            }
+            changed
+                .entry(dest)
+                .or_insert_with(|| self.zones[&dest].cards.clone())
+                .push(*card_id);
+        }
+
+        let mut zone_ids: Vec<GameZoneID> = changed.keys().copied().collect();
+        zone_ids.sort();
+        for zone_id in zone_ids {
+            self.check_zone_rules(zone_id, &changed[&zone_id])?;
+        }
+
+        for card_id in cards {
+            self.movements
+                .entered
+                .entry(*card_id)
+                .or_default()
+                .insert(dest);
+        }
+        for (zone_id, cards) in changed {
+            if let Some(zone) = self.zones.get_mut(&zone_id) {
+                zone.cards = cards;
+            }
+        }
+        Ok(())
+    }
+
+    // None if the pattern doesn't exist
+    pub fn match_pattern(
+        &self,
+        pattern: &patterns::PatternIdentifier,
+        cards: &[CardID],
+    ) -> Option<patterns::MatchReport> {
+        let pattern = self.config.patterns.get(pattern)?;
+        Some(
+            patterns::Matcher::new(&self.cards, pattern, &self.config.orders, cards)
+                .match_patterns(),
+        )
+    }
+
+    // None if the pattern doesn't exist
+    pub fn find_pattern(
+        &self,
+        pattern: &patterns::PatternIdentifier,
+        cards: &[CardID],
+    ) -> Option<Result<Vec<CardID>, patterns::SearchExhausted>> {
+        let pattern = self.config.patterns.get(pattern)?;
+        Some(patterns::find_match(
+            &self.cards,
+            pattern,
+            &self.config.orders,
+            cards,
+        ))
+    }
+
+    // Checks if a zone would be allowed to hold these cards, they have to match at least one of
+    // the zone class's rules. Zones without rules and empty zones always pass
+    pub fn check_zone_rules(
+        &self,
+        zone_id: GameZoneID,
+        cards: &[CardID],
+    ) -> Result<(), StateModifyError> {
+        let zone = self.get_zone(zone_id)?;
+        let rules = match self.config.zone_classes.get(&zone.class) {
+            Some(class) => &class.rules,
+            None => return Ok(()),
+        };
+        if rules.is_empty() || cards.is_empty() {
+            return Ok(());
+        }
+
+        let mut reports = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let Some(rule_patterns) = self.config.patterns.get(rule) else {
+                continue;
+            };
+            let report =
+                patterns::Matcher::new(&self.cards, rule_patterns, &self.config.orders, cards)
+                    .match_patterns();
+            if report.is_match() {
+                return Ok(());
+            }
+            reports.push(RuleReport {
+                rule: rule.clone(),
+                report,
+            });
+        }
+        Err(ZoneRuleViolation {
+            zone: zone_id,
+            reports,
+        }
+        .into())
+    }
+
+    pub fn take_movements(&mut self) -> CardMovements {
+        std::mem::take(&mut self.movements)
+    }
+
+    // Zones without an owner always use the others rule
+    pub fn zone_visibility(
+        &self,
+        zone_id: GameZoneID,
+        viewer: Option<PlayerOrderIndex>,
+    ) -> zones::ZoneVisibilityRule {
+        let Some(zone) = self.zones.get(&zone_id) else {
+            return zones::ZoneVisibilityRule::Hidden;
+        };
+        match self.config.zone_classes.get(&zone.class) {
+            Some(class) if zone.owner.is_some() && zone.owner == viewer => {
+                class.visibility.owner.clone()
+            }
+            Some(class) => class.visibility.others.clone(),
+            None => zones::ZoneVisibilityRule::Hidden,
+        }
+    }
+
+    // What a player (or a spectator, None) is allowed to see of every zone, ordered by zone id
+    pub fn visible_zones(&self, viewer: Option<PlayerOrderIndex>) -> Vec<ZoneView> {
+        self.zone_views(|zone_id| self.zone_visibility(zone_id, viewer))
+    }
+
+    // Every card face up, for replays of games that are already over
+    pub fn revealed_zones(&self) -> Vec<ZoneView> {
+        self.zone_views(|_| zones::ZoneVisibilityRule::Visible)
+    }
+
+    fn zone_views(
+        &self,
+        rule_for: impl Fn(GameZoneID) -> zones::ZoneVisibilityRule,
+    ) -> Vec<ZoneView> {
+        let mut zone_ids: Vec<&GameZoneID> = self.zones.keys().collect();
+        zone_ids.sort();
+
+        let mut result = Vec::with_capacity(zone_ids.len());
+        for zone in zone_ids.into_iter().map(|id| &self.zones[id]) {
+            let rule = rule_for(zone.zone_id);
+            let last = zone.cards.len().saturating_sub(1);
+            let cards = zone
+                .cards
+                .iter()
+                .enumerate()
+                .map(|(idx, card_id)| {
+                    let shown = match &rule {
+                        zones::ZoneVisibilityRule::Visible => true,
+                        zones::ZoneVisibilityRule::Hidden => false,
+                        zones::ZoneVisibilityRule::Top => idx == last,
+                        zones::ZoneVisibilityRule::Bottom => idx == 0,
+                    };
+                    match self.cards.get(card_id) {
+                        Some(card) if shown => Some((*card_id, *card)),
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            result.push(ZoneView {
+                zone_id: zone.zone_id,
+                owner: zone.owner,
+                name: zone.name.as_ref().map(|name| match name {
+                    ZoneName::Variable(n) | ZoneName::Template(n) => n.clone(),
+                }),
+                cards,
+            });
+        }
+        result
+    }
+
+    // Top of a zone is the end of its card list
+    pub fn top_card(&self, zone_id: GameZoneID) -> Result<Option<CardID>, StateModifyError> {
+        Ok(self.get_zone(zone_id)?.cards.last().copied())
+    }
+
+    pub fn shuffle_zone<R: rand::Rng + ?Sized>(
+        &mut self,
+        zone_id: GameZoneID,
+        rng: &mut R,
+    ) -> Result<(), StateModifyError> {
+        use rand::seq::SliceRandom;
+        match self.zones.get_mut(&zone_id) {
+            Some(zone) => {
+                zone.cards.shuffle(rng);
+                self.movements.shuffled.extend(zone.cards.iter().copied());
+                Ok(())
+            }
+            None => Err(StateModifyError::MissingResource(
+                ResourceReferenceError::Zone(zone_id),
+            )),
+        }
+    }
+
     pub fn new_cardset(&mut self, set: &cards::CardSet) -> Vec<CardID> {
         let mut result = Vec::with_capacity(set.ranks.len() * set.suits.len());
         for suit in set.suits.iter() {
@@ -140,13 +454,16 @@ impl CardState {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
+    #[serde(skip, default = "detached_config")]
     pub config: Arc<config::GameConfig>,
     pub status: GameStatus,
 
     pub players: Vec<PlayerClassIdentifier>,
     pub cards: CardState,
+    pub current_player: PlayerOrderIndex,
+    pub winners: Vec<PlayerOrderIndex>,
 }
 
 #[derive(Error, Debug, Clone)]
@@ -163,6 +480,34 @@ pub enum StateModifyError {
     Init(#[from] InitError),
     #[error("Bad resource reference")]
     MissingResource(#[from] ResourceReferenceError),
+    #[error("Move breaks a zone rule")]
+    ZoneRule(#[from] ZoneRuleViolation),
+}
+
+// Why a zone refused its new cards, with how far the cards got in each of its rules
+#[derive(TS, Error, Debug, Clone, Serialize, Deserialize)]
+#[ts(export)]
+#[error("Cards in zone {zone} would not match any of its rules")]
+pub struct ZoneRuleViolation {
+    pub zone: GameZoneID,
+    pub reports: Vec<RuleReport>,
+}
+
+#[derive(TS, Debug, Clone, Serialize, Deserialize)]
+#[ts(export)]
+pub struct RuleReport {
+    pub rule: patterns::PatternIdentifier,
+    pub report: patterns::MatchReport,
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum CardCountError {
+    #[error("Card {0} exists but isn't in any zone")]
+    Lost(CardID),
+    #[error("Card {0} is in both zone {1} and zone {2}")]
+    Duplicated(CardID, GameZoneID, GameZoneID),
+    #[error("Zone {1} holds card {0} which doesn't exist")]
+    Unknown(CardID, GameZoneID),
 }
 
 #[derive(Error, Debug, Clone)]
@@ -181,6 +526,8 @@ pub enum ResourceReferenceError {
     Card(CardID),
     #[error("Bad zone reference: {0}")]
     Zone(GameZoneID),
+    #[error("No player of class {0} exists")]
+    PlayerClass(PlayerClassIdentifier),
 }
 
 impl GameState {
@@ -190,12 +537,27 @@ impl GameState {
             players: Vec::new(),
             status: GameStatus::Waiting(GameWaitingStatus::NotReady),
             cards: CardState::new(config.clone()),
+            current_player: 0,
+            winners: Vec::new(),
         }
     }
 
-    //TODO
-    pub fn check_config(&self) -> Result<(), String> {
-        Ok(())
+    // Used after restoring a checkpoint, which leaves the config out
+    pub fn attach_config(&mut self, config: Arc<config::GameConfig>) {
+        self.cards.config = config.clone();
+        self.config = config;
+    }
+
+    pub fn check_config(&self) -> Result<(), Vec<validation::ConfigProblem>> {
+        let problems: Vec<validation::ConfigProblem> = validation::validate_config(&self.config)
+            .into_iter()
+            .filter(|p| p.is_error())
+            .collect();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
     }
 
     // Assign players roles depending on class order
@@ -261,6 +623,55 @@ impl GameState {
         Err("Game already started".into())
     }
 
+    pub fn player_count(&self) -> u64 {
+        self.players.len() as u64
+    }
+
+    pub fn check_player(&self, player: PlayerOrderIndex) -> Result<(), StateModifyError> {
+        if player < self.player_count() {
+            Ok(())
+        } else {
+            Err(StateModifyError::MissingResource(
+                ResourceReferenceError::Player(player),
+            ))
+        }
+    }
+
+    // Player `delta` places after the current one, wrapping around in both directions
+    pub fn advance_player(&mut self, delta: i32) -> Result<(), StateModifyError> {
+        self.check_player(self.current_player)?;
+        let count = self.player_count() as i64;
+        self.current_player = (self.current_player as i64 + delta as i64).rem_euclid(count) as u64;
+        Ok(())
+    }
+
+    // Same as advance_player but only counts players of the given class
+    pub fn advance_player_by_class(
+        &mut self,
+        delta: i32,
+        class: &PlayerClassIdentifier,
+    ) -> Result<(), StateModifyError> {
+        self.check_player(self.current_player)?;
+        if !self.players.contains(class) {
+            return Err(StateModifyError::MissingResource(
+                ResourceReferenceError::PlayerClass(class.clone()),
+            ));
+        }
+        let step = delta.signum();
+        for _ in 0..delta.unsigned_abs() {
+            self.advance_player(step)?;
+            while &self.players[self.current_player as usize] != class {
+                self.advance_player(step)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn declare_winners(&mut self, winners: Vec<PlayerOrderIndex>) {
+        self.winners = winners;
+        self.status = GameStatus::Finished;
+    }
+
     pub fn game_ready(&self) -> bool {
         if let GameStatus::Waiting(GameWaitingStatus::Ready) = self.status {
             return true;
@@ -286,8 +697,10 @@ impl GameState {
         if self.game_ready() {
             self.status = GameStatus::Playing;
 
-            //Create initial zones
-            for (name, class) in self.config.initial_zones.iter() {
+            //Create initial zones, sorted so zone ids don't depend on hash map iteration
+            let mut initial_zones: Vec<_> = self.config.initial_zones.iter().collect();
+            initial_zones.sort();
+            for (name, class) in initial_zones {
                 match self.cards.create_zone(
                     Vec::new(),
                     &class,
@@ -304,7 +717,9 @@ impl GameState {
             //Set up player zones
             for (idx, player_class_name) in self.players.iter().enumerate() {
                 if let Some(player_class) = self.config.player_classes.get(player_class_name) {
-                    for zone_name in player_class.active_zones.iter() {
+                    let mut zone_names: Vec<_> = player_class.active_zones.iter().collect();
+                    zone_names.sort();
+                    for zone_name in zone_names {
                         if let Some(zone_class_name) = self.config.player_zones.get(zone_name) {
                             match self.cards.create_zone(
                                 Vec::new(),
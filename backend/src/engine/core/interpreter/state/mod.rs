@@ -1,3 +1,4 @@
+pub mod event_log;
 pub mod execution_state;
 pub mod game_state;
 pub mod var_state;
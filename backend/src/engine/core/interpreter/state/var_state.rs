@@ -1,17 +1,19 @@
 use super::game_state;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VarMapping {
     pub zone: HashMap<String, game_state::GameZoneID>,
     pub zone_collection: HashMap<String, Vec<game_state::GameZoneID>>,
     pub player: HashMap<String, game_state::PlayerOrderIndex>,
-    pub player_collection: HashMap<String, game_state::PlayerOrderIndex>,
+    pub player_collection: HashMap<String, Vec<game_state::PlayerOrderIndex>>,
     pub card: HashMap<String, game_state::CardID>,
     pub card_collection: HashMap<String, Vec<game_state::CardID>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RootVarMapping {
     pub zone: HashMap<String, game_state::GameZoneID>,
     pub number: HashMap<String, i32>,
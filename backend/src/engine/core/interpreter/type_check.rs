@@ -0,0 +1,491 @@
+use super::config::{ConfigError, GameConfig};
+use super::lang::{expressions, statements};
+use super::validation::{ConfigProblem, ProblemSeverity};
+use statements::Statement;
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableType {
+    Number,
+    Zone,
+    ZoneCollection,
+    Player,
+    PlayerCollection,
+    Card,
+    CardCollection,
+    Order,
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VariableType::Number => "number",
+            VariableType::Zone => "zone",
+            VariableType::ZoneCollection => "zone collection",
+            VariableType::Player => "player",
+            VariableType::PlayerCollection => "player collection",
+            VariableType::Card => "card",
+            VariableType::CardCollection => "card collection",
+            VariableType::Order => "order",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Checks every GetVariable in the phases refers to a variable of the right type that is in scope
+// where it's used. Offers are the only statements that bind variables, their player name and
+// selections are visible until the end of the chosen case's handler
+pub fn check_program(config: &GameConfig) -> Vec<ConfigProblem> {
+    let mut root = HashMap::new();
+    for name in &config.numbers {
+        root.insert(name.clone(), VariableType::Number);
+    }
+    for name in config.initial_zones.keys() {
+        root.insert(name.clone(), VariableType::Zone);
+    }
+    for name in config.orders.keys() {
+        root.insert(name.clone(), VariableType::Order);
+    }
+
+    let mut checker = TypeChecker {
+        path: vec!["phases".to_string()],
+        problems: Vec::new(),
+        scopes: vec![root],
+    };
+
+    let mut phases: Vec<&String> = config.phases.keys().collect();
+    phases.sort();
+    for name in phases {
+        checker.within(name, |c| {
+            c.within("evaluate", |c| {
+                c.check_statement(&config.phases[name].evaluate)
+            })
+        });
+    }
+    checker.problems
+}
+
+struct TypeChecker {
+    path: Vec<String>,
+    problems: Vec<ConfigProblem>,
+    scopes: Vec<HashMap<String, VariableType>>,
+}
+
+impl TypeChecker {
+    fn within(&mut self, segment: impl ToString, check: impl FnOnce(&mut Self)) {
+        self.path.push(segment.to_string());
+        check(self);
+        self.path.pop();
+    }
+
+    fn scoped(&mut self, check: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        check(self);
+        self.scopes.pop();
+    }
+
+    fn bind(&mut self, name: &str, var_type: VariableType) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), var_type);
+        }
+    }
+
+    fn expect(&mut self, name: &str, expected: VariableType) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied();
+        let error = match found {
+            Some(found) if found == expected => return,
+            Some(found) => ConfigError::MistypedVariable {
+                name: name.to_string(),
+                expected: expected.to_string(),
+                found: found.to_string(),
+            },
+            None if expected == VariableType::Number => {
+                ConfigError::UndeclaredNumber(name.to_string())
+            }
+            None => ConfigError::UndefinedVariable(name.to_string(), expected.to_string()),
+        };
+        self.problems.push(ConfigProblem {
+            path: self.path.clone(),
+            error,
+            severity: ProblemSeverity::Error,
+        });
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Empty | Statement::EnterPhase(_) => (),
+            Statement::Block(statements) => {
+                for (idx, s) in statements.iter().enumerate() {
+                    self.within(idx, |c| c.check_statement(s));
+                }
+            }
+            Statement::Conditional(cond) => {
+                self.within("condition", |c| c.check_bool(&cond.condition));
+                self.within("go_true", |c| c.check_statement(&cond.go_true));
+                self.within("go_false", |c| c.check_statement(&cond.go_false));
+            }
+            Statement::While { condition, r#do } => {
+                self.within("condition", |c| c.check_bool(condition));
+                self.within("do", |c| c.check_statement(r#do));
+            }
+            Statement::Broadcast { msg: _, to } => {
+                self.within("to", |c| c.check_player_collection(to));
+            }
+            Statement::DeclareWinner(players) => self.check_player_collection(players),
+            Statement::SetNumber { name: _, value } => {
+                self.within("value", |c| c.check_number(value));
+            }
+            Statement::AdvancePlayerStateByType {
+                to_advance,
+                type_name: _,
+            } => self.within("to_advance", |c| c.check_number(to_advance)),
+            Statement::AdvancePlayerState(to_advance) => self.check_number(to_advance),
+            Statement::MoveCardsTo { source, dest } => {
+                self.within("source", |c| c.check_card_collection(source));
+                self.within("dest", |c| c.check_zone(dest));
+            }
+            Statement::GenerateCards { cards: _, dest } => {
+                self.within("dest", |c| c.check_zone(dest));
+            }
+            Statement::Deal {
+                num_cards,
+                source,
+                dest,
+            } => {
+                self.within("num_cards", |c| c.check_number(num_cards));
+                self.within("source", |c| c.check_zone(source));
+                self.within("dest", |c| c.check_zone_collection(dest));
+            }
+            Statement::Shuffle(zones) => self.check_zone_collection(zones),
+            Statement::Offer(offer) => self.check_offer(offer),
+        }
+    }
+
+    fn check_offer(&mut self, offer: &statements::Offer) {
+        self.within("offer_to", |c| c.check_player_collection(&offer.offer_to));
+        self.scoped(|c| {
+            if let Some(name) = &offer.player_name {
+                c.bind(name, VariableType::Player);
+            }
+            c.within("cases", |c| {
+                for (idx, case) in offer.cases.iter().enumerate() {
+                    c.within(idx, |c| c.scoped(|c| c.check_offer_case(case)));
+                }
+            });
+        });
+    }
+
+    // Choices bind in order, so a choice can refer to the ones before it
+    fn check_offer_case(&mut self, case: &statements::OfferCase) {
+        if let Some(condition) = &case.condition {
+            self.within("condition", |c| c.check_bool(condition));
+        }
+        self.within("choices", |c| {
+            for (idx, choice) in case.choices.iter().enumerate() {
+                use statements::{ChoiceAction, ChoiceSelectionEnum, OfferChoice};
+                c.within(idx, |c| match choice {
+                    OfferChoice::Selection(selection) => {
+                        let bound = match &selection.choice_type {
+                            ChoiceSelectionEnum::Player(players) => {
+                                c.check_player_collection(players);
+                                VariableType::Player
+                            }
+                            ChoiceSelectionEnum::PlayerSelection(players) => {
+                                c.check_player_collection(players);
+                                VariableType::PlayerCollection
+                            }
+                            ChoiceSelectionEnum::Card(cards) => {
+                                c.check_card_collection(cards);
+                                VariableType::Card
+                            }
+                            ChoiceSelectionEnum::CardSelection(cards) => {
+                                c.check_card_collection(cards);
+                                VariableType::CardCollection
+                            }
+                        };
+                        c.bind(&selection.name, bound);
+                    }
+                    OfferChoice::Action(ChoiceAction::MoveCards { from, to }) => {
+                        c.within("from", |c| c.check_zone(from));
+                        c.within("to", |c| c.check_zone(to));
+                    }
+                });
+            }
+        });
+        self.within("handle", |c| c.check_statement(&case.handle));
+    }
+
+    fn check_number(&mut self, expr: &expressions::NumberExpression) {
+        use expressions::NumberExpression::*;
+        match expr {
+            Literal(_) => (),
+            GetVariable(name) => self.expect(name, VariableType::Number),
+            CardsIn(cards) => self.check_card_collection(cards),
+        }
+    }
+
+    fn check_bool(&mut self, expr: &expressions::BooleanExpression) {
+        use expressions::BooleanExpression::*;
+        match expr {
+            Literal(_) => (),
+            Comparison {
+                a,
+                compared_to: _,
+                b,
+            } => {
+                self.within("a", |c| c.check_number(a));
+                self.within("b", |c| c.check_number(b));
+            }
+            PlayerIsType {
+                player,
+                type_name: _,
+            } => self.within("player", |c| c.check_player(player)),
+            MatchesPattern { cards, pattern: _ } => {
+                self.within("cards", |c| c.check_card_collection(cards))
+            }
+        }
+    }
+
+    fn check_card(&mut self, expr: &expressions::CardExpression) {
+        use expressions::CardExpression::*;
+        match expr {
+            Create(suit, rank) => {
+                self.check_suit(suit);
+                self.check_rank(rank);
+            }
+            GetVariable(name) => self.expect(name, VariableType::Card),
+        }
+    }
+
+    fn check_suit(&mut self, expr: &expressions::SuitExpression) {
+        if let expressions::SuitExpression::FromCard(card) = expr {
+            self.check_card(card);
+        }
+    }
+
+    fn check_rank(&mut self, expr: &expressions::RankExpression) {
+        if let expressions::RankExpression::FromCard(card) = expr {
+            self.check_card(card);
+        }
+    }
+
+    fn check_card_collection(&mut self, expr: &expressions::CardCollectionExpression) {
+        use expressions::CardCollectionExpression::*;
+        match expr {
+            Single(card) => self.check_card(card),
+            GetVariable(name) => self.expect(name, VariableType::CardCollection),
+            AllInZone(zone) | TopInZone(zone) | BottomInZone(zone) => self.check_zone(zone),
+            InZoneMatchingSuit { zone, suit } => {
+                self.within("zone", |c| c.check_zone(zone));
+                self.within("suit", |c| c.check_suit(suit));
+            }
+            InZoneMatchingRank { zone, rank } => {
+                self.within("zone", |c| c.check_zone(zone));
+                self.within("rank", |c| c.check_rank(rank));
+            }
+            InZoneMatchingPattern { zone, pattern: _ } => {
+                self.within("zone", |c| c.check_zone(zone))
+            }
+        }
+    }
+
+    fn check_zone(&mut self, expr: &expressions::ZoneExpression) {
+        use expressions::ZoneExpression::*;
+        match expr {
+            OwnedByPlayer {
+                player,
+                zone_name: _,
+            } => self.within("player", |c| c.check_player(player)),
+            GetVariable(name) => self.expect(name, VariableType::Zone),
+        }
+    }
+
+    fn check_zone_collection(&mut self, expr: &expressions::ZoneCollectionExpression) {
+        use expressions::ZoneCollectionExpression::*;
+        match expr {
+            Single(zone) => self.check_zone(zone),
+            OfType(_) => (),
+            GetVariable(name) => self.expect(name, VariableType::ZoneCollection),
+        }
+    }
+
+    fn check_player(&mut self, expr: &expressions::PlayerExpression) {
+        use expressions::PlayerExpression::*;
+        match expr {
+            CurrentPlayer => (),
+            GetVariable(name) => self.expect(name, VariableType::Player),
+        }
+    }
+
+    fn check_player_collection(&mut self, expr: &expressions::PlayerCollectionExpression) {
+        use expressions::PlayerCollectionExpression::*;
+        match expr {
+            Single(player) => self.check_player(player),
+            AllPlayers => (),
+            GetVariable(name) => self.expect(name, VariableType::PlayerCollection),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+
+    const HEADER: &str = r#"
+        players 2..4;
+        ranks Two, Three, Four, Ace;
+        suits Hearts, Spades;
+        numbers score;
+        order high = Two, Three, Four, Ace;
+        assignment Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        player zone hand: Hand;
+        zone deck: Hand;
+        start setup;
+    "#;
+
+    fn problems(phase: &str) -> Vec<(Vec<String>, ConfigError)> {
+        let text = format!("{HEADER} phase setup {{ {phase} }}");
+        let config = dsl::parse_config(&text).expect("test ruleset parses");
+        check_program(&config)
+            .into_iter()
+            .map(|problem| (problem.path, problem.error))
+            .collect()
+    }
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        ["phases", "setup", "evaluate"]
+            .iter()
+            .chain(segments)
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn undefined(name: &str, var_type: VariableType) -> ConfigError {
+        ConfigError::UndefinedVariable(name.into(), var_type.to_string())
+    }
+
+    #[test]
+    fn choices_are_visible_to_later_choices_and_the_handler() {
+        let found = problems(
+            r#"offer to current player as chooser {
+                case "Pick" choices
+                    player target from all players,
+                    card picked from all in hand of target,
+                    cards chosen from top of deck,
+                    move from deck to hand of target
+                handle {
+                    move card picked to hand of chooser;
+                    move chosen to hand of target;
+                    set score = count chosen;
+                }
+            }"#,
+        );
+        assert!(found.is_empty(), "{found:?}");
+    }
+
+    #[test]
+    fn choices_are_not_visible_before_they_are_made() {
+        let found = problems(
+            r#"offer to current player {
+                case "Pick" choices
+                    card picked from all in hand of target,
+                    player target from all players
+                handle pass;
+            }"#,
+        );
+        assert_eq!(
+            found,
+            [(
+                path(&["0", "cases", "0", "choices", "0", "player"]),
+                undefined("target", VariableType::Player),
+            )]
+        );
+    }
+
+    #[test]
+    fn choices_are_not_visible_in_other_cases_or_after_the_offer() {
+        let found = problems(
+            r#"offer to current player as chooser {
+                case "Pick" choices player target from all players handle pass;
+                case "Other" handle declare winner player target;
+            }
+            declare winner player target;
+            declare winner player chooser;"#,
+        );
+        assert_eq!(
+            found,
+            [
+                (
+                    path(&["0", "cases", "1", "handle"]),
+                    undefined("target", VariableType::Player),
+                ),
+                (path(&["1"]), undefined("target", VariableType::Player)),
+                (path(&["2"]), undefined("chooser", VariableType::Player)),
+            ]
+        );
+    }
+
+    #[test]
+    fn mistyped_variable() {
+        let found = problems(
+            r#"set score = deck;
+            offer to current player {
+                case "Pick" choices cards chosen from top of deck
+                handle move card chosen to deck;
+            }"#,
+        );
+        assert_eq!(
+            found,
+            [
+                (
+                    path(&["0", "value"]),
+                    ConfigError::MistypedVariable {
+                        name: "deck".into(),
+                        expected: "number".into(),
+                        found: "zone".into(),
+                    },
+                ),
+                (
+                    path(&["1", "cases", "0", "handle", "source"]),
+                    ConfigError::MistypedVariable {
+                        name: "chosen".into(),
+                        expected: "card".into(),
+                        found: "card collection".into(),
+                    },
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let found = problems("shuffle piles; move top of pile to deck; set score = lives;");
+        assert_eq!(
+            found,
+            [
+                (
+                    path(&["0"]),
+                    undefined("piles", VariableType::ZoneCollection)
+                ),
+                (
+                    path(&["1", "source"]),
+                    undefined("pile", VariableType::Zone)
+                ),
+                (
+                    path(&["2", "value"]),
+                    ConfigError::UndeclaredNumber("lives".into())
+                ),
+            ]
+        );
+    }
+}
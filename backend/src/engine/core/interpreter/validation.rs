@@ -0,0 +1,509 @@
+use super::config::{ConfigError, GameConfig};
+use super::lang::{expressions, statements};
+use super::type_check;
+use crate::engine::core::types::patterns;
+use statements::Statement;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// A single problem found in a config, path points to where in the config tree it was found
+// e.g. ["phases", "main", "evaluate", "2", "go_true"]
+#[derive(Debug, Clone)]
+pub struct ConfigProblem {
+    pub path: Vec<String>,
+    pub error: ConfigError,
+    pub severity: ProblemSeverity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemSeverity {
+    Error,   // Game can't be started
+    Warning, // Game runs but probably not as intended
+}
+
+impl ConfigProblem {
+    pub fn is_error(&self) -> bool {
+        self.severity == ProblemSeverity::Error
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.join("/"), self.error)
+    }
+}
+
+// Checks that everything the config refers to by name actually exists, reporting every problem
+// instead of stopping at the first one
+pub fn validate_config(config: &GameConfig) -> Vec<ConfigProblem> {
+    let mut validator = Validator {
+        config,
+        path: Vec::new(),
+        problems: Vec::new(),
+        entered_phases: HashSet::new(),
+    };
+    validator.check_config();
+    validator.problems.extend(type_check::check_program(config));
+    validator.problems
+}
+
+struct Validator<'a> {
+    config: &'a GameConfig,
+    path: Vec<String>,
+    problems: Vec<ConfigProblem>,
+    entered_phases: HashSet<String>,
+}
+
+// Config maps are hash maps, sort keys so problems are always reported in the same order
+fn sorted_keys<K: Ord, V>(map: &HashMap<K, V>) -> Vec<&K> {
+    let mut keys: Vec<&K> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, error: ConfigError) {
+        self.problems.push(ConfigProblem {
+            path: self.path.clone(),
+            error,
+            severity: ProblemSeverity::Error,
+        });
+    }
+
+    fn warn(&mut self, error: ConfigError) {
+        self.problems.push(ConfigProblem {
+            path: self.path.clone(),
+            error,
+            severity: ProblemSeverity::Warning,
+        });
+    }
+
+    fn within(&mut self, segment: impl ToString, check: impl FnOnce(&mut Self)) {
+        self.path.push(segment.to_string());
+        check(self);
+        self.path.pop();
+    }
+
+    fn check_phase(&mut self, name: &str) {
+        self.entered_phases.insert(name.to_string());
+        if !self.config.phases.contains_key(name) {
+            self.report(ConfigError::NonexistantPhase(name.to_string()));
+        }
+    }
+
+    fn check_player_class(&mut self, name: &str) {
+        if !self.config.player_classes.contains_key(name) {
+            self.report(ConfigError::NonexistantPlayerClass(name.to_string()));
+        }
+    }
+
+    fn check_zone_class(&mut self, name: &str) {
+        if !self.config.zone_classes.contains_key(name) {
+            self.report(ConfigError::NonexistantZoneClass(name.to_string()));
+        }
+    }
+
+    fn check_player_zone(&mut self, name: &str) {
+        if !self.config.player_zones.contains_key(name) {
+            self.report(ConfigError::NonexistantPlayerZone(name.to_string()));
+        }
+    }
+
+    fn check_pattern(&mut self, name: &str) {
+        if !self.config.patterns.contains_key(name) {
+            self.report(ConfigError::NonexistantPattern(name.to_string()));
+        }
+    }
+
+    fn check_number(&mut self, name: &str) {
+        if !self.config.numbers.contains(name) {
+            self.report(ConfigError::UndeclaredNumber(name.to_string()));
+        }
+    }
+
+    fn check_config(&mut self) {
+        let config = self.config;
+
+        self.within("initial_phase", |v| v.check_phase(&config.initial_phase));
+
+        self.within("player_assignment", |v| {
+            for (idx, class) in config.player_assignment.iter().enumerate() {
+                v.within(idx, |v| v.check_player_class(class));
+            }
+        });
+
+        self.within("player_classes", |v| {
+            for name in sorted_keys(&config.player_classes) {
+                let mut zones: Vec<&String> =
+                    config.player_classes[name].active_zones.iter().collect();
+                zones.sort();
+                v.within(name, |v| {
+                    v.within("active_zones", |v| {
+                        for zone in zones {
+                            v.check_player_zone(zone);
+                        }
+                    })
+                });
+            }
+        });
+
+        self.within("player_zones", |v| {
+            for name in sorted_keys(&config.player_zones) {
+                v.within(name, |v| v.check_zone_class(&config.player_zones[name]));
+            }
+        });
+
+        self.within("initial_zones", |v| {
+            for name in sorted_keys(&config.initial_zones) {
+                v.within(name, |v| v.check_zone_class(&config.initial_zones[name]));
+            }
+        });
+
+        self.within("zone_classes", |v| {
+            for name in sorted_keys(&config.zone_classes) {
+                v.within(name, |v| {
+                    v.within("rules", |v| {
+                        for (idx, rule) in config.zone_classes[name].rules.iter().enumerate() {
+                            v.within(idx, |v| v.check_pattern(rule));
+                        }
+                    })
+                });
+            }
+        });
+
+        self.within("patterns", |v| {
+            for name in sorted_keys(&config.patterns) {
+                v.within(name, |v| {
+                    for (idx, pattern) in config.patterns[name].iter().enumerate() {
+                        if let patterns::Pattern::Relation(patterns::Relation::Consecutive(order)) =
+                            pattern
+                            && !config.orders.contains_key(order)
+                        {
+                            v.within(idx, |v| {
+                                v.report(ConfigError::NonexistantOrder(order.clone()))
+                            });
+                        }
+                    }
+                });
+            }
+        });
+
+        self.within("phases", |v| {
+            for name in sorted_keys(&config.phases) {
+                v.within(name, |v| {
+                    v.within("evaluate", |v| {
+                        v.check_statement(&config.phases[name].evaluate)
+                    })
+                });
+            }
+        });
+
+        // Needs every EnterPhase seen first
+        self.within("phases", |v| {
+            for name in sorted_keys(&config.phases) {
+                if !v.entered_phases.contains(name) {
+                    v.within(name, |v| {
+                        v.warn(ConfigError::UnreachablePhase(name.clone()))
+                    });
+                }
+            }
+        });
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Empty => (),
+            Statement::Block(statements) => {
+                for (idx, s) in statements.iter().enumerate() {
+                    self.within(idx, |v| v.check_statement(s));
+                }
+            }
+            Statement::Conditional(cond) => {
+                self.within("condition", |v| v.check_bool(&cond.condition));
+                self.within("go_true", |v| v.check_statement(&cond.go_true));
+                self.within("go_false", |v| v.check_statement(&cond.go_false));
+            }
+            Statement::While { condition, r#do } => {
+                self.within("condition", |v| v.check_bool(condition));
+                self.within("do", |v| v.check_statement(r#do));
+            }
+            // Player expressions don't refer to anything in the config
+            Statement::Broadcast { .. } | Statement::DeclareWinner(_) => (),
+            Statement::SetNumber { name, value } => {
+                self.within("name", |v| v.check_number(name));
+                self.within("value", |v| v.check_number_expr(value));
+            }
+            Statement::AdvancePlayerStateByType {
+                to_advance,
+                type_name,
+            } => {
+                self.within("to_advance", |v| v.check_number_expr(to_advance));
+                self.within("type_name", |v| v.check_player_class(type_name));
+            }
+            Statement::AdvancePlayerState(to_advance) => self.check_number_expr(to_advance),
+            Statement::MoveCardsTo { source, dest } => {
+                self.within("source", |v| v.check_card_collection(source));
+                self.within("dest", |v| v.check_zone(dest));
+            }
+            Statement::GenerateCards { cards: _, dest } => {
+                self.within("dest", |v| v.check_zone(dest));
+            }
+            Statement::Deal {
+                num_cards,
+                source,
+                dest,
+            } => {
+                self.within("num_cards", |v| v.check_number_expr(num_cards));
+                self.within("source", |v| v.check_zone(source));
+                self.within("dest", |v| v.check_zone_collection(dest));
+            }
+            Statement::Shuffle(zones) => self.check_zone_collection(zones),
+            Statement::EnterPhase(phase) => self.check_phase(phase),
+            Statement::Offer(offer) => {
+                self.within("cases", |v| {
+                    for (idx, case) in offer.cases.iter().enumerate() {
+                        v.within(idx, |v| v.check_offer_case(case));
+                    }
+                });
+            }
+        }
+    }
+
+    fn check_offer_case(&mut self, case: &statements::OfferCase) {
+        if let Some(condition) = &case.condition {
+            self.within("condition", |v| v.check_bool(condition));
+        }
+        self.within("choices", |v| {
+            for (idx, choice) in case.choices.iter().enumerate() {
+                use statements::{ChoiceAction, ChoiceSelectionEnum, OfferChoice};
+                v.within(idx, |v| match choice {
+                    OfferChoice::Selection(selection) => match &selection.choice_type {
+                        ChoiceSelectionEnum::Player(_)
+                        | ChoiceSelectionEnum::PlayerSelection(_) => (),
+                        ChoiceSelectionEnum::Card(cards)
+                        | ChoiceSelectionEnum::CardSelection(cards) => {
+                            v.check_card_collection(cards)
+                        }
+                    },
+                    OfferChoice::Action(ChoiceAction::MoveCards { from, to }) => {
+                        v.within("from", |v| v.check_zone(from));
+                        v.within("to", |v| v.check_zone(to));
+                    }
+                });
+            }
+        });
+        self.within("handle", |v| v.check_statement(&case.handle));
+    }
+
+    fn check_number_expr(&mut self, expr: &expressions::NumberExpression) {
+        use expressions::NumberExpression::*;
+        match expr {
+            // Variable references are left to the type checker
+            Literal(_) | GetVariable(_) => (),
+            CardsIn(cards) => self.check_card_collection(cards),
+        }
+    }
+
+    fn check_bool(&mut self, expr: &expressions::BooleanExpression) {
+        use expressions::BooleanExpression::*;
+        match expr {
+            Literal(_) => (),
+            Comparison {
+                a,
+                compared_to: _,
+                b,
+            } => {
+                self.within("a", |v| v.check_number_expr(a));
+                self.within("b", |v| v.check_number_expr(b));
+            }
+            PlayerIsType {
+                player: _,
+                type_name,
+            } => self.within("type_name", |v| v.check_player_class(type_name)),
+            MatchesPattern { cards, pattern } => {
+                self.within("cards", |v| v.check_card_collection(cards));
+                self.within("pattern", |v| v.check_pattern(pattern));
+            }
+        }
+    }
+
+    fn check_card(&mut self, expr: &expressions::CardExpression) {
+        use expressions::CardExpression::*;
+        match expr {
+            Create(suit, rank) => {
+                self.check_suit(suit);
+                self.check_rank(rank);
+            }
+            GetVariable(_) => (),
+        }
+    }
+
+    fn check_suit(&mut self, expr: &expressions::SuitExpression) {
+        if let expressions::SuitExpression::FromCard(card) = expr {
+            self.check_card(card);
+        }
+    }
+
+    fn check_rank(&mut self, expr: &expressions::RankExpression) {
+        if let expressions::RankExpression::FromCard(card) = expr {
+            self.check_card(card);
+        }
+    }
+
+    fn check_card_collection(&mut self, expr: &expressions::CardCollectionExpression) {
+        use expressions::CardCollectionExpression::*;
+        match expr {
+            Single(card) => self.check_card(card),
+            GetVariable(_) => (),
+            AllInZone(zone) | TopInZone(zone) | BottomInZone(zone) => self.check_zone(zone),
+            InZoneMatchingSuit { zone, suit } => {
+                self.within("zone", |v| v.check_zone(zone));
+                self.within("suit", |v| v.check_suit(suit));
+            }
+            InZoneMatchingRank { zone, rank } => {
+                self.within("zone", |v| v.check_zone(zone));
+                self.within("rank", |v| v.check_rank(rank));
+            }
+            InZoneMatchingPattern { zone, pattern } => {
+                self.within("zone", |v| v.check_zone(zone));
+                self.within("pattern", |v| v.check_pattern(pattern));
+            }
+        }
+    }
+
+    fn check_zone(&mut self, expr: &expressions::ZoneExpression) {
+        use expressions::ZoneExpression::*;
+        match expr {
+            OwnedByPlayer {
+                player: _,
+                zone_name,
+            } => self.within("zone_name", |v| v.check_player_zone(zone_name)),
+            GetVariable(_) => (),
+        }
+    }
+
+    fn check_zone_collection(&mut self, expr: &expressions::ZoneCollectionExpression) {
+        use expressions::ZoneCollectionExpression::*;
+        match expr {
+            Single(zone) => self.check_zone(zone),
+            OfType(class) => self.check_zone_class(class),
+            GetVariable(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+
+    const HEADER: &str = r#"
+        players 2..4;
+        ranks Two, Three, Four, Ace;
+        suits Hearts, Spades;
+        numbers score;
+        order high = Two, Three, Four, Ace;
+        pattern pair = ranks(2 any);
+        assignment Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; rules pair; }
+        player zone hand: Hand;
+        zone deck: Hand;
+    "#;
+
+    fn problems(rest: &str) -> Vec<ConfigProblem> {
+        let config = dsl::parse_config(&format!("{HEADER}{rest}")).expect("test ruleset parses");
+        validate_config(&config)
+    }
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let found = problems("start setup; phase setup { shuffle every Hand; enter setup; }");
+        assert!(found.is_empty(), "{found:?}");
+    }
+
+    #[test]
+    fn each_error_is_reported_where_it_was_found() {
+        let cases = [
+            (
+                "start nowhere; phase setup { enter setup; }",
+                ConfigError::NonexistantPhase("nowhere".into()),
+                path(&["initial_phase"]),
+            ),
+            (
+                "start setup; phase setup { enter nowhere; }",
+                ConfigError::NonexistantPhase("nowhere".into()),
+                path(&["phases", "setup", "evaluate", "0"]),
+            ),
+            (
+                "start setup; phase setup { advance 1 among Ghost; }",
+                ConfigError::NonexistantPlayerClass("Ghost".into()),
+                path(&["phases", "setup", "evaluate", "0", "type_name"]),
+            ),
+            (
+                "start setup; phase setup { shuffle every Ghost; }",
+                ConfigError::NonexistantZoneClass("Ghost".into()),
+                path(&["phases", "setup", "evaluate", "0"]),
+            ),
+            (
+                "start setup; phase setup { move all in pocket of current player to deck; }",
+                ConfigError::NonexistantPlayerZone("pocket".into()),
+                path(&["phases", "setup", "evaluate", "0", "source", "zone_name"]),
+            ),
+            (
+                "start setup; phase setup { move pattern ghost in deck to deck; }",
+                ConfigError::NonexistantPattern("ghost".into()),
+                path(&["phases", "setup", "evaluate", "0", "source", "pattern"]),
+            ),
+            (
+                "pattern run = consecutive low; start setup; phase setup { pass; }",
+                ConfigError::NonexistantOrder("low".into()),
+                path(&["patterns", "run", "0"]),
+            ),
+            (
+                "start setup; phase setup { set lives = 1; }",
+                ConfigError::UndeclaredNumber("lives".into()),
+                path(&["phases", "setup", "evaluate", "0", "name"]),
+            ),
+        ];
+        for (rest, error, at) in cases {
+            let found = problems(rest);
+            assert_eq!(found.len(), 1, "{rest}: {found:?}");
+            assert_eq!(found[0].error, error, "{rest}");
+            assert_eq!(found[0].path, at, "{rest}");
+            assert!(found[0].is_error(), "{rest}");
+        }
+    }
+
+    #[test]
+    fn unreachable_phase_is_a_warning() {
+        let found = problems("start setup; phase setup { pass; } phase lost { pass; }");
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].error, ConfigError::UnreachablePhase("lost".into()));
+        assert_eq!(found[0].path, path(&["phases", "lost"]));
+        assert_eq!(found[0].severity, ProblemSeverity::Warning);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let found = problems("start nowhere; phase setup { shuffle every Ghost; enter gone; }");
+        let errors: Vec<&ConfigError> = found
+            .iter()
+            .filter(|p| p.is_error())
+            .map(|p| &p.error)
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                &ConfigError::NonexistantPhase("nowhere".into()),
+                &ConfigError::NonexistantZoneClass("Ghost".into()),
+                &ConfigError::NonexistantPhase("gone".into()),
+            ]
+        );
+    }
+}
@@ -1,4 +1,4 @@
+pub mod dsl;
 pub mod interpreter;
 pub mod ruleset;
 pub mod types;
-//pub mod dsl;
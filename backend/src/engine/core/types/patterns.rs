@@ -1,8 +1,10 @@
 use super::{cards, identifiers::OrderIdentifier, rank_order, ranks, suits};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use ts_rs::TS;
 
 use std::collections::HashMap;
+use std::ops::Range;
 
 pub type PatternIdentifier = String;
 
@@ -28,39 +30,566 @@ pub enum Pattern {
     Rank(Vec<PatternPiece<Option<ranks::Rank>>>),
 }
 
+// Which cards a piece took, pattern is the index into the list of patterns being matched
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct PieceMatch {
+    pub pattern: usize,
+    pub piece: usize,
+    pub cards: Vec<u64>,
+}
+
+#[derive(TS, Error, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub enum MatchFailure {
+    #[error("Card {0} doesn't exist")]
+    MissingCard(u64),
+    #[error("The order '{0}' doesn't exist")]
+    MissingOrder(OrderIdentifier),
+    #[error("Card {card} doesn't follow on from the card before it")]
+    NotConsecutive { pattern: usize, card: u64 },
+    #[error("Card {card} doesn't fit in any piece of the pattern")]
+    Unmatched { pattern: usize, card: u64 },
+    #[error("The pattern needs more cards")]
+    TooFewCards { pattern: usize },
+}
+
+// On failure pieces holds everything matched before it, including as far as the failing
+// pattern got, so the player can be shown where their cards stopped fitting
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct MatchReport {
+    pub pieces: Vec<PieceMatch>,
+    pub failure: Option<MatchFailure>,
+}
+
+impl MatchReport {
+    pub fn is_match(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+// Matches a list of cards (bottom to top) against every pattern in a list
+// Suit and Rank patterns split the cards into runs, one per piece in order, each piece taking
+// between match_min and match_max cards. A piece with Some(x) only takes cards of x, None takes
+// cards that all share a suit/rank, whichever it is
+// Consecutive needs every card to be one step above the card before it in the order
 pub struct Matcher<'a> {
     card_map: &'a HashMap<u64, cards::Card>,
-    patterns: &'a Vec<Pattern>,
-    cards: &'a mut Vec<u64>,
+    patterns: &'a [Pattern],
+    orderings: &'a HashMap<OrderIdentifier, rank_order::RankOrder>,
+    cards: &'a [u64],
 }
 
 impl<'a> Matcher<'a> {
     pub fn new(
         card_map: &'a HashMap<u64, cards::Card>,
-        patterns: &'a Vec<Pattern>,
-        orderings: HashMap<OrderIdentifier, rank_order::RankOrder>,
-        cards: &'a mut Vec<u64>,
+        patterns: &'a [Pattern],
+        orderings: &'a HashMap<OrderIdentifier, rank_order::RankOrder>,
+        cards: &'a [u64],
     ) -> Self {
         Self {
             card_map,
             patterns,
+            orderings,
             cards,
         }
     }
 
-    pub fn match_patterns(&mut self) -> bool {
-        let mut first_pattern = true;
-        for pattern in self.patterns.iter() {
-            if first_pattern {
-                first_pattern = false;
-            } else {
+    pub fn match_patterns(&self) -> MatchReport {
+        let mut pieces = Vec::new();
+        let failure = self.match_all(&mut pieces).err();
+        MatchReport { pieces, failure }
+    }
+
+    fn match_all(&self, matched: &mut Vec<PieceMatch>) -> Result<(), MatchFailure> {
+        let mut cards = Vec::new();
+        for id in self.cards {
+            let card = self
+                .card_map
+                .get(id)
+                .ok_or(MatchFailure::MissingCard(*id))?;
+            cards.push((*id, *card));
+        }
+        for (idx, pattern) in self.patterns.iter().enumerate() {
+            self.match_pattern(idx, pattern, &cards, matched)?;
+        }
+        Ok(())
+    }
+
+    fn match_pattern(
+        &self,
+        idx: usize,
+        pattern: &Pattern,
+        cards: &[(u64, cards::Card)],
+        matched: &mut Vec<PieceMatch>,
+    ) -> Result<(), MatchFailure> {
+        match pattern {
+            Pattern::Relation(Relation::Consecutive(order)) => {
+                let order = self
+                    .orderings
+                    .get(order)
+                    .ok_or(MatchFailure::MissingOrder(order.clone()))?;
+                let mut previous: Option<usize> = None;
+                for (id, card) in cards {
+                    let index = order.index_of(card.rank);
+                    let follows = match (previous, index) {
+                        (_, None) => false,
+                        (None, Some(_)) => true,
+                        (Some(previous), Some(index)) => index == previous + 1,
+                    };
+                    if !follows {
+                        return Err(MatchFailure::NotConsecutive {
+                            pattern: idx,
+                            card: *id,
+                        });
+                    }
+                    previous = index;
+                }
+                Ok(())
+            }
+            Pattern::Suit(pieces) => {
+                let suits: Vec<(u64, suits::Suit)> =
+                    cards.iter().map(|(id, card)| (*id, card.suit)).collect();
+                match_pieces(idx, pieces, &suits, matched)
+            }
+            Pattern::Rank(pieces) => {
+                let ranks: Vec<(u64, ranks::Rank)> =
+                    cards.iter().map(|(id, card)| (*id, card.rank)).collect();
+                match_pieces(idx, pieces, &ranks, matched)
             }
         }
-        false
     }
+}
+
+fn match_pieces<T: Copy + Eq>(
+    pattern: usize,
+    pieces: &[PatternPiece<Option<T>>],
+    cards: &[(u64, T)],
+    matched: &mut Vec<PieceMatch>,
+) -> Result<(), MatchFailure> {
+    let mut search = PieceSearch {
+        pieces,
+        cards,
+        taken: Vec::new(),
+        best: Vec::new(),
+        best_end: 0,
+        furthest: 0,
+    };
+    let found = search.fit(0, 0);
+    let taken = if found { search.taken } else { search.best };
+    matched.extend(taken.into_iter().map(|(piece, range)| PieceMatch {
+        pattern,
+        piece,
+        cards: cards[range].iter().map(|(id, _)| *id).collect(),
+    }));
+
+    let needed: usize = pieces.iter().map(|piece| piece.match_min as usize).sum();
+    if found {
+        Ok(())
+    } else if cards.len() < needed {
+        Err(MatchFailure::TooFewCards { pattern })
+    } else if let Some((card, _)) = cards.get(search.furthest) {
+        Err(MatchFailure::Unmatched {
+            pattern,
+            card: *card,
+        })
+    } else {
+        Err(MatchFailure::TooFewCards { pattern })
+    }
+}
+
+// Backtracking search for how many cards each piece takes, longest runs are tried first
+// Remembers the assignment that got furthest through the cards for reporting failures, and the
+// first card no piece could take
+struct PieceSearch<'a, T> {
+    pieces: &'a [PatternPiece<Option<T>>],
+    cards: &'a [(u64, T)],
+    taken: Vec<(usize, Range<usize>)>,
+    best: Vec<(usize, Range<usize>)>,
+    best_end: usize,
+    furthest: usize,
+}
 
-    pub fn pattern_mutate(&mut self, pattern: &Pattern) -> bool {
-        // match pattern {}
+impl<T: Copy + Eq> PieceSearch<'_, T> {
+    fn fit(&mut self, piece: usize, pos: usize) -> bool {
+        if pos >= self.best_end {
+            self.best_end = pos;
+            self.best = self.taken.clone();
+        }
+        let Some(current) = self.pieces.get(piece) else {
+            return pos == self.cards.len();
+        };
+
+        let max = (current.match_max as usize).min(self.cards.len() - pos);
+        let run = (1..=max)
+            .take_while(|len| accepts(&current.pattern, &self.cards[pos..pos + len]))
+            .count();
+        self.furthest = self.furthest.max(pos + run);
+        for len in (current.match_min as usize..=max).rev() {
+            if !accepts(&current.pattern, &self.cards[pos..pos + len]) {
+                continue;
+            }
+            self.taken.push((piece, pos..pos + len));
+            if self.fit(piece + 1, pos + len) {
+                return true;
+            }
+            self.taken.pop();
+        }
         false
     }
 }
+
+fn accepts<T: Copy + Eq>(pattern: &Option<T>, run: &[(u64, T)]) -> bool {
+    match (pattern, run.first()) {
+        (_, None) => true,
+        (Some(value), _) => run.iter().all(|(_, v)| v == value),
+        (None, Some((_, first))) => run.iter().all(|(_, v)| v == first),
+    }
+}
+
+// Groups tried before find_match gives up, keeps big zones from stalling the game
+const MAX_SEARCH_CANDIDATES: usize = 20_000;
+
+#[derive(Error, Debug, Clone)]
+#[error("Gave up after trying {0} groups of cards without finding one that matches")]
+pub struct SearchExhausted(pub usize);
+
+// Finds the largest group of cards that matches every pattern, or nothing if there's none
+// Cards keep the order they're given in, unless a pattern needs them consecutive in which case
+// they're sorted by that order first
+// Group sizes that run out of budget are skipped for smaller ones, it's only an error if that
+// happened and no group matched, since a match could have been among the skipped ones
+pub fn find_match(
+    card_map: &HashMap<u64, cards::Card>,
+    patterns: &[Pattern],
+    orderings: &HashMap<OrderIdentifier, rank_order::RankOrder>,
+    cards: &[u64],
+) -> Result<Vec<u64>, SearchExhausted> {
+    let mut candidates: Vec<u64> = cards
+        .iter()
+        .copied()
+        .filter(|id| card_map.contains_key(id))
+        .collect();
+    let order = patterns.iter().find_map(|pattern| match pattern {
+        Pattern::Relation(Relation::Consecutive(order)) => orderings.get(order),
+        _ => None,
+    });
+    if let Some(order) = order {
+        candidates.sort_by_key(|id| order.index_of(card_map[id].rank).unwrap_or(usize::MAX));
+    }
+
+    // Every Suit/Rank pattern has to cover all the cards, which bounds how many there can be
+    let (mut min_size, mut max_size) = (1, candidates.len());
+    for pattern in patterns {
+        let pieces: Vec<(u32, u32)> = match pattern {
+            Pattern::Suit(pieces) => pieces.iter().map(|p| (p.match_min, p.match_max)).collect(),
+            Pattern::Rank(pieces) => pieces.iter().map(|p| (p.match_min, p.match_max)).collect(),
+            Pattern::Relation(_) => continue,
+        };
+        let needed = pieces
+            .iter()
+            .fold(0usize, |acc, p| acc.saturating_add(p.0 as usize));
+        let allowed = pieces
+            .iter()
+            .fold(0usize, |acc, p| acc.saturating_add(p.1 as usize));
+        min_size = min_size.max(needed);
+        max_size = max_size.min(allowed);
+    }
+
+    let sizes: Vec<usize> = (min_size..=max_size).rev().collect();
+    let mut tried = 0;
+    let mut cut_short = false;
+    for (done, size) in sizes.iter().enumerate() {
+        // Sizes still to go split what's left, so one huge size can't starve the smaller ones
+        let share = (MAX_SEARCH_CANDIDATES - tried) / (sizes.len() - done);
+        let mut picked: Vec<usize> = (0..*size).collect();
+        for attempt in 0.. {
+            if attempt == share {
+                cut_short = true;
+                break;
+            }
+            tried += 1;
+            let group: Vec<u64> = picked.iter().map(|idx| candidates[*idx]).collect();
+            if Matcher::new(card_map, patterns, orderings, &group)
+                .match_patterns()
+                .is_match()
+            {
+                return Ok(group);
+            }
+            if !next_combination(&mut picked, candidates.len()) {
+                break;
+            }
+        }
+    }
+    match cut_short {
+        true => Err(SearchExhausted(tried)),
+        false => Ok(Vec::new()),
+    }
+}
+
+// Steps through index combinations in lexicographic order, false once they run out
+fn next_combination(picked: &mut [usize], total: usize) -> bool {
+    let size = picked.len();
+    for pos in (0..size).rev() {
+        if picked[pos] < total - size + pos {
+            picked[pos] += 1;
+            for next in pos + 1..size {
+                picked[next] = picked[next - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cards::Card;
+    use ranks::Rank::{self, *};
+    use suits::Suit::{self, *};
+
+    // Card ids are their position in the hand
+    fn hand(cards: &[(Suit, Rank)]) -> (HashMap<u64, Card>, Vec<u64>) {
+        let map = cards
+            .iter()
+            .enumerate()
+            .map(|(id, (suit, rank))| (id as u64, Card::new(*suit, *rank)))
+            .collect();
+        (map, (0..cards.len() as u64).collect())
+    }
+
+    fn piece<T>(match_min: u32, match_max: u32, pattern: Option<T>) -> PatternPiece<Option<T>> {
+        PatternPiece {
+            match_min,
+            match_max,
+            pattern,
+        }
+    }
+
+    fn orderings() -> HashMap<OrderIdentifier, rank_order::RankOrder> {
+        let order = vec![
+            Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace,
+        ];
+        HashMap::from([("ace_high".to_string(), rank_order::RankOrder::new(order))])
+    }
+
+    fn check(cards: &[(Suit, Rank)], patterns: &[Pattern]) -> MatchReport {
+        let (map, ids) = hand(cards);
+        Matcher::new(&map, patterns, &orderings(), &ids).match_patterns()
+    }
+
+    fn taken(report: &MatchReport) -> Vec<(usize, usize, Vec<u64>)> {
+        report
+            .pieces
+            .iter()
+            .map(|m| (m.pattern, m.piece, m.cards.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn run_matches_consecutive_ranks() {
+        let run = [Pattern::Relation(Relation::Consecutive("ace_high".into()))];
+        let report = check(&[(Hearts, Nine), (Clubs, Ten), (Spades, Jack)], &run);
+        assert!(report.is_match());
+    }
+
+    #[test]
+    fn run_with_gap_is_not_consecutive() {
+        let run = [Pattern::Relation(Relation::Consecutive("ace_high".into()))];
+        let report = check(&[(Hearts, Nine), (Clubs, Ten), (Spades, Queen)], &run);
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::NotConsecutive {
+                pattern: 0,
+                card: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn run_with_unknown_order_fails() {
+        let run = [Pattern::Relation(Relation::Consecutive("missing".into()))];
+        let report = check(&[(Hearts, Nine)], &run);
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::MissingOrder(_))
+        ));
+    }
+
+    #[test]
+    fn set_takes_any_shared_rank() {
+        let set = [Pattern::Rank(vec![piece(3, 4, None)])];
+        let report = check(&[(Hearts, Seven), (Clubs, Seven), (Spades, Seven)], &set);
+        assert!(report.is_match());
+        assert_eq!(taken(&report), vec![(0, 0, vec![0, 1, 2])]);
+    }
+
+    #[test]
+    fn flush_needs_the_named_suit() {
+        let flush = [Pattern::Suit(vec![piece(3, 3, Some(Hearts))])];
+        assert!(check(&[(Hearts, Two), (Hearts, Nine), (Hearts, King)], &flush).is_match());
+
+        let report = check(&[(Hearts, Two), (Hearts, Nine), (Clubs, King)], &flush);
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::Unmatched {
+                pattern: 0,
+                card: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn pieces_split_cards_in_order() {
+        // A full house, three of one rank then two of another
+        let full_house = [Pattern::Rank(vec![piece(3, 3, None), piece(2, 2, None)])];
+        let report = check(
+            &[
+                (Hearts, Five),
+                (Clubs, Five),
+                (Spades, Five),
+                (Hearts, Ace),
+                (Clubs, Ace),
+            ],
+            &full_house,
+        );
+        assert!(report.is_match());
+        assert_eq!(
+            taken(&report),
+            vec![(0, 0, vec![0, 1, 2]), (0, 1, vec![3, 4])]
+        );
+    }
+
+    #[test]
+    fn search_backtracks_from_longest_runs() {
+        // The first piece would take all four cards if it didn't leave one for the second
+        let pattern = [Pattern::Rank(vec![
+            piece(1, 4, None),
+            piece(1, 1, Some(Two)),
+        ])];
+        let report = check(
+            &[(Hearts, Two), (Clubs, Two), (Spades, Two), (Diamonds, Two)],
+            &pattern,
+        );
+        assert!(report.is_match());
+        assert_eq!(taken(&report), vec![(0, 0, vec![0, 1, 2]), (0, 1, vec![3])]);
+    }
+
+    #[test]
+    fn match_min_above_card_count_is_too_few_cards() {
+        let set = [Pattern::Rank(vec![piece(4, 4, None)])];
+        let report = check(&[(Hearts, Seven), (Clubs, Seven)], &set);
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::TooFewCards { pattern: 0 })
+        ));
+        assert!(report.pieces.is_empty());
+    }
+
+    #[test]
+    fn leftover_cards_are_unmatched() {
+        let pair = [Pattern::Rank(vec![piece(2, 2, None)])];
+        let report = check(&[(Hearts, Seven), (Clubs, Seven), (Spades, Seven)], &pair);
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::Unmatched {
+                pattern: 0,
+                card: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn failure_reports_how_far_the_cards_fit() {
+        // The first pattern matches, the second gets through its first piece before failing
+        let patterns = [
+            Pattern::Suit(vec![piece(4, 4, Some(Spades))]),
+            Pattern::Rank(vec![piece(2, 2, None), piece(2, 2, Some(King))]),
+        ];
+        let report = check(
+            &[
+                (Spades, Three),
+                (Spades, Three),
+                (Spades, King),
+                (Spades, Queen),
+            ],
+            &patterns,
+        );
+        assert!(matches!(
+            report.failure,
+            Some(MatchFailure::Unmatched {
+                pattern: 1,
+                card: 3
+            })
+        ));
+        assert_eq!(
+            taken(&report),
+            vec![(0, 0, vec![0, 1, 2, 3]), (1, 0, vec![0, 1])]
+        );
+    }
+
+    #[test]
+    fn missing_card_fails_before_any_pattern() {
+        let set = [Pattern::Rank(vec![piece(1, 1, None)])];
+        let (map, _) = hand(&[(Hearts, Seven)]);
+        let report = Matcher::new(&map, &set, &orderings(), &[9]).match_patterns();
+        assert!(matches!(report.failure, Some(MatchFailure::MissingCard(9))));
+        assert!(report.pieces.is_empty());
+    }
+
+    // Every card, a suit at a time
+    fn deck() -> Vec<(Suit, Rank)> {
+        Suit::all()
+            .into_iter()
+            .flat_map(|suit| Rank::all().into_iter().map(move |rank| (suit, rank)))
+            .collect()
+    }
+
+    fn find(cards: &[(Suit, Rank)], patterns: &[Pattern]) -> Result<Vec<u64>, SearchExhausted> {
+        let (map, ids) = hand(cards);
+        find_match(&map, patterns, &orderings(), &ids)
+    }
+
+    #[test]
+    fn find_returns_the_largest_match() {
+        let pair_or_three = [Pattern::Rank(vec![piece(2, 3, None)])];
+        let cards = [(Hearts, Two), (Clubs, Nine), (Spades, Nine), (Hearts, Nine)];
+        assert_eq!(find(&cards, &pair_or_three).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn find_returns_nothing_without_a_match() {
+        let pair = [Pattern::Rank(vec![piece(2, 2, None)])];
+        let cards = [(Hearts, Two), (Clubs, Nine), (Spades, Ace)];
+        assert_eq!(find(&cards, &pair).unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn find_sorts_cards_for_runs() {
+        let run = [Pattern::Relation(Relation::Consecutive("ace_high".into()))];
+        let cards = [(Hearts, Ten), (Clubs, Eight), (Spades, Nine)];
+        assert_eq!(find(&cards, &run).unwrap(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn find_falls_back_to_smaller_groups_in_a_big_zone() {
+        // Too many groups of four to get through, but three of a kind is still found
+        let three_or_four = [Pattern::Rank(vec![piece(3, 4, None)])];
+        let cards = deck();
+        let found = find(&cards, &three_or_four).unwrap();
+        assert_eq!(found.len(), 3);
+        let rank = cards[found[0] as usize].1;
+        assert!(found.iter().all(|id| cards[*id as usize].1 == rank));
+    }
+
+    #[test]
+    fn find_reports_giving_up() {
+        let five_of_a_kind = [Pattern::Rank(vec![piece(5, 5, None)])];
+        assert!(matches!(
+            find(&deck(), &five_of_a_kind),
+            Err(SearchExhausted(MAX_SEARCH_CANDIDATES))
+        ));
+    }
+}
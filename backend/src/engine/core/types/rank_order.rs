@@ -24,6 +24,14 @@ impl RankOrder {
         }
     }
 
+    pub fn ranks(&self) -> &[Rank] {
+        &self.order
+    }
+
+    pub fn index_of(&self, rank: Rank) -> Option<usize> {
+        self.rank_to_index.get(&rank).copied()
+    }
+
     pub fn get_index(&self, card: Card) -> usize {
         *self.rank_to_index.get(&card.rank).unwrap()
     }
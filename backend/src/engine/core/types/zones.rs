@@ -7,8 +7,8 @@ use ts_rs::TS;
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
 #[ts(export)]
 pub struct ZoneVisibility {
-    owner: ZoneVisibilityRule, //Created player (or no one if this is a game created deck)
-    others: ZoneVisibilityRule,
+    pub owner: ZoneVisibilityRule, //Created player (or no one if this is a game created deck)
+    pub others: ZoneVisibilityRule,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -32,9 +32,9 @@ pub enum CardSelector {
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
 #[ts(export)]
 pub struct ZoneClass {
-    visibility: ZoneVisibility,
-    cleanup: ZoneCleanupBehavior,
-    rules: Vec<patterns::PatternIdentifier>, //Cards here after to match one of these patterns
+    pub visibility: ZoneVisibility,
+    pub cleanup: ZoneCleanupBehavior,
+    pub rules: Vec<patterns::PatternIdentifier>, //Cards here after to match one of these patterns
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -7,5 +7,5 @@ pub mod errors;
 pub mod v1;
 pub mod wss;
 
-//use lalrpop_util::lalrpop_mod;
-//lalrpop_mod!(pub ast_raw, "/engine/core/dsl/ast_raw.rs");
+use lalrpop_util::lalrpop_mod;
+lalrpop_mod!(pub ast_raw, "/engine/core/dsl/ast_raw.rs");
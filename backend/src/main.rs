@@ -9,7 +9,11 @@ use axum::{
 };
 use sqlx::postgres::PgPoolOptions;
 
-use backend::{state::app::AppState, v1, wss};
+use backend::engine::core::interpreter::migration::CONFIG_VERSION;
+use backend::{
+    state::{self, app::AppState},
+    v1, wss,
+};
 
 use tracing::info;
 use tracing_subscriber;
@@ -35,6 +39,25 @@ async fn main() {
         db: pool,
     };
 
+    info!("Restoring rooms from their last checkpoints");
+    if let Err(e) = state.resurrect_rooms().await {
+        tracing::error!("Couldn't restore rooms: {e}");
+    }
+
+    if config.upgrade_stored_configs {
+        info!("Upgrading stored rulesets to config version {CONFIG_VERSION}");
+        match state::ruleset::upgrade_stored_configs(state.clone()).await {
+            Ok(count) => info!("Upgraded {count} rulesets and revisions"),
+            Err(e) => tracing::error!("Couldn't upgrade rulesets: {e}"),
+        }
+    }
+
+    info!("Checking published rulesets still pass validation");
+    match state::ruleset::unpublish_invalid_rulesets(state.clone()).await {
+        Ok(count) => info!("Unpublished {count} rulesets with errors"),
+        Err(e) => tracing::error!("Couldn't check published rulesets: {e}"),
+    }
+
     let app = Router::new()
         .route("/v1/hello", get(hello))
         .route("/v1/test", get(v1::object_test::handler))
@@ -45,6 +68,54 @@ async fn main() {
             "/v1/rulesets/{ruleset_id}/edit",
             post(v1::ruleset::edit_ruleset),
         )
+        .route(
+            "/v1/rulesets/{ruleset_id}/source",
+            get(v1::ruleset::get_ruleset_source),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/fork",
+            post(v1::ruleset::fork_ruleset),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/forks",
+            get(v1::ruleset::get_forks),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/ancestry",
+            get(v1::ruleset::get_ancestry),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/upstream",
+            get(v1::ruleset::get_upstream_changes),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/publish",
+            post(v1::ruleset::publish_ruleset),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/unpublish",
+            post(v1::ruleset::unpublish_ruleset),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/revisions",
+            get(v1::ruleset::get_revisions),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/revisions/{revision}",
+            get(v1::ruleset::get_revision),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/revisions/{revision}/rollback",
+            post(v1::ruleset::rollback_ruleset),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/diff",
+            get(v1::ruleset::diff_revisions),
+        )
+        .route(
+            "/v1/rulesets/{ruleset_id}/games",
+            get(v1::game::get_game_logs),
+        )
         .route(
             "/v1/users/by-name/{username}",
             get(v1::users::get_user_by_name),
@@ -55,6 +126,10 @@ async fn main() {
         )
         .route("/v1/games/new", post(v1::game::start_game))
         .route("/v1/games/{game_code}", get(v1::game::game_code_get))
+        .route(
+            "/v1/games/logs/{game_log_id}/replay",
+            get(v1::game::replay_game),
+        )
         .route("/v1/rooms/{room}", get(wss::rooms::join_handler)) //wss upgrade
         .route("/v1/signup", post(v1::users::create_user))
         .route("/v1/login", post(v1::users::login_user))
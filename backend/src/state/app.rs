@@ -5,15 +5,41 @@ use std::{
 
 use rand::Rng;
 
-use super::engine_wrapper::interface::WebgameRequest;
+use super::engine_wrapper::{handler::RoomCheckpoint, interface::WebgameRequest};
 use super::web::WebGame;
+use super::{
+    game_log,
+    room::{self, SavedRoom},
+};
 use crate::engine::core::interpreter::config;
 use std::fmt;
 use tokio::sync::mpsc;
 use tracing::{error, info};
 
 pub type GameTx = mpsc::UnboundedSender<WebgameRequest>;
-pub type RoomMap = Arc<Mutex<HashMap<u64, GameTx>>>;
+pub type RoomMap = Arc<Mutex<HashMap<u64, RoomHandle>>>;
+
+// A running room and what it was started from, so it can be described without asking its game
+#[derive(Debug, Clone)]
+pub struct RoomHandle {
+    pub tx: GameTx,
+    pub name: String,
+    pub ruleset_id: uuid::Uuid,
+    pub revision: Option<i32>,
+    pub config_hash: String,
+}
+
+impl RoomHandle {
+    fn new(game: &WebGame, tx: GameTx) -> Self {
+        Self {
+            tx,
+            name: game.name.clone(),
+            ruleset_id: game.ruleset_id,
+            revision: game.revision,
+            config_hash: game.config_hash.clone(),
+        }
+    }
+}
 
 use sqlx::PgPool;
 
@@ -36,6 +62,8 @@ impl AppState {
         config: &config::GameConfig,
         name: &str,
         ruleset_id: &uuid::Uuid,
+        revision: i32,
+        config_hash: &str,
     ) -> Result<u64, String> {
         info!("Attempting to lock rooms mutex");
 
@@ -50,29 +78,145 @@ impl AppState {
             return Result::Err(String::from("Failed to get available room"));
         }
 
-        let (game, tx) = WebGame::new(config, name, ruleset_id);
-        room_map.insert(room_id, tx);
+        let (game, tx) =
+            WebGame::new(config, name, ruleset_id, revision, config_hash).map_err(|e| {
+                error!("Room config was rejected: {e}");
+                e.to_string()
+            })?;
+        room_map.insert(room_id, RoomHandle::new(&game, tx));
         drop(room_map);
+        let seed = game.seed;
+
+        self.spawn_room(room_id, game)?;
+        info!("Room ready, spawned with id {room_id} and seed {seed}");
+        Result::Ok(room_id)
+    }
+
+    // Brings back every room that was still running when the server last went down
+    #[tracing::instrument]
+    pub async fn resurrect_rooms(&self) -> anyhow::Result<()> {
+        let rooms = room::get_rooms(self.clone()).await?;
+        info!("Resurrecting {} rooms", rooms.len());
+
+        for saved in rooms {
+            let room_id = saved.room_code as u64;
+            let restored = saved
+                .config()
+                .map_err(|e| e.to_string())
+                .and_then(|config| {
+                    let checkpoint: RoomCheckpoint =
+                        serde_json::from_str(&saved.checkpoint).map_err(|e| e.to_string())?;
+                    Ok(WebGame::restore(
+                        config,
+                        &saved.name,
+                        &saved.ruleset_id,
+                        saved.revision,
+                        &saved.config_hash,
+                        checkpoint,
+                    ))
+                });
+            let (game, tx) = match restored {
+                Ok(restored) => restored,
+                Err(e) => {
+                    error!("Room {room_id} couldn't be restored, dropping it: {e}");
+                    room::delete_room(self.clone(), room_id).await?;
+                    continue;
+                }
+            };
+
+            self.rooms
+                .lock()
+                .unwrap()
+                .insert(room_id, RoomHandle::new(&game, tx));
+            if let Err(e) = self.spawn_room(room_id, game) {
+                error!("Room {room_id} couldn't be restarted: {e}");
+                self.rooms.lock().unwrap().remove(&room_id);
+            }
+        }
+        Ok(())
+    }
+
+    // Runs a room until it ends, checkpointing it while it runs and saving its log afterwards
+    fn spawn_room(&self, room_id: u64, game: WebGame) -> Result<(), String> {
+        let saved = SavedRoom {
+            ruleset_id: game.ruleset_id,
+            revision: game.revision,
+            name: game.name.clone(),
+            config: serde_json::to_string(game.state.game.config()).map_err(|e| e.to_string())?,
+            config_hash: game.config_hash.clone(),
+        };
+        let (checkpoint_tx, checkpoint_rx) = mpsc::unbounded_channel();
+        let saver = tokio::spawn(save_checkpoints(
+            self.clone(),
+            room_id,
+            saved,
+            checkpoint_rx,
+        ));
 
-        let cleanup_reserved_room_mutex = self.rooms.clone();
+        let app = self.clone();
         tokio::spawn(async move {
-            game.run().await;
+            let record = game.run(checkpoint_tx).await;
             info!("Room {room_id} has finished, cleaning up");
-            let mut rooms = cleanup_reserved_room_mutex.lock().unwrap();
-            if rooms.remove(&room_id).is_some() {
-                info!("Removed room {room_id} from room map");
-            } else {
-                tracing::warn!("Room {room_id} did not exist on cleanup");
+            {
+                let mut rooms = app.rooms.lock().unwrap();
+                if rooms.remove(&room_id).is_some() {
+                    info!("Removed room {room_id} from room map");
+                } else {
+                    tracing::warn!("Room {room_id} did not exist on cleanup");
+                }
+            }
+
+            // Last checkpoint has to land before the room is forgotten or it would come back
+            if let Err(e) = saver.await {
+                error!("Checkpoint saver for room {room_id} failed: {e}");
+            }
+            if let Err(e) = room::delete_room(app.clone(), room_id).await {
+                error!("Couldn't remove checkpoint of room {room_id}: {e}");
+            }
+
+            // Rooms that never started have nothing worth replaying
+            if record.events.is_empty() {
+                return;
+            }
+            match game_log::save_game_log(app, &record).await {
+                Ok(game_log_id) => info!("Saved room {room_id} as game log {game_log_id}"),
+                Err(e) => error!("Couldn't save the log of room {room_id}: {e}"),
             }
         });
-        info!("Room ready, spawned with id {room_id}");
-        Result::Ok(room_id)
+        Ok(())
     }
 
     pub fn has_room(&self, room_id: u64) -> bool {
         let room_map = self.rooms.lock().unwrap();
         room_map.contains_key(&room_id)
     }
+
+    pub fn room_handle(&self, room_id: u64) -> Option<RoomHandle> {
+        self.rooms.lock().unwrap().get(&room_id).cloned()
+    }
+}
+
+async fn save_checkpoints(
+    app: AppState,
+    room_id: u64,
+    saved: SavedRoom,
+    mut checkpoints: mpsc::UnboundedReceiver<RoomCheckpoint>,
+) {
+    while let Some(mut checkpoint) = checkpoints.recv().await {
+        // Only the newest one matters if saving fell behind
+        while let Ok(newer) = checkpoints.try_recv() {
+            checkpoint = newer;
+        }
+        let result = match serde_json::to_string(&checkpoint) {
+            Ok(serialized) => room::save_room(app.clone(), room_id, &saved, &serialized)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            error!("Couldn't checkpoint room {room_id}: {e}");
+        }
+    }
 }
 
 fn random_code() -> u64 {
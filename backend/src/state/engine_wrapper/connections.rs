@@ -11,9 +11,14 @@ pub enum WebGameConnection {
 pub struct WebGamePlayer {
     pub nickname: String,
     pub conn: WebGameConnection,
+    pub reconnect_token: String, // Secret, only ever sent to this player
 }
 
 impl WebGamePlayer {
+    pub fn new_token() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
     pub fn to_pinfo(&self, role: Option<wrapper::PlayerRole>) -> wrapper::PlayerInformation {
         wrapper::PlayerInformation {
             nickname: self.nickname.clone(),
@@ -1,13 +1,21 @@
 use crate::engine::core::interpreter;
-use std::collections::{HashMap, VecDeque};
+use interpreter::state::execution_state::{ChoiceValue, ChoiceView, OfferResponse, OfferView};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use super::{connections, interface, names, status::InterpreterStatus, wrapper};
+use super::{
+    connections, identities::CardIdentities, interface, names, status::InterpreterStatus, wrapper,
+};
 
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
+// Statements run before the room goes back to handling requests
+const STATEMENTS_PER_BATCH: usize = 256;
+
 #[derive(Debug, Clone)]
 pub struct WebGameState {
     pub connections: HashMap<wrapper::PlayerId, connections::WebGamePlayer>,
+    pub identities: HashMap<wrapper::PlayerId, CardIdentities>,
     // Underlying engine will only care about order, it won't know
     // about player IDs or such
     pub player_order: Vec<wrapper::PlayerId>,
@@ -16,7 +24,66 @@ pub struct WebGameState {
     pub status: InterpreterStatus,
 }
 
+// A room as saved to the database, connections aren't kept since everyone has to reconnect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomCheckpoint {
+    pub game: interpreter::game::Checkpoint,
+    pub player_order: Vec<wrapper::PlayerId>,
+    pub nicknames: HashMap<wrapper::PlayerId, String>,
+    #[serde(default)]
+    pub reconnect_tokens: HashMap<wrapper::PlayerId, String>,
+    pub status: InterpreterStatus,
+}
+
 impl WebGameState {
+    pub fn checkpoint(&self) -> RoomCheckpoint {
+        RoomCheckpoint {
+            game: self.game.checkpoint(),
+            player_order: self.player_order.clone(),
+            nicknames: self
+                .connections
+                .iter()
+                .map(|(player_id, player)| (player_id.clone(), player.nickname.clone()))
+                .collect(),
+            reconnect_tokens: self
+                .connections
+                .iter()
+                .map(|(player_id, player)| (player_id.clone(), player.reconnect_token.clone()))
+                .collect(),
+            status: self.status.clone(),
+        }
+    }
+
+    // Everyone comes back disconnected and keeps their seat until they reconnect with their token
+    // Checkpoints saved before tokens were kept get fresh ones, so nobody can take those seats
+    pub fn restore(config: interpreter::config::GameConfig, checkpoint: RoomCheckpoint) -> Self {
+        let mut tokens = checkpoint.reconnect_tokens;
+        let connections = checkpoint
+            .nicknames
+            .into_iter()
+            .map(|(player_id, nickname)| {
+                let player = connections::WebGamePlayer {
+                    nickname,
+                    conn: connections::WebGameConnection::Disconnected(0),
+                    reconnect_token: tokens
+                        .remove(&player_id)
+                        .unwrap_or_else(connections::WebGamePlayer::new_token),
+                };
+                (player_id, player)
+            })
+            .collect();
+        let mut state = Self {
+            connections,
+            identities: HashMap::new(),
+            player_order: checkpoint.player_order,
+            public_action_queue: VecDeque::new(),
+            game: interpreter::game::Game::restore(config, checkpoint.game),
+            status: checkpoint.status,
+        };
+        state.queue_chat(None, "Room was restored after a server restart");
+        state
+    }
+
     pub fn get_player_snapshot(&self) -> wrapper::PlayerSnapshot {
         let mut psnapshot = wrapper::PlayerSnapshot {
             players: self
@@ -50,8 +117,9 @@ impl WebGameState {
             private_actions: Vec::new(),
             status: self.game.get_status(),
             players: Some(self.get_player_snapshot()),
-            public_cards: HashMap::new(), //TODO
-            zones: Vec::new(),            //TODO
+            public_cards: HashMap::new(),
+            zones: Vec::new(),
+            offer: None,
         }
     }
 
@@ -78,15 +146,45 @@ impl WebGameState {
         let public = self.get_snapshot();
         let mut invalid: Vec<wrapper::PlayerId> = Vec::new();
 
-        for (player_id, player) in &mut self.connections {
+        // Disconnected players still need to forget what moved while they were gone
+        let movements = self.game.take_card_movements();
+        for player_id in self.connections.keys() {
+            let viewer = viewer_of(&self.player_order, &self.game, player_id);
+            self.identities
+                .entry(player_id.clone())
+                .or_default()
+                .forget_moved(&self.game, viewer, &movements);
+        }
+
+        let mut offers: HashMap<u64, OfferView> = HashMap::new();
+        let offered = match self.game.pending_offer() {
+            Some(pending) => pending.players.clone(),
+            None => Vec::new(),
+        };
+        for player in offered {
+            if let Some(view) = self.game.describe_offer(player) {
+                offers.insert(player, view);
+            }
+        }
+
+        for (player_id, player) in &self.connections {
             if let connections::WebGameConnection::Connected(tx) = player.conn.clone() {
-                let copy = wrapper::GameSnapshot {
+                let mut copy = wrapper::GameSnapshot {
                     private_actions: match &private_actions {
                         Some(p_actions) => p_actions.get(player_id).cloned().unwrap_or(Vec::new()),
                         None => Vec::new(),
                     },
                     ..public.clone()
                 };
+                let viewer = viewer_of(&self.player_order, &self.game, player_id);
+                if let Some(identities) = self.identities.get_mut(player_id) {
+                    let view = viewer.and_then(|v| offers.get(&v));
+                    let offered = view.map(offered_cards).unwrap_or_default();
+                    add_visible_cards(&self.game, viewer, &offered, identities, &mut copy);
+                    if let Some(view) = view {
+                        copy.offer = Some(offer_snapshot(view, identities));
+                    }
+                }
                 if let Err(e) = tx.send(copy) {
                     info!("Broadcast to closed channel!, removing player! {e}");
                     //TODO: This shouldn't happen at all and results in annoying behaviors if it
@@ -105,13 +203,16 @@ impl WebGameState {
     }
 
     fn broadcast_join_acc(&mut self, player_id: &wrapper::PlayerId) {
-        let mut join_ack: HashMap<wrapper::PlayerId, Vec<wrapper::GameAction>> = HashMap::new();
-        join_ack.insert(
-            player_id.clone(),
-            vec![wrapper::GameAction::JoinResult(Ok(player_id.clone()))],
-        );
         let nick_option = self.connections.get(player_id);
         if let Some(p_info) = nick_option {
+            let accepted = wrapper::JoinAccepted {
+                player_id: player_id.clone(),
+                reconnect_token: p_info.reconnect_token.clone(),
+            };
+            let join_ack = HashMap::from([(
+                player_id.clone(),
+                vec![wrapper::GameAction::JoinResult(Ok(accepted))],
+            )]);
             self.queue_chat(None, &format!("{} has joined", p_info.nickname));
             self.broadcast(Some(join_ack));
         }
@@ -134,10 +235,12 @@ impl WebGameState {
                 if self.game.is_waiting() {
                     self.player_order.remove(idx);
                     self.connections.remove(player_id);
+                    self.identities.remove(player_id);
                     self.update_player_roles();
                 }
             } else {
                 self.connections.remove(player_id);
+                self.identities.remove(player_id);
             }
         }
         if let Some(n) = nick {
@@ -179,6 +282,9 @@ impl WebGameState {
                         }
                         // Process this for timeout
                         PlayerCommand::Heartbeat => {}
+                        PlayerCommand::RespondToOffer(answer) => {
+                            self.respond_to_offer(&msg.player_id, answer)
+                        }
                         PlayerCommand::StartGame => {
                             if self.game.is_ready()
                                 && self.player_order.len() > 0
@@ -196,10 +302,7 @@ impl WebGameState {
                                     Err(gerror) => {
                                         match gerror {
                                             GameError::Recoverable(_) => (), //TODO, in the future log
-                                            GameError::Fatal(e) => {
-                                                self.queue_chat(None, &format!("FATAL: {:?}", e));
-                                                self.broadcast(None);
-                                            }
+                                            GameError::Fatal(e) => self.fail(e),
                                         }
                                     }
                                 }
@@ -212,6 +315,155 @@ impl WebGameState {
         }
     }
 
+    // Steps the interpreter until it has to wait or the batch runs out, so requests still get
+    // handled in between long stretches of statements
+    pub fn run_batch(&mut self) {
+        let mut private: HashMap<wrapper::PlayerId, Vec<wrapper::GameAction>> = HashMap::new();
+        for _ in 0..STATEMENTS_PER_BATCH {
+            if !matches!(self.status, InterpreterStatus::PendingExecution) {
+                break;
+            }
+            self.execute_statement(&mut private);
+        }
+        if !self.is_crashed() {
+            self.broadcast(Some(private));
+        }
+    }
+
+    // Runs one statement, keeping the interpreter status in line with the engine
+    // Anything only some players should see is added to private
+    fn execute_statement(
+        &mut self,
+        private: &mut HashMap<wrapper::PlayerId, Vec<wrapper::GameAction>>,
+    ) {
+        use interpreter::game::{EngineStatus, GameError, RecoverableGameError};
+        match self.game.eval_statement() {
+            Ok(EngineStatus::Ready) => (),
+            Ok(EngineStatus::Blocked) => self.status = InterpreterStatus::Blocked,
+            Ok(EngineStatus::Sleep(seconds)) => {
+                self.status = InterpreterStatus::InstructionDelay(seconds as u64)
+            }
+            Ok(EngineStatus::Broadcast { msg, to }) => {
+                if to.len() >= self.game.get_roles().len() {
+                    self.queue_chat(None, &msg);
+                } else {
+                    let chat = wrapper::GameAction::ChatMsg(wrapper::GameChat {
+                        from: None,
+                        contents: msg,
+                    });
+                    for player_id in to.iter().filter_map(|p| self.player_order.get(*p as usize)) {
+                        private
+                            .entry(player_id.clone())
+                            .or_default()
+                            .push(chat.clone());
+                    }
+                }
+            }
+            Ok(EngineStatus::Finished) => {
+                self.status = InterpreterStatus::Finished;
+                let winners: Vec<String> = self
+                    .game
+                    .get_winners()
+                    .iter()
+                    .filter_map(|p| self.player_order.get(*p as usize))
+                    .map(|pid| match self.connections.get(pid) {
+                        Some(player) => player.nickname.clone(),
+                        None => pid.clone(),
+                    })
+                    .collect();
+                if winners.is_empty() {
+                    self.queue_chat(None, "Game over");
+                } else {
+                    self.queue_chat(None, &format!("Game over, won by {}", winners.join(", ")));
+                }
+            }
+            // Engine went back to the offer, the player has to answer it again
+            Err(GameError::Recoverable(RecoverableGameError::BrokenZoneRule {
+                player,
+                violation,
+            })) => {
+                self.status = InterpreterStatus::Blocked;
+                if let Some(player_id) = self.player_order.get(player as usize) {
+                    private
+                        .entry(player_id.clone())
+                        .or_default()
+                        .push(wrapper::GameAction::OfferResult(Err(violation.to_string())));
+                }
+            }
+            // Nothing would change by running the statement again
+            Err(GameError::Recoverable(e)) => self.fail(e),
+            Err(GameError::Fatal(e)) => self.fail(e),
+        }
+    }
+
+    fn fail(&mut self, error: impl std::fmt::Display) {
+        tracing::error!("Game failed: {error}");
+        self.status = InterpreterStatus::Failed;
+        self.game.record_crash(error.to_string());
+        self.queue_chat(None, &format!("Game crashed: {error}"));
+        self.broadcast(None);
+    }
+
+    fn respond_to_offer(&mut self, player_id: &wrapper::PlayerId, answer: &wrapper::OfferAnswer) {
+        use interpreter::game::GameError;
+        let result = match self.resolve_answer(player_id, answer) {
+            Ok((player, response)) => match self.game.respond_to_offer(player, &response) {
+                Ok(()) => {
+                    self.status = InterpreterStatus::PendingExecution;
+                    Ok(())
+                }
+                Err(GameError::Recoverable(e)) => Err(e.to_string()),
+                Err(GameError::Fatal(e)) => return self.fail(e),
+            },
+            Err(reason) => Err(reason),
+        };
+
+        let reply = HashMap::from([(
+            player_id.clone(),
+            vec![wrapper::GameAction::OfferResult(result)],
+        )]);
+        self.broadcast(Some(reply));
+    }
+
+    // Swaps the ids the player was sent for the engine's
+    fn resolve_answer(
+        &self,
+        player_id: &wrapper::PlayerId,
+        answer: &wrapper::OfferAnswer,
+    ) -> Result<(u64, OfferResponse), String> {
+        let player = viewer_of(&self.player_order, &self.game, player_id)
+            .ok_or("Spectators can't answer offers".to_string())?;
+        let identities = self
+            .identities
+            .get(player_id)
+            .ok_or("No cards have been sent to you yet".to_string())?;
+        let card = |id: &u32| {
+            identities
+                .resolve(*id)
+                .ok_or(format!("Card {id} isn't one you can see"))
+        };
+
+        let mut choices = Vec::with_capacity(answer.choices.len());
+        for choice in &answer.choices {
+            use wrapper::OfferChoiceAnswer;
+            choices.push(match choice {
+                OfferChoiceAnswer::Player(p) => ChoiceValue::Player(*p),
+                OfferChoiceAnswer::Players(ps) => ChoiceValue::Players(ps.clone()),
+                OfferChoiceAnswer::Card(c) => ChoiceValue::Card(card(c)?),
+                OfferChoiceAnswer::Cards(cs) => {
+                    ChoiceValue::Cards(cs.iter().map(card).collect::<Result<_, _>>()?)
+                }
+            });
+        }
+        Ok((
+            player,
+            OfferResponse {
+                case: answer.case as usize,
+                choices,
+            },
+        ))
+    }
+
     // Checks if player exists or is trying to Join
     // If a player is trying to join: add the player to active connections
     // If a player id is found in list of active connections, return True
@@ -222,9 +474,13 @@ impl WebGameState {
                 let tx = request.tx.clone();
 
                 // Previously added player try reconnecting them unless they already have a
-                // connection somehow
+                // connection somehow, the player id is public so only the token proves it's them
                 if let Some(player) = self.connections.get_mut(&msg.player_id) {
                     use connections::WebGameConnection;
+                    if request.reconnect_token.as_ref() != Some(&player.reconnect_token) {
+                        reject_join(&tx, "Wrong reconnect token");
+                        return false;
+                    }
                     match player.conn.clone() {
                         WebGameConnection::Connected(original_tx) => {
                             if original_tx.is_closed() {
@@ -235,11 +491,7 @@ impl WebGameState {
                                 self.broadcast_join_acc(&msg.player_id);
                                 return true;
                             } else {
-                                let mut error = wrapper::GameSnapshot::new();
-                                error.add_private_action(wrapper::GameAction::JoinResult(Err(
-                                    "ID already exists".into(),
-                                )));
-                                tx.send(error).unwrap_or(());
+                                reject_join(&tx, "ID already exists");
                                 return false;
                             }
                         }
@@ -249,6 +501,9 @@ impl WebGameState {
                             return true;
                         }
                     }
+                } else if request.reconnect_token.is_some() {
+                    reject_join(&tx, "That seat is no longer in the room");
+                    return false;
                 } else {
                     // Add player to connections and broadcast addition, additionally informing newly
                     // added player of success
@@ -266,6 +521,7 @@ impl WebGameState {
                                 None => names::make_random_name(),
                             },
                             conn: connections::WebGameConnection::Connected(tx),
+                            reconnect_token: connections::WebGamePlayer::new_token(),
                         },
                     );
 
@@ -278,3 +534,362 @@ impl WebGameState {
         self.connections.contains_key(&msg.player_id)
     }
 }
+
+fn reject_join(tx: &tokio::sync::mpsc::UnboundedSender<wrapper::GameSnapshot>, reason: &str) {
+    let mut error = wrapper::GameSnapshot::new();
+    error.add_private_action(wrapper::GameAction::JoinResult(Err(reason.into())));
+    tx.send(error).unwrap_or(());
+}
+
+// Engine only knows players by order, anyone without a role is a spectator
+fn viewer_of(
+    player_order: &[wrapper::PlayerId],
+    game: &interpreter::game::Game,
+    player_id: &wrapper::PlayerId,
+) -> Option<u64> {
+    let idx = player_order.iter().position(|pid| pid == player_id)?;
+    (idx < game.get_roles().len()).then_some(idx as u64)
+}
+
+// Only cards the player can see go into the snapshot, under the ids that player knows them by
+// Face down cards carry no id at all, unless they're offered to the player in which case they
+// keep the id the offer gave them until it's answered
+fn add_visible_cards(
+    game: &interpreter::game::Game,
+    viewer: Option<u64>,
+    offered: &HashSet<u64>,
+    identities: &mut CardIdentities,
+    snapshot: &mut wrapper::GameSnapshot,
+) {
+    let zones = game.visible_zones(viewer);
+    let visible: HashSet<u64> = zones
+        .iter()
+        .flat_map(|zone| zone.cards.iter().flatten().map(|(card_id, _)| *card_id))
+        .chain(offered.iter().copied())
+        .collect();
+    identities.forget_unseen(&visible);
+
+    for zone in zones {
+        let mut cards = Vec::with_capacity(zone.cards.len());
+        for card in zone.cards {
+            cards.push(card.map(|(card_id, card)| {
+                let id = identities.id_for(card_id);
+                snapshot.public_cards.insert(id, card);
+                id
+            }));
+        }
+        snapshot.zones.push(wrapper::ZoneSnapshot {
+            cards,
+            owner: zone.owner.map(|owner| owner as u32),
+            display_name: zone.name,
+            zone_id: zone.zone_id as u32,
+        });
+    }
+}
+
+// Every card an offer lets the player pick or move, seen or not
+fn offered_cards(view: &OfferView) -> HashSet<u64> {
+    view.cases
+        .iter()
+        .flat_map(|case| case.choices.iter())
+        .filter_map(|choice| match choice {
+            ChoiceView::Card { options, .. }
+            | ChoiceView::Cards { options, .. }
+            | ChoiceView::MoveCards { options, .. } => options.as_ref(),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+// Offered cards the player can't see still get an id to answer with, but their face stays out
+// of public_cards
+fn offer_snapshot(view: &OfferView, identities: &mut CardIdentities) -> wrapper::OfferSnapshot {
+    let mut cards = |options: &Option<Vec<u64>>| {
+        options.as_ref().map(|cards| {
+            cards
+                .iter()
+                .map(|card_id| identities.id_for(*card_id))
+                .collect()
+        })
+    };
+
+    wrapper::OfferSnapshot {
+        cases: view
+            .cases
+            .iter()
+            .map(|case| wrapper::OfferCaseSnapshot {
+                case: case.index as u32,
+                message: case.message.clone(),
+                choices: case
+                    .choices
+                    .iter()
+                    .map(|choice| match choice {
+                        ChoiceView::Player { name, options } => {
+                            wrapper::OfferChoiceSnapshot::Player {
+                                name: name.clone(),
+                                options: options.clone(),
+                            }
+                        }
+                        ChoiceView::Players { name, options } => {
+                            wrapper::OfferChoiceSnapshot::Players {
+                                name: name.clone(),
+                                options: options.clone(),
+                            }
+                        }
+                        ChoiceView::Card { name, options } => wrapper::OfferChoiceSnapshot::Card {
+                            name: name.clone(),
+                            options: cards(options),
+                        },
+                        ChoiceView::Cards { name, options } => {
+                            wrapper::OfferChoiceSnapshot::Cards {
+                                name: name.clone(),
+                                options: cards(options),
+                            }
+                        }
+                        ChoiceView::MoveCards { from, to, options } => {
+                            wrapper::OfferChoiceSnapshot::MoveCards {
+                                from: from.map(|zone| zone as u32),
+                                to: to.map(|zone| zone as u32),
+                                options: cards(options),
+                            }
+                        }
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+    use tokio::sync::mpsc;
+
+    // The deck is hidden from everyone, the first player is offered to draw from it
+    const RULESET: &str = r#"
+        players 2..3;
+        ranks Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace;
+        suits Hearts, Diamonds, Spades, Clubs;
+        assignment Player, Player;
+        player class Player { zones hand; for all players; }
+        zone class Hand { owner visible; others hidden; }
+        zone class Pile { owner hidden; others hidden; }
+        zone class Stack { owner top; others top; }
+        zone class Under { owner bottom; others bottom; }
+        player zone hand: Hand;
+        zone deck: Pile;
+        zone discard: Stack;
+        zone under: Under;
+        start setup;
+
+        phase setup {
+            generate all cards into deck;
+            shuffle zone deck;
+            deal 2 from deck to every Hand;
+            deal 3 from deck to every Stack;
+            deal 3 from deck to every Under;
+            enter turn;
+        }
+
+        phase turn offer to current player {
+            case "Draw" choices move from deck to hand of current player handle pass;
+        }
+    "#;
+
+    fn room() -> WebGameState {
+        let config = dsl::parse_config(RULESET).expect("test ruleset parses");
+        WebGameState {
+            connections: HashMap::new(),
+            identities: HashMap::new(),
+            player_order: Vec::new(),
+            public_action_queue: VecDeque::new(),
+            game: interpreter::game::Game::new(config, 7).unwrap(),
+            status: InterpreterStatus::Setup,
+        }
+    }
+
+    fn request(player_id: &str, body: interface::WebgameRequestBody) -> interface::WebgameRequest {
+        interface::WebgameRequest {
+            body,
+            player_id: player_id.to_string(),
+        }
+    }
+
+    fn join(
+        state: &mut WebGameState,
+        player_id: &str,
+    ) -> mpsc::UnboundedReceiver<wrapper::GameSnapshot> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let join = interface::WebgameJoin {
+            nickname: Some(player_id.to_string()),
+            reconnect_token: None,
+            tx,
+        };
+        state.process_request(&request(
+            player_id,
+            interface::WebgameRequestBody::Join(join),
+        ));
+        rx
+    }
+
+    fn command(state: &mut WebGameState, player_id: &str, command: wrapper::PlayerCommand) {
+        let body = interface::WebgameRequestBody::PlayerCommand(command);
+        state.process_request(&request(player_id, body));
+        while matches!(state.status, InterpreterStatus::PendingExecution) {
+            state.run_batch();
+        }
+    }
+
+    // Everything sent so far, oldest first
+    fn received(
+        rx: &mut mpsc::UnboundedReceiver<wrapper::GameSnapshot>,
+    ) -> Vec<wrapper::GameSnapshot> {
+        let mut snapshots = Vec::new();
+        while let Ok(snapshot) = rx.try_recv() {
+            snapshots.push(snapshot);
+        }
+        snapshots
+    }
+
+    // Two players at the first offer, with a spectator who came in after the start
+    fn started() -> (
+        WebGameState,
+        [mpsc::UnboundedReceiver<wrapper::GameSnapshot>; 3],
+    ) {
+        let mut state = room();
+        let first = join(&mut state, "first");
+        let second = join(&mut state, "second");
+        command(&mut state, "first", wrapper::PlayerCommand::StartGame);
+        let spectator = join(&mut state, "spectator");
+        assert!(matches!(state.status, InterpreterStatus::Blocked));
+        (state, [first, second, spectator])
+    }
+
+    fn move_options(snapshot: &wrapper::GameSnapshot) -> Vec<u32> {
+        let offer = snapshot
+            .offer
+            .as_ref()
+            .expect("first player is offered to draw");
+        match &offer.cases[0].choices[0] {
+            wrapper::OfferChoiceSnapshot::MoveCards {
+                options: Some(options),
+                ..
+            } => options.clone(),
+            choice => panic!("expected cards to move, got {choice:?}"),
+        }
+    }
+
+    #[test]
+    fn move_from_hidden_zone_can_be_answered() {
+        let (mut state, [mut first, ..]) = started();
+        let snapshot = received(&mut first).pop().unwrap();
+        let options = move_options(&snapshot);
+        assert_eq!(options.len(), 52 - 2 * 2 - 3 - 3);
+        assert!(
+            options
+                .iter()
+                .all(|id| !snapshot.public_cards.contains_key(id))
+        );
+
+        let answer = wrapper::OfferAnswer {
+            case: 0,
+            choices: vec![wrapper::OfferChoiceAnswer::Cards(vec![options[0]])],
+        };
+        command(
+            &mut state,
+            "first",
+            wrapper::PlayerCommand::RespondToOffer(answer),
+        );
+        let replies: Vec<wrapper::GameAction> = received(&mut first)
+            .into_iter()
+            .flat_map(|snapshot| snapshot.private_actions)
+            .collect();
+        assert!(
+            matches!(
+                replies.as_slice(),
+                [wrapper::GameAction::OfferResult(Ok(()))]
+            ),
+            "{replies:?}"
+        );
+        assert!(matches!(state.status, InterpreterStatus::Finished));
+    }
+
+    // Every face sent is for a card the viewer's visibility rules show, and nothing else
+    fn assert_only_visible_faces(
+        state: &WebGameState,
+        viewer: Option<u64>,
+        snapshot: &wrapper::GameSnapshot,
+    ) {
+        use crate::engine::core::types::zones::ZoneVisibilityRule;
+        let revealed = state.game.revealed_zones();
+        let mut shown = 0;
+        for zone in &snapshot.zones {
+            let actual = revealed
+                .iter()
+                .find(|z| z.zone_id as u32 == zone.zone_id)
+                .unwrap();
+            let rule = state.game.zone_visibility(actual.zone_id, viewer);
+            let last = zone.cards.len().saturating_sub(1);
+            for (idx, (sent, card)) in zone.cards.iter().zip(actual.cards.iter()).enumerate() {
+                let visible = match rule {
+                    ZoneVisibilityRule::Visible => true,
+                    ZoneVisibilityRule::Hidden => false,
+                    ZoneVisibilityRule::Top => idx == last,
+                    ZoneVisibilityRule::Bottom => idx == 0,
+                };
+                assert_eq!(sent.is_some(), visible, "zone {} card {idx}", zone.zone_id);
+                if let Some(id) = sent {
+                    let (_, card) = card.unwrap();
+                    let face = snapshot.public_cards[id];
+                    assert_eq!((face.suit, face.rank), (card.suit, card.rank));
+                    shown += 1;
+                }
+            }
+        }
+        assert_eq!(snapshot.public_cards.len(), shown);
+    }
+
+    // How many cards of each zone the viewer was sent faces for, by zone name
+    fn faces_by_zone(snapshot: &wrapper::GameSnapshot) -> Vec<(Option<u32>, String, usize)> {
+        let mut zones: Vec<_> = snapshot
+            .zones
+            .iter()
+            .map(|zone| {
+                let name = zone.display_name.clone().unwrap_or_default();
+                (zone.owner, name, zone.cards.iter().flatten().count())
+            })
+            .collect();
+        zones.sort();
+        zones
+    }
+
+    #[test]
+    fn snapshots_only_show_what_each_viewer_can_see() {
+        let (mut state, [mut first, mut second, mut spectator]) = started();
+        state.broadcast(None);
+        let first = received(&mut first).pop().unwrap();
+        let second = received(&mut second).pop().unwrap();
+        let spectator = received(&mut spectator).pop().unwrap();
+
+        assert_only_visible_faces(&state, Some(0), &first);
+        assert_only_visible_faces(&state, Some(1), &second);
+        assert_only_visible_faces(&state, None, &spectator);
+
+        let zones = |hands: [usize; 2]| {
+            vec![
+                (None, "deck".to_string(), 0),
+                (None, "discard".to_string(), 1),
+                (None, "under".to_string(), 1),
+                (Some(0), "hand".to_string(), hands[0]),
+                (Some(1), "hand".to_string(), hands[1]),
+            ]
+        };
+        assert_eq!(faces_by_zone(&first), zones([2, 0]));
+        assert_eq!(faces_by_zone(&second), zones([0, 2]));
+        assert_eq!(faces_by_zone(&spectator), zones([0, 0]));
+        assert!(second.offer.is_none() && spectator.offer.is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::engine::core::interpreter::{
+    game::Game,
+    state::game_state::{CardID, CardMovements},
+};
+use crate::engine::core::types::zones::ZoneVisibilityRule;
+
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+// The ids one viewer knows cards by, engine ids are sequential so sending them would let players
+// follow hidden cards around. A card keeps its id only while the viewer can keep track of it,
+// once it's hidden or shuffled it gets a new random one the next time it's seen
+#[derive(Debug, Clone, Default)]
+pub struct CardIdentities {
+    ids: HashMap<CardID, u32>,
+    cards: HashMap<u32, CardID>,
+    used: HashSet<u32>, // Never handed out twice so an old id can't point at a new card
+}
+
+impl CardIdentities {
+    // Forgets cards that went through a zone the viewer couldn't fully see or got shuffled
+    // The zone a card ends up in is left to forget_unseen
+    pub fn forget_moved(&mut self, game: &Game, viewer: Option<u64>, movements: &CardMovements) {
+        for card_id in movements.shuffled.iter() {
+            self.forget(*card_id);
+        }
+        for (card_id, zones) in movements.entered.iter() {
+            let current = game.zone_of_card(*card_id);
+            let hidden = zones.iter().any(|zone_id| {
+                Some(*zone_id) != current
+                    && !matches!(
+                        game.zone_visibility(*zone_id, viewer),
+                        ZoneVisibilityRule::Visible
+                    )
+            });
+            if hidden {
+                self.forget(*card_id);
+            }
+        }
+    }
+
+    // Anything not visible right now loses its id
+    pub fn forget_unseen(&mut self, visible: &HashSet<CardID>) {
+        let unseen: Vec<CardID> = self
+            .ids
+            .keys()
+            .filter(|card_id| !visible.contains(card_id))
+            .copied()
+            .collect();
+        for card_id in unseen {
+            self.forget(card_id);
+        }
+    }
+
+    pub fn id_for(&mut self, card_id: CardID) -> u32 {
+        if let Some(id) = self.ids.get(&card_id) {
+            return *id;
+        }
+        let mut rng = rand::rng();
+        let id = loop {
+            let candidate: u32 = rng.random();
+            if self.used.insert(candidate) {
+                break candidate;
+            }
+        };
+        self.ids.insert(card_id, id);
+        self.cards.insert(id, card_id);
+        id
+    }
+
+    // Turns an id the viewer sent back into the engine's card, only works for cards they can see
+    // or were offered
+    pub fn resolve(&self, id: u32) -> Option<CardID> {
+        self.cards.get(&id).copied()
+    }
+
+    fn forget(&mut self, card_id: CardID) {
+        if let Some(id) = self.ids.remove(&card_id) {
+            self.cards.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::core::dsl;
+    use crate::engine::core::interpreter::{
+        game::EngineStatus,
+        state::{execution_state::OfferResponse, game_state::GameZoneID},
+    };
+
+    // Cards start face up on the table, each case of the offer moves them once
+    const RULESET: &str = r#"
+        players 2..2;
+        ranks Two, Three, Four;
+        suits Hearts, Spades;
+        assignment Player;
+        player class Player { zones hand; for all players; }
+        zone class Open { owner visible; others visible; }
+        zone class Closed { owner hidden; others hidden; }
+        player zone hand: Open;
+        zone table: Open;
+        zone board: Open;
+        zone deck: Closed;
+        start setup;
+
+        phase setup {
+            generate all cards into table;
+            offer to current player {
+                case "Shuffle" handle shuffle zone table;
+                case "Slide" handle move top of table to board;
+                case "Through the deck" handle { move top of table to deck; move top of deck to board; }
+                case "Bury" handle move top of table to deck;
+            }
+        }
+    "#;
+
+    fn run(game: &mut Game) {
+        while let Ok(status) = game.eval_statement() {
+            if matches!(status, EngineStatus::Blocked | EngineStatus::Finished) {
+                return;
+            }
+        }
+        panic!("test ruleset failed");
+    }
+
+    // Ids for everything on the table, then the moves of the chosen case forgotten
+    fn after_case(case: usize) -> (Game, CardIdentities, HashMap<CardID, u32>) {
+        let config = dsl::parse_config(RULESET).expect("test ruleset parses");
+        let mut game = Game::new(config, 3).unwrap();
+        game.update_players(2).unwrap();
+        game.init().unwrap();
+        run(&mut game);
+        game.take_card_movements();
+
+        let mut identities = CardIdentities::default();
+        let table = game.cards_in_zone(zone(&game, "table"));
+        let before = table
+            .iter()
+            .map(|card_id| (*card_id, identities.id_for(*card_id)))
+            .collect();
+
+        let response = OfferResponse {
+            case,
+            choices: Vec::new(),
+        };
+        game.respond_to_offer(0, &response).unwrap();
+        run(&mut game);
+        let movements = game.take_card_movements();
+        identities.forget_moved(&game, Some(0), &movements);
+        (game, identities, before)
+    }
+
+    fn zone(game: &Game, name: &str) -> GameZoneID {
+        game.visible_zones(None)
+            .into_iter()
+            .find(|zone| zone.name.as_deref() == Some(name))
+            .unwrap()
+            .zone_id
+    }
+
+    fn top_of_table(game: &Game) -> CardID {
+        *game.cards_in_zone(zone(game, "table")).last().unwrap()
+    }
+
+    #[test]
+    fn ids_resolve_back_to_their_cards() {
+        let mut identities = CardIdentities::default();
+        assert_eq!(identities.resolve(0), None);
+        let id = identities.id_for(7);
+        assert_eq!(identities.id_for(7), id);
+        assert_eq!(identities.resolve(id), Some(7));
+        let other = identities.id_for(8);
+        assert_ne!(other, id);
+        assert_eq!(identities.resolve(other), Some(8));
+    }
+
+    #[test]
+    fn forgotten_ids_are_never_handed_out_again() {
+        let mut identities = CardIdentities::default();
+        let old = identities.id_for(7);
+        identities.forget_unseen(&HashSet::new());
+        assert_eq!(identities.resolve(old), None);
+        let new = identities.id_for(7);
+        assert_ne!(new, old);
+        assert_eq!(identities.resolve(new), Some(7));
+    }
+
+    #[test]
+    fn cards_moved_in_plain_sight_keep_their_ids() {
+        let (game, mut identities, before) = after_case(1);
+        for (card_id, id) in before {
+            assert_eq!(identities.id_for(card_id), id);
+            assert_eq!(identities.resolve(id), Some(card_id));
+        }
+        assert_eq!(game.cards_in_zone(zone(&game, "board")).len(), 1);
+    }
+
+    #[test]
+    fn shuffled_cards_get_new_ids() {
+        let (_, mut identities, before) = after_case(0);
+        for (card_id, id) in before {
+            assert_eq!(identities.resolve(id), None);
+            assert_ne!(identities.id_for(card_id), id);
+        }
+    }
+
+    #[test]
+    fn cards_passing_through_a_hidden_zone_get_new_ids() {
+        let (game, mut identities, before) = after_case(2);
+        let moved = game.cards_in_zone(zone(&game, "board"))[0];
+        assert_eq!(identities.resolve(before[&moved]), None);
+        assert_ne!(identities.id_for(moved), before[&moved]);
+
+        // The rest of the table never left it
+        let stayed = top_of_table(&game);
+        assert_eq!(identities.id_for(stayed), before[&stayed]);
+    }
+
+    #[test]
+    fn cards_ending_up_hidden_are_forgotten_once_unseen() {
+        let (game, mut identities, before) = after_case(3);
+        let buried = game.cards_in_zone(zone(&game, "deck"))[0];
+        // Still known until the snapshot works out the viewer can't see it
+        assert_eq!(identities.resolve(before[&buried]), Some(buried));
+
+        let visible: HashSet<CardID> = game
+            .cards_in_zone(zone(&game, "table"))
+            .into_iter()
+            .collect();
+        identities.forget_unseen(&visible);
+        assert_eq!(identities.resolve(before[&buried]), None);
+        for card_id in visible {
+            assert_eq!(identities.id_for(card_id), before[&card_id]);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use tokio::sync::mpsc;
 
 pub struct WebgameJoin {
     pub nickname: Option<String>,
+    pub reconnect_token: Option<String>, // Set when taking back a seat
     pub tx: mpsc::UnboundedSender<wrapper::GameSnapshot>,
 }
 
@@ -1,5 +1,6 @@
 pub mod connections;
 pub mod handler;
+pub mod identities;
 pub mod interface;
 pub mod names;
 pub mod status;
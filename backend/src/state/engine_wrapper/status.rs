@@ -1,8 +1,11 @@
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum InterpreterStatus {
     Setup,                 //Initial state as a game loads
     PendingExecution,      //Some instructions need to be executed
     InstructionDelay(u64), //Delay some seconds (Spawn a task to decr each second)
     Blocked,               //Waiting on some external input from players
+    Finished,              //Ruleset ran to the end or declared winners
     Failed,                // Fatal error / crash
 }
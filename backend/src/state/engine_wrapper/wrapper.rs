@@ -13,8 +13,18 @@ pub enum GameAction {
     SetCards,
     Layout,
     Private,
-    JoinResult(Result<PlayerId, String>),
+    JoinResult(Result<JoinAccepted, String>),
     ChatMsg(GameChat),
+    OfferResult(Result<(), String>),
+}
+
+// Only ever sent to the player it's for, the token is what proves a reconnect is them since the
+// player id is visible to everyone in the room
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub struct JoinAccepted {
+    pub player_id: PlayerId,
+    pub reconnect_token: String,
 }
 
 #[derive(TS, Debug, Clone, Deserialize, Serialize)]
@@ -31,6 +41,24 @@ pub enum PlayerCommand {
     StartGame,
     Heartbeat,
     SendMsg(String),
+    RespondToOffer(OfferAnswer),
+}
+
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub struct OfferAnswer {
+    pub case: u32,
+    pub choices: Vec<OfferChoiceAnswer>, // One per choice of the case, in order
+}
+
+// Players are referred to by order, cards by the ids the player was sent
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub enum OfferChoiceAnswer {
+    Player(u64),
+    Players(Vec<u64>),
+    Card(u32),
+    Cards(Vec<u32>), // Also answers MoveCards
 }
 
 #[derive(TS, Debug, Clone, Deserialize, Serialize)]
@@ -42,12 +70,54 @@ pub struct GameSnapshot {
     pub status: game_state::GameStatus,
     pub public_cards: HashMap<u32, cards::Card>,
     pub zones: Vec<ZoneSnapshot>,
+    pub offer: Option<OfferSnapshot>, // Only sent to players the offer is made to
+}
+
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub struct OfferSnapshot {
+    pub cases: Vec<OfferCaseSnapshot>,
+}
+
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub struct OfferCaseSnapshot {
+    pub case: u32,
+    pub message: String,
+    pub choices: Vec<OfferChoiceSnapshot>,
+}
+
+// Options are None if they depend on an earlier choice, cards the player can't see are left out
+#[derive(TS, Debug, Clone, Deserialize, Serialize)]
+#[ts(export)]
+pub enum OfferChoiceSnapshot {
+    Player {
+        name: String,
+        options: Option<Vec<u64>>,
+    },
+    Players {
+        name: String,
+        options: Option<Vec<u64>>,
+    },
+    Card {
+        name: String,
+        options: Option<Vec<u32>>,
+    },
+    Cards {
+        name: String,
+        options: Option<Vec<u32>>,
+    },
+    MoveCards {
+        from: Option<u32>,
+        to: Option<u32>,
+        options: Option<Vec<u32>>, // Ids for the cards in the from zone, even if it's hidden
+    },
 }
 
 #[derive(TS, Debug, Clone, Deserialize, Serialize)]
 #[ts(export)]
 pub struct ZoneSnapshot {
-    pub cards: Vec<u32>,
+    pub cards: Vec<Option<u32>>, // None for cards that are face down to this player
     pub owner: Option<u32>,
     pub display_name: Option<String>,
     pub zone_id: u32,
@@ -62,6 +132,7 @@ impl GameSnapshot {
             status: game_state::GameStatus::Invalid,
             public_cards: HashMap::new(),
             zones: Vec::new(),
+            offer: None,
         }
     }
     pub fn add_action(&mut self, action: GameAction) {
@@ -0,0 +1,114 @@
+use crate::engine::core::interpreter::{
+    config::GameConfig,
+    migration::{self, CONFIG_VERSION, MigrationError},
+    state::event_log::GameEvent,
+};
+use crate::state::app::AppState;
+use anyhow;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx;
+use uuid::Uuid;
+
+use super::web::GameRecord;
+
+pub struct GameLog {
+    pub game_log_id: Uuid,
+    pub created_at: DateTime<Utc>,
+
+    pub ruleset_id: Uuid,
+    pub config: String,
+    pub config_hash: String,
+    pub config_version: i32,
+    pub revision: Option<i32>, // Missing for games played before revisions were pinned
+    pub seed: i64,             // Stored as the u64 the game was seeded with, bit for bit
+    pub events: String,
+}
+
+impl GameLog {
+    pub fn seed(&self) -> u64 {
+        self.seed as u64
+    }
+
+    pub fn config(&self) -> Result<GameConfig, MigrationError> {
+        migration::load_config(&self.config, self.config_version)
+    }
+
+    pub fn events(&self) -> anyhow::Result<Vec<GameEvent>> {
+        Ok(serde_json::from_str(&self.events)?)
+    }
+
+    // Why the game crashed, if it did
+    pub fn failure(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.events()?.into_iter().find_map(|event| match event {
+            GameEvent::Crashed { error } => Some(error),
+            _ => None,
+        }))
+    }
+}
+
+// Hash of a ruleset's stored config, tells apart games played on different versions of it
+pub fn config_hash(config: &str) -> String {
+    format!("{:x}", Sha256::digest(config.as_bytes()))
+}
+
+pub async fn save_game_log(state: AppState, record: &GameRecord) -> anyhow::Result<Uuid> {
+    let config = serde_json::to_string(&record.config)?;
+    let events = serde_json::to_string(&record.events)?;
+    let game_log_id = sqlx::query_scalar!(
+        r#"insert into "game_log" (ruleset_id, config, config_hash, config_version, revision, seed, events) values ($1, $2, $3, $4, $5, $6, $7) returning game_log_id"#,
+        record.ruleset_id, config, record.config_hash, CONFIG_VERSION, record.revision, record.seed as i64, events
+    ).fetch_one(&state.db).await?;
+    Ok(game_log_id)
+}
+
+pub async fn get_game_log(state: AppState, game_log_id: &Uuid) -> anyhow::Result<GameLog> {
+    let result = sqlx::query_as!(
+        GameLog,
+        r#"
+        select game_log_id, created_at, ruleset_id, config, config_hash, config_version, revision, seed, events
+        from "game_log" where game_log_id=$1
+        "#,
+        game_log_id.clone(),
+    )
+    .fetch_one(&state.db)
+    .await?;
+    Ok(result)
+}
+
+pub async fn get_game_logs_by_ruleset(
+    state: AppState,
+    ruleset_id: &Uuid,
+    limit: u32,
+    offset: u32,
+) -> anyhow::Result<Vec<GameLog>> {
+    let limit = i64::from(limit);
+    let offset = i64::from(offset);
+
+    let result = sqlx::query_as!(
+        GameLog,
+        r#"
+        select game_log_id, created_at, ruleset_id, config, config_hash, config_version, revision, seed, events
+        from "game_log"
+        where ruleset_id = $1
+        order by created_at desc
+        limit $2 offset $3
+        "#,
+        ruleset_id.clone(),
+        limit,
+        offset,
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(result)
+}
+
+pub async fn count_game_logs_by_ruleset(state: AppState, ruleset_id: &Uuid) -> anyhow::Result<u32> {
+    let result = sqlx::query_scalar!(
+        r#"select count(*) from "game_log" where ruleset_id = $1"#,
+        ruleset_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+    Ok(result.unwrap_or(0).try_into()?)
+}
@@ -1,6 +1,9 @@
 pub mod app; //Manipulation of global state
 pub mod auth;
 pub mod engine_wrapper;
+pub mod game_log;
+pub mod revision;
+pub mod room;
 pub mod ruleset;
 pub mod user;
 pub mod web;
@@ -0,0 +1,131 @@
+use crate::engine::core::interpreter::{
+    config::GameConfig,
+    migration::{self, MigrationError},
+};
+use crate::state::app::AppState;
+use anyhow;
+use chrono::{DateTime, Utc};
+use sqlx::{self, PgConnection};
+use uuid::Uuid;
+
+// Copy of a ruleset as it was after one save, these are never changed once written
+pub struct RulesetRevision {
+    pub ruleset_id: Uuid,
+    pub revision: i32,
+    pub created_at: DateTime<Utc>,
+
+    pub config: String,
+    pub config_version: i32,
+    pub title: String,
+    pub description: String,
+}
+
+impl RulesetRevision {
+    pub fn config(&self) -> Result<GameConfig, MigrationError> {
+        migration::load_config(&self.config, self.config_version)
+    }
+}
+
+// Called inside the transaction that saves the ruleset, so a save and its revision go in together
+pub async fn record_revision(
+    db: &mut PgConnection,
+    ruleset_id: &Uuid,
+    config: &str,
+    version: i32,
+    title: &str,
+    description: &str,
+) -> anyhow::Result<i32> {
+    let revision = sqlx::query_scalar!(
+        r#"
+        insert into "ruleset_revision" (ruleset_id, revision, config, config_version, title, description)
+        select $1, coalesce(max(revision), 0) + 1, $2, $3, $4, $5
+        from "ruleset_revision" where ruleset_id = $1
+        returning revision
+        "#,
+        ruleset_id.clone(),
+        config,
+        version,
+        title,
+        description,
+    )
+    .fetch_one(db)
+    .await?;
+    Ok(revision)
+}
+
+pub async fn get_revision(
+    state: AppState,
+    ruleset_id: &Uuid,
+    revision: i32,
+) -> anyhow::Result<RulesetRevision> {
+    let result = sqlx::query_as!(
+        RulesetRevision,
+        r#"
+        select ruleset_id, revision, created_at, config, config_version, title, description
+        from "ruleset_revision"
+        where ruleset_id = $1 and revision = $2
+        "#,
+        ruleset_id.clone(),
+        revision,
+    )
+    .fetch_one(&state.db)
+    .await?;
+    Ok(result)
+}
+
+// What a ruleset is right now, new games start from this
+pub async fn get_latest_revision(
+    state: AppState,
+    ruleset_id: &Uuid,
+) -> anyhow::Result<RulesetRevision> {
+    let result = sqlx::query_as!(
+        RulesetRevision,
+        r#"
+        select ruleset_id, revision, created_at, config, config_version, title, description
+        from "ruleset_revision"
+        where ruleset_id = $1
+        order by revision desc
+        limit 1
+        "#,
+        ruleset_id.clone(),
+    )
+    .fetch_one(&state.db)
+    .await?;
+    Ok(result)
+}
+
+pub async fn get_revisions(
+    state: AppState,
+    ruleset_id: &Uuid,
+    limit: u32,
+    offset: u32,
+) -> anyhow::Result<Vec<RulesetRevision>> {
+    let limit: i64 = limit.into();
+    let offset: i64 = offset.into();
+    let result = sqlx::query_as!(
+        RulesetRevision,
+        r#"
+        select ruleset_id, revision, created_at, config, config_version, title, description
+        from "ruleset_revision"
+        where ruleset_id = $1
+        order by revision desc
+        limit $2 offset $3
+        "#,
+        ruleset_id.clone(),
+        limit,
+        offset,
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(result)
+}
+
+pub async fn count_revisions(state: AppState, ruleset_id: &Uuid) -> anyhow::Result<u32> {
+    let count = sqlx::query_scalar!(
+        r#"select count(*) from "ruleset_revision" where ruleset_id = $1"#,
+        ruleset_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+    Ok(count.unwrap_or(0).try_into()?)
+}
@@ -0,0 +1,81 @@
+use crate::engine::core::interpreter::{
+    config::GameConfig,
+    migration::{self, CONFIG_VERSION, MigrationError},
+};
+use crate::state::app::AppState;
+use anyhow;
+use chrono::{DateTime, Utc};
+use sqlx;
+use uuid::Uuid;
+
+// Last checkpoint of a room that was still running, rooms are removed once they end
+pub struct Room {
+    pub room_code: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+
+    pub ruleset_id: Uuid,
+    pub name: String,
+    pub config: String,
+    pub config_hash: String,
+    pub checkpoint: String,
+    pub config_version: i32,
+    pub revision: Option<i32>, // Missing for rooms started before revisions were pinned
+}
+
+impl Room {
+    pub fn config(&self) -> Result<GameConfig, MigrationError> {
+        migration::load_config(&self.config, self.config_version)
+    }
+}
+
+// What a room's checkpoints are saved alongside, fixed for as long as the room runs
+pub struct SavedRoom {
+    pub ruleset_id: Uuid,
+    pub revision: Option<i32>,
+    pub name: String,
+    pub config: String,
+    pub config_hash: String,
+}
+
+pub async fn save_room(
+    state: AppState,
+    room_code: u64,
+    saved: &SavedRoom,
+    checkpoint: &str,
+) -> anyhow::Result<()> {
+    let room_code: i64 = room_code.try_into()?;
+    sqlx::query!(
+        r#"
+        insert into "room" (room_code, ruleset_id, name, config, config_hash, checkpoint, config_version, revision)
+        values ($1, $2, $3, $4, $5, $6, $7, $8)
+        on conflict (room_code) do update set checkpoint = excluded.checkpoint
+        "#,
+        room_code,
+        saved.ruleset_id,
+        saved.name,
+        saved.config,
+        saved.config_hash,
+        checkpoint,
+        CONFIG_VERSION,
+        saved.revision,
+    )
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_room(state: AppState, room_code: u64) -> anyhow::Result<()> {
+    let room_code: i64 = room_code.try_into()?;
+    sqlx::query!(r#"delete from "room" where room_code = $1"#, room_code)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_rooms(state: AppState) -> anyhow::Result<Vec<Room>> {
+    let result = sqlx::query_as!(Room, r#"select * from "room""#)
+        .fetch_all(&state.db)
+        .await?;
+    Ok(result)
+}
@@ -1,10 +1,15 @@
+use crate::engine::core::interpreter::{
+    migration::{self, CONFIG_VERSION},
+    validation,
+};
 use crate::state::app::AppState;
 use anyhow;
 use chrono::{DateTime, Utc};
 use sqlx;
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use super::user;
+use super::{revision, user};
 
 pub struct Ruleset {
     pub ruleset_id: Uuid,
@@ -21,6 +26,20 @@ pub struct Ruleset {
     pub description: String,
     pub published: bool,
     pub title: String,
+
+    pub published_revision: Option<i32>, // Set while published, what everyone but the owner sees
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+// One ruleset in a based_on tree, depth counts steps away from the ruleset it was looked up from
+pub struct LineageEntry {
+    pub ruleset_id: Uuid,
+    pub based_on: Option<Uuid>,
+    pub owner: Uuid,
+    pub owner_name: String,
+    pub title: String,
+    pub published_title: Option<String>,
+    pub depth: i32,
 }
 
 pub async fn create_ruleset(
@@ -33,10 +52,13 @@ pub async fn create_ruleset(
     owner: &Uuid,
 ) -> anyhow::Result<Uuid> {
     let owner_name = user::get_user(state.clone(), owner).await?.username;
+    let mut tx = state.db.begin().await?;
     let ruleset_id = sqlx::query_scalar!(
         r#"insert into "ruleset" (config, config_version, based_on, owner, owner_name, title, description) values ($1, $2, $3, $4, $5, $6, $7) returning ruleset_id"#,
         config.to_string(), version, based_on.cloned(), owner.clone(), owner_name, title.to_string(), description.to_string()
-    ).fetch_one(&state.db).await?;
+    ).fetch_one(&mut *tx).await?;
+    revision::record_revision(&mut tx, &ruleset_id, config, version, title, description).await?;
+    tx.commit().await?;
     Ok(ruleset_id)
 }
 
@@ -52,28 +74,36 @@ pub async fn ruleset_get_owner(state: AppState, ruleset_id: &Uuid) -> anyhow::Re
     Ok(owner_id)
 }
 
+// Every save is kept as a new revision, returns its number
 pub async fn update_ruleset(
     state: AppState,
     ruleset_id: &Uuid,
     new_config: &str,
+    version: i32,
     title: &str,
     description: &str,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i32> {
+    let mut tx = state.db.begin().await?;
     sqlx::query!(
         r#"
         update "ruleset"
-        set config = $1, title = $2, description = $3
-        where ruleset_id = $4
+        set config = $1, config_version = $2, title = $3, description = $4
+        where ruleset_id = $5
         "#,
         new_config,
+        version,
         title.to_string(),
         description.to_string(),
         ruleset_id.clone(),
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
+    let revision =
+        revision::record_revision(&mut tx, ruleset_id, new_config, version, title, description)
+            .await?;
+    tx.commit().await?;
 
-    Ok(())
+    Ok(revision)
 }
 
 pub async fn get_ruleset(state: AppState, ruleset_id: &Uuid) -> anyhow::Result<Ruleset> {
@@ -89,19 +119,41 @@ pub async fn get_ruleset(state: AppState, ruleset_id: &Uuid) -> anyhow::Result<R
     Ok(result)
 }
 
+// Drafts are only listed for their owner, everyone else gets the published versions
 pub async fn get_rulesets_by_owner(
     state: AppState,
     owner: &Uuid,
+    include_drafts: bool,
     limit: u32,
     offset: u32,
 ) -> anyhow::Result<Vec<Ruleset>> {
     let limit: i64 = limit.try_into()?;
     let offset: i64 = offset.try_into()?;
 
+    if !include_drafts {
+        let result = sqlx::query_as!(
+            Ruleset,
+            r#"
+            select r.ruleset_id, r.created_at, r.updated_at, rev.config, rev.config_version, r.based_on, r.owner, r.owner_name, rev.description, r.published, rev.title, r.published_revision, r.published_at
+            from "ruleset" r
+            join "ruleset_revision" rev on rev.ruleset_id = r.ruleset_id and rev.revision = r.published_revision
+            where r.owner = $1 and r.published
+            order by r.created_at desc
+            limit $2 offset $3
+            "#,
+            owner.clone(),
+            limit,
+            offset,
+        )
+        .fetch_all(&state.db)
+        .await?;
+        return Ok(result);
+    }
+
     let result = sqlx::query_as!(
         Ruleset,
         r#"
-        select ruleset_id, created_at, updated_at, config, config_version, based_on, owner, owner_name, description, published, title
+        select ruleset_id, created_at, updated_at, config, config_version, based_on, owner, owner_name, description, published, title, published_revision, published_at
         from "ruleset"
         where owner = $1
         order by created_at desc
@@ -116,13 +168,22 @@ pub async fn get_rulesets_by_owner(
     Ok(result)
 }
 
-pub async fn count_rulesets_by_owner(state: AppState, owner: &Uuid) -> anyhow::Result<u32> {
-    let result = sqlx::query_scalar!(r#"select count(*) from "ruleset" where owner = $1"#, owner)
-        .fetch_one(&state.db)
-        .await?;
+pub async fn count_rulesets_by_owner(
+    state: AppState,
+    owner: &Uuid,
+    include_drafts: bool,
+) -> anyhow::Result<u32> {
+    let result = sqlx::query_scalar!(
+        r#"select count(*) from "ruleset" where owner = $1 and (published or $2)"#,
+        owner,
+        include_drafts
+    )
+    .fetch_one(&state.db)
+    .await?;
     Ok(result.unwrap_or(0).try_into()?)
 }
 
+// Public listing, only published rulesets as they were when they were last published
 pub async fn get_rulesets(
     state: AppState,
     limit: u32,
@@ -133,9 +194,11 @@ pub async fn get_rulesets(
     let result = sqlx::query_as!(
         Ruleset,
         r#"
-        select ruleset_id, created_at, updated_at, config, config_version, based_on, owner, owner_name, description, published, title
-        from "ruleset"
-        order by coalesce(updated_at, created_at) desc
+        select r.ruleset_id, r.created_at, r.updated_at, rev.config, rev.config_version, r.based_on, r.owner, r.owner_name, rev.description, r.published, rev.title, r.published_revision, r.published_at
+        from "ruleset" r
+        join "ruleset_revision" rev on rev.ruleset_id = r.ruleset_id and rev.revision = r.published_revision
+        where r.published
+        order by r.published_at desc
         limit $1 offset $2
         "#,
         limit,
@@ -147,8 +210,237 @@ pub async fn get_rulesets(
 }
 
 pub async fn count_rulesets(state: AppState) -> anyhow::Result<u32> {
-    let count = sqlx::query_scalar!(r#"select count(*) from "ruleset" "#)
+    let count = sqlx::query_scalar!(r#"select count(*) from "ruleset" where published"#)
         .fetch_one(&state.db)
         .await?;
     Ok(count.unwrap_or(0).try_into()?)
 }
+
+// Makes a revision the public version, publishing again moves it to a newer one
+pub async fn publish_ruleset(
+    state: AppState,
+    ruleset_id: &Uuid,
+    revision: i32,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        update "ruleset"
+        set published = true, published_revision = $1, published_at = now()
+        where ruleset_id = $2
+        "#,
+        revision,
+        ruleset_id.clone(),
+    )
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
+
+pub async fn unpublish_ruleset(state: AppState, ruleset_id: &Uuid) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        update "ruleset"
+        set published = false, published_revision = null, published_at = null
+        where ruleset_id = $1
+        "#,
+        ruleset_id.clone(),
+    )
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
+
+// Takes down published revisions that have errors, covers rulesets published before publishing
+// checked them and ones caught by checks added since. Their authors have to fix and republish
+pub async fn unpublish_invalid_rulesets(state: AppState) -> anyhow::Result<u32> {
+    let published = sqlx::query!(
+        r#"
+        select r.ruleset_id, rev.config, rev.config_version
+        from "ruleset" r
+        join "ruleset_revision" rev on rev.ruleset_id = r.ruleset_id and rev.revision = r.published_revision
+        where r.published
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut unpublished = 0;
+    for rs in published {
+        let problem = match migration::load_config(&rs.config, rs.config_version) {
+            Ok(config) => validation::validate_config(&config)
+                .into_iter()
+                .find(|p| p.is_error())
+                .map(|p| p.error.to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(problem) = problem {
+            tracing::warn!("Unpublishing ruleset {}: {problem}", rs.ruleset_id);
+            unpublish_ruleset(state.clone(), &rs.ruleset_id).await?;
+            unpublished += 1;
+        }
+    }
+    Ok(unpublished)
+}
+
+// Rewrites every ruleset and revision saved with an older config version in the current shape,
+// rooms run revisions so both have to move together. Revisions keep their numbers, the upgraded
+// text is the same ruleset, though games started from it afterwards get a different config hash
+// This counts as an edit and moves them up the recently updated listing, so it only runs on request
+pub async fn upgrade_stored_configs(state: AppState) -> anyhow::Result<u32> {
+    let mut tx = state.db.begin().await?;
+
+    let stale = sqlx::query!(
+        r#"select ruleset_id, config, config_version from "ruleset" where config_version < $1"#,
+        CONFIG_VERSION
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut upgraded = 0;
+    for rs in stale {
+        let Some(config) = upgraded_config(&rs.config, rs.config_version, &rs.ruleset_id)? else {
+            continue;
+        };
+        sqlx::query!(
+            r#"update "ruleset" set config = $1, config_version = $2 where ruleset_id = $3 and config_version = $4"#,
+            config,
+            CONFIG_VERSION,
+            rs.ruleset_id,
+            rs.config_version,
+        )
+        .execute(&mut *tx)
+        .await?;
+        upgraded += 1;
+    }
+
+    let stale = sqlx::query!(
+        r#"select ruleset_id, revision, config, config_version from "ruleset_revision" where config_version < $1"#,
+        CONFIG_VERSION
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for rev in stale {
+        let Some(config) = upgraded_config(&rev.config, rev.config_version, &rev.ruleset_id)?
+        else {
+            continue;
+        };
+        sqlx::query!(
+            r#"update "ruleset_revision" set config = $1, config_version = $2 where ruleset_id = $3 and revision = $4 and config_version = $5"#,
+            config,
+            CONFIG_VERSION,
+            rev.ruleset_id,
+            rev.revision,
+            rev.config_version,
+        )
+        .execute(&mut *tx)
+        .await?;
+        upgraded += 1;
+    }
+
+    tx.commit().await?;
+    Ok(upgraded)
+}
+
+// None if the config is broken beyond upgrading, it's left for load_config to report
+fn upgraded_config(
+    config: &str,
+    version: i32,
+    ruleset_id: &Uuid,
+) -> anyhow::Result<Option<String>> {
+    match migration::load_config(config, version) {
+        Ok(config) => Ok(Some(serde_json::to_string(&config)?)),
+        Err(e) => {
+            tracing::error!("Ruleset {ruleset_id} can't be upgraded: {e}");
+            Ok(None)
+        }
+    }
+}
+
+// Every ruleset forked from this one, forks of forks included, nearest first
+// Only published rulesets and the viewer's own are listed, a hidden ruleset's forks hang off the
+// closest ruleset above it that's listed and depth only counts listed steps
+pub async fn get_forks(
+    state: AppState,
+    ruleset_id: &Uuid,
+    viewer: Option<&Uuid>,
+) -> anyhow::Result<Vec<LineageEntry>> {
+    let result = sqlx::query_as!(
+        LineageEntry,
+        r#"
+        with recursive tree as (
+            select r.ruleset_id, r.based_on as shown_parent, v.shown, v.shown::int as depth
+            from "ruleset" r, lateral (select coalesce(r.published or r.owner = $2, false) as shown) v
+            where r.based_on = $1
+            union all
+            select r.ruleset_id, case when t.shown then t.ruleset_id else t.shown_parent end, v.shown, t.depth + v.shown::int
+            from "ruleset" r join tree t on r.based_on = t.ruleset_id,
+            lateral (select coalesce(r.published or r.owner = $2, false) as shown) v
+        )
+        select r.ruleset_id, t.shown_parent as based_on, r.owner, r.owner_name, r.title, rev.title as "published_title?", t.depth as "depth!"
+        from tree t
+        join "ruleset" r on r.ruleset_id = t.ruleset_id
+        left join "ruleset_revision" rev on rev.ruleset_id = r.ruleset_id and rev.revision = r.published_revision
+        where t.shown
+        order by t.depth, r.created_at
+        "#,
+        ruleset_id.clone(),
+        viewer.cloned(),
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(result)
+}
+
+// Parent, grandparent and so on up to the ruleset that started the line
+// Like get_forks, hidden ancestors are skipped and each listed one is based on the next listed one
+pub async fn get_ancestry(
+    state: AppState,
+    ruleset_id: &Uuid,
+    viewer: Option<&Uuid>,
+) -> anyhow::Result<Vec<LineageEntry>> {
+    let result = sqlx::query_as!(
+        LineageEntry,
+        r#"
+        with recursive chain as (
+            select based_on as ruleset_id, 1 as depth from "ruleset" where ruleset_id = $1
+            union all
+            select r.based_on, c.depth + 1 from "ruleset" r join chain c on r.ruleset_id = c.ruleset_id
+        )
+        select r.ruleset_id, lead(r.ruleset_id) over (order by c.depth) as based_on, r.owner, r.owner_name, r.title,
+            rev.title as "published_title?", row_number() over (order by c.depth)::int as "depth!"
+        from chain c
+        join "ruleset" r on r.ruleset_id = c.ruleset_id
+        left join "ruleset_revision" rev on rev.ruleset_id = r.ruleset_id and rev.revision = r.published_revision
+        where r.published or r.owner = $2
+        order by c.depth
+        "#,
+        ruleset_id.clone(),
+        viewer.cloned(),
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(result)
+}
+
+// Published direct forks of each ruleset, rulesets nobody forked are left out
+pub async fn count_forks(
+    state: AppState,
+    ruleset_ids: &[Uuid],
+) -> anyhow::Result<HashMap<Uuid, u32>> {
+    let rows = sqlx::query!(
+        r#"
+        select based_on as "based_on!", count(*) as "forks!"
+        from "ruleset"
+        where based_on = any($1) and published
+        group by based_on
+        "#,
+        ruleset_ids,
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut forks = HashMap::new();
+    for row in rows {
+        forks.insert(row.based_on, row.forks.try_into()?);
+    }
+    Ok(forks)
+}
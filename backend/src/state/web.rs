@@ -1,16 +1,18 @@
 use crate::engine::core::interpreter;
-use interpreter::config;
+use interpreter::{config, state::event_log::GameEvent};
 use std::collections::{HashMap, VecDeque};
 
 use super::engine_wrapper::{
-    handler::WebGameState, interface::WebgameRequest, status::InterpreterStatus,
+    handler::{RoomCheckpoint, WebGameState},
+    interface::WebgameRequest,
+    status::InterpreterStatus,
 };
 
 use std::fmt;
 
 use tokio::{
     sync::mpsc,
-    time::{Duration, sleep},
+    time::{Duration, Instant, sleep_until},
 };
 
 use tracing::info;
@@ -20,51 +22,119 @@ pub struct WebGame {
     pub rx: mpsc::UnboundedReceiver<WebgameRequest>,
     pub name: String,
     pub ruleset_id: uuid::Uuid,
+    pub revision: Option<i32>,
+    pub config_hash: String,
+    pub seed: u64,
+}
+
+// What's kept of a room once it closes, enough to replay the game
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub ruleset_id: uuid::Uuid,
+    pub revision: Option<i32>,
+    pub config_hash: String,
+    pub seed: u64,
+    pub config: config::GameConfig,
+    pub events: Vec<GameEvent>,
 }
 impl fmt::Debug for WebGame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("WebGame")
             .field("name", &self.name)
             .field("ruleset_id", &self.ruleset_id)
+            .field("revision", &self.revision)
+            .field("config_hash", &self.config_hash)
+            .field("seed", &self.seed)
             .finish()
     }
 }
 
+// How stale the saved copy of a room is allowed to get while it's changing
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(15);
+
 impl WebGame {
     pub fn new(
         config: &config::GameConfig,
         name: &str,
         ruleset_id: &uuid::Uuid,
-    ) -> (Self, mpsc::UnboundedSender<WebgameRequest>) {
+        revision: i32,
+        config_hash: &str,
+    ) -> Result<(Self, mpsc::UnboundedSender<WebgameRequest>), config::ConfigError> {
         let (tx, rx) = mpsc::unbounded_channel::<WebgameRequest>();
+        let seed: u64 = rand::random();
 
         let state = WebGameState {
             connections: HashMap::new(),
+            identities: HashMap::new(),
             player_order: Vec::new(),
             public_action_queue: VecDeque::new(),
-            game: interpreter::game::Game::new(config.clone()),
+            game: interpreter::game::Game::new(config.clone(), seed)?,
             status: InterpreterStatus::Setup,
         };
 
-        (
+        Ok((
             Self {
                 state: state,
                 rx: rx,
                 name: name.to_string(),
                 ruleset_id: ruleset_id.clone(),
+                revision: Some(revision),
+                config_hash: config_hash.to_string(),
+                seed,
+            },
+            tx,
+        ))
+    }
+
+    // Picks a room back up from its last checkpoint
+    pub fn restore(
+        config: config::GameConfig,
+        name: &str,
+        ruleset_id: &uuid::Uuid,
+        revision: Option<i32>,
+        config_hash: &str,
+        checkpoint: RoomCheckpoint,
+    ) -> (Self, mpsc::UnboundedSender<WebgameRequest>) {
+        let (tx, rx) = mpsc::unbounded_channel::<WebgameRequest>();
+        let state = WebGameState::restore(config, checkpoint);
+        (
+            Self {
+                seed: state.game.seed(),
+                state,
+                rx,
+                name: name.to_string(),
+                ruleset_id: *ruleset_id,
+                revision,
+                config_hash: config_hash.to_string(),
             },
             tx,
         )
     }
 
-    #[tracing::instrument]
-    pub async fn run(mut self) {
+    #[tracing::instrument(skip(checkpoints))]
+    pub async fn run(mut self, checkpoints: mpsc::UnboundedSender<RoomCheckpoint>) -> GameRecord {
         info!("Starting game");
         let mut game_valid: bool = true;
+        let mut resume_at: Option<Instant> = None;
+        let mut changed = false;
+        let mut last_checkpoint = Instant::now();
         while game_valid {
+            if changed && last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                if checkpoints.send(self.state.checkpoint()).is_err() {
+                    tracing::warn!("Nothing is saving checkpoints for this room");
+                }
+                changed = false;
+                last_checkpoint = Instant::now();
+            }
+
             match self.state.status {
-                InterpreterStatus::PendingExecution => (),
-                InterpreterStatus::InstructionDelay(_) => (),
+                InterpreterStatus::PendingExecution => {
+                    self.state.run_batch();
+                    changed = true;
+                }
+                InterpreterStatus::InstructionDelay(seconds) if resume_at.is_none() => {
+                    resume_at = Some(Instant::now() + Duration::from_secs(seconds));
+                }
                 _ => (),
             }
 
@@ -75,14 +145,36 @@ impl WebGame {
                 break;
             }
 
+            // Keep executing right away if there's more to run, but let requests in first
+            let wait_until = match self.state.status {
+                InterpreterStatus::PendingExecution => Instant::now(),
+                InterpreterStatus::InstructionDelay(_) => resume_at.unwrap_or_else(Instant::now),
+                _ => Instant::now() + Duration::from_secs(120),
+            };
+            // Wake up early if a checkpoint is due first, it's taken at the top of the loop
+            let checkpoint_at = last_checkpoint + CHECKPOINT_INTERVAL;
+            let checkpoint_first = changed && checkpoint_at < wait_until;
+
             tokio::select! {
+                biased;
                 Some(msg) = self.rx.recv() => {
                     info!("Processing a player request");
                     self.state.process_request(&msg);
+                    changed = true;
                 }
-                _timed_out = sleep(Duration::from_secs(120)) => {
-                    tracing::info!("Game has timed out, quitting");
-                    game_valid = false;
+                _checkpoint = sleep_until(checkpoint_at), if checkpoint_first => (),
+                _timed_out = sleep_until(wait_until) => {
+                    match self.state.status {
+                        InterpreterStatus::PendingExecution => (),
+                        InterpreterStatus::InstructionDelay(_) => {
+                            resume_at = None;
+                            self.state.status = InterpreterStatus::PendingExecution;
+                        }
+                        _ => {
+                            tracing::info!("Game has timed out, quitting");
+                            game_valid = false;
+                        }
+                    }
                 }
             }
         }
@@ -91,5 +183,15 @@ impl WebGame {
             self.state.queue_chat(None, "Game is no longer running");
             self.state.broadcast(None);
         }
+
+        let game = &self.state.game;
+        GameRecord {
+            ruleset_id: self.ruleset_id,
+            revision: self.revision,
+            config_hash: self.config_hash,
+            seed: game.seed(),
+            config: game.config().clone(),
+            events: game.events().to_vec(),
+        }
     }
 }
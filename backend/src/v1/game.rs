@@ -1,44 +1,198 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
 
-use super::schema::game_schema::*;
-use crate::engine::core::interpreter::config;
+use axum_extra::extract::cookie::CookieJar;
+
+use super::schema::{common, game_schema::*};
+use crate::engine::core::interpreter::{replay, validation};
 use crate::errors::{WebError, new_web_error};
 
 use crate::state;
-use state::app::AppState;
+use state::{app::AppState, auth::auth_or_error, game_log, revision};
 
 pub async fn game_code_get(
     State(state): State<AppState>,
     Path(game_code): Path<u64>,
 ) -> impl IntoResponse {
+    let handle = state.room_handle(game_code);
+    let room = match handle {
+        Some(handle) => {
+            // The ruleset can be gone while its rooms are still playing
+            let latest = revision::get_latest_revision(state.clone(), &handle.ruleset_id)
+                .await
+                .ok()
+                .map(|rev| rev.revision);
+            Some(RoomInfo {
+                name: handle.name,
+                ruleset_id: handle.ruleset_id.to_string(),
+                revision: handle.revision,
+                latest_revision: latest,
+                config_hash: handle.config_hash,
+            })
+        }
+        None => None,
+    };
     Json(RoomExistance {
-        exists: state.has_room(game_code),
+        exists: room.is_some(),
+        room,
     })
 }
 
 pub async fn start_game(
     State(state): State<AppState>,
+    jar: CookieJar,
     Json(req): Json<NewGame>,
 ) -> Result<Json<GameInfo>, WebError> {
     let ruleset_id = uuid::Uuid::parse_str(&req.ruleset_id)
         .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "string is not a UUID"))?;
-    let rs = state::ruleset::get_ruleset(state.clone(), &ruleset_id)
-        .await
-        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "ruleset not found"))?;
+    // Authors can try out their drafts, everyone else plays the published revision
+    let rev = super::ruleset::viewable_revision(state.clone(), jar, &ruleset_id).await?;
+
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
 
-    let config: config::GameConfig = serde_json::from_str(&rs.config)
-        .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Corrupted config"))?;
+    if let Some(problem) = validation::validate_config(&config)
+        .iter()
+        .find(|p| p.is_error())
+    {
+        return Err(new_web_error(
+            StatusCode::BAD_REQUEST,
+            &format!("Ruleset has errors: {problem}"),
+        ));
+    }
 
     let code = state
-        .start_room(&config, &rs.title, &rs.ruleset_id)
+        .start_room(
+            &config,
+            &rev.title,
+            &rev.ruleset_id,
+            rev.revision,
+            &state::game_log::config_hash(&rev.config),
+        )
         .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Unable to start game"))?;
 
     let ginfo = GameInfo { code: code as u32 };
 
     Ok(Json(ginfo))
 }
+
+// Logs show every hidden card, so only the ruleset's author gets to read them
+async fn auth_ruleset_owner(
+    state: AppState,
+    jar: CookieJar,
+    ruleset_id: &uuid::Uuid,
+) -> Result<(), WebError> {
+    let session = auth_or_error(state.clone(), jar).await?;
+    let owner_id = state::ruleset::ruleset_get_owner(state, ruleset_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "ruleset doesn't exist"))?;
+    if owner_id != session.player_id {
+        return Err(new_web_error(
+            StatusCode::FORBIDDEN,
+            "only the ruleset's author can see its games",
+        ));
+    }
+    Ok(())
+}
+
+pub async fn get_game_logs(
+    State(state): State<AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+    pagination: Query<common::Pagination>,
+) -> Result<Json<GameLogListing>, WebError> {
+    auth_ruleset_owner(state.clone(), jar, &ruleset_id).await?;
+
+    let count = game_log::count_game_logs_by_ruleset(state.clone(), &ruleset_id)
+        .await
+        .map_err(|_e| {
+            new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "couldn't get game count")
+        })?;
+    let logs = game_log::get_game_logs_by_ruleset(
+        state,
+        &ruleset_id,
+        pagination.per_page,
+        pagination.page * pagination.per_page,
+    )
+    .await
+    .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Error fetching page"))?;
+
+    Ok(Json(GameLogListing {
+        total: count,
+        pagination: common::Pagination {
+            page: pagination.page,
+            per_page: pagination.per_page,
+        },
+        contents: logs
+            .iter()
+            .map(|log| GameLogPreview {
+                game_log_id: log.game_log_id.to_string(),
+                played_at: log.created_at.to_rfc3339(),
+                revision: log.revision,
+                config_hash: log.config_hash.clone(),
+                seed: log.seed().to_string(),
+                error: log.failure().ok().flatten(),
+            })
+            .collect(),
+    }))
+}
+
+pub async fn replay_game(
+    State(state): State<AppState>,
+    Path(game_log_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<GameReplay>, WebError> {
+    let log = game_log::get_game_log(state.clone(), &game_log_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "game not found"))?;
+    auth_ruleset_owner(state, jar, &log.ruleset_id).await?;
+
+    let config = log
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let events = log
+        .events()
+        .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Corrupted game log"))?;
+    let steps = replay::replay(config, log.seed(), &events).map_err(|e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Couldn't replay game: {e}"),
+        )
+    })?;
+
+    Ok(Json(GameReplay {
+        game_log_id: log.game_log_id.to_string(),
+        ruleset_id: log.ruleset_id.to_string(),
+        revision: log.revision,
+        config_hash: log.config_hash.clone(),
+        seed: log.seed().to_string(),
+        error: log.failure().ok().flatten(),
+        steps: steps
+            .into_iter()
+            .map(|step| ReplayStep {
+                events: step.events,
+                zones: step
+                    .zones
+                    .into_iter()
+                    .map(|zone| ReplayZone {
+                        zone_id: zone.zone_id,
+                        owner: zone.owner,
+                        display_name: zone.name,
+                        cards: zone
+                            .cards
+                            .into_iter()
+                            .flatten()
+                            .map(|(card_id, card)| ReplayCard { card_id, card })
+                            .collect(),
+                    })
+                    .collect(),
+                error: step.error,
+            })
+            .collect(),
+    }))
+}
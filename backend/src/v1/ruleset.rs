@@ -10,12 +10,34 @@ use axum_extra::extract::cookie::CookieJar;
 use super::schema::{common, ruleset_schema::*};
 use crate::errors::{WebError, new_web_error};
 use crate::state;
-use state::{auth::auth_or_error, ruleset};
+use state::{
+    auth::{auth_or_error, user_auth},
+    revision, ruleset,
+};
 
-use crate::engine::core::interpreter::config;
+use crate::engine::core::dsl::{diff, printer};
+use crate::engine::core::interpreter::{config, migration::CONFIG_VERSION, validation};
+use std::collections::HashMap;
 use tracing::{info, instrument};
 
-pub fn rulesets_to_listing(rulesets: Vec<ruleset::Ruleset>) -> Vec<RulesetPreview> {
+pub fn config_diagnostics(config: &config::GameConfig) -> Vec<ConfigDiagnostic> {
+    validation::validate_config(config)
+        .into_iter()
+        .map(|problem| ConfigDiagnostic {
+            severity: match problem.severity {
+                validation::ProblemSeverity::Error => DiagnosticSeverity::Error,
+                validation::ProblemSeverity::Warning => DiagnosticSeverity::Warning,
+            },
+            message: problem.error.to_string(),
+            location: problem.path,
+        })
+        .collect()
+}
+
+pub fn rulesets_to_listing(
+    rulesets: Vec<ruleset::Ruleset>,
+    forks: &HashMap<uuid::Uuid, u32>,
+) -> Vec<RulesetPreview> {
     rulesets
         .iter()
         .map(|rs| RulesetPreview {
@@ -28,16 +50,67 @@ pub fn rulesets_to_listing(rulesets: Vec<ruleset::Ruleset>) -> Vec<RulesetPrevie
                 None => None,
             },
             ruleset_id: rs.ruleset_id.to_string(),
+            published: rs.published,
+            forks: forks.get(&rs.ruleset_id).copied().unwrap_or(0),
         })
         .collect()
 }
 
+// Authors see their latest draft, everyone else the revision that was last published
+pub async fn viewable_revision(
+    state: state::app::AppState,
+    jar: CookieJar,
+    ruleset_id: &uuid::Uuid,
+) -> Result<revision::RulesetRevision, WebError> {
+    let rs = ruleset::get_ruleset(state.clone(), ruleset_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "Not found"))?;
+    let viewer = user_auth(state.clone(), jar).await.ok();
+
+    let result = if viewer.is_some_and(|session| session.player_id == rs.owner) {
+        revision::get_latest_revision(state, ruleset_id).await
+    } else {
+        let published = rs
+            .published_revision
+            .ok_or_else(|| new_web_error(StatusCode::BAD_REQUEST, "Not found"))?;
+        revision::get_revision(state, ruleset_id, published).await
+    };
+    result.map_err(|_e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Couldn't get ruleset revision",
+        )
+    })
+}
+
+async fn require_owner(
+    state: state::app::AppState,
+    jar: CookieJar,
+    ruleset_id: &uuid::Uuid,
+    action: &str,
+) -> Result<(), WebError> {
+    let session = auth_or_error(state.clone(), jar).await?;
+    let owner_id = ruleset::ruleset_get_owner(state, ruleset_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "ruleset doesn't exist"))?;
+    if owner_id != session.player_id {
+        return Err(new_web_error(
+            StatusCode::FORBIDDEN,
+            &format!("only the ruleset's author can {action}"),
+        ));
+    }
+    Ok(())
+}
+
 pub async fn get_ruleset_by_user(
     State(state): State<state::app::AppState>,
     Path(user): Path<uuid::Uuid>,
+    jar: CookieJar,
     pagination: Query<common::Pagination>,
 ) -> Result<Json<RulesetListing>, WebError> {
-    let count = ruleset::count_rulesets_by_owner(state.clone(), &user)
+    let viewer = user_auth(state.clone(), jar).await.ok();
+    let include_drafts = viewer.is_some_and(|session| session.player_id == user);
+    let count = ruleset::count_rulesets_by_owner(state.clone(), &user, include_drafts)
         .await
         .map_err(|_e| {
             new_web_error(
@@ -48,11 +121,19 @@ pub async fn get_ruleset_by_user(
     let rulesets = ruleset::get_rulesets_by_owner(
         state.clone(),
         &user,
+        include_drafts,
         pagination.per_page,
         pagination.page * pagination.per_page,
     )
     .await
     .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Error fetching page"))?;
+    let ids: Vec<uuid::Uuid> = rulesets.iter().map(|rs| rs.ruleset_id).collect();
+    let forks = ruleset::count_forks(state, &ids).await.map_err(|_e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "couldn't get fork counts",
+        )
+    })?;
     //TODO: idk how to extract the query pagination thing
     Ok(Json(RulesetListing {
         total: count,
@@ -60,7 +141,7 @@ pub async fn get_ruleset_by_user(
             page: pagination.page,
             per_page: pagination.per_page,
         },
-        contents: rulesets_to_listing(rulesets),
+        contents: rulesets_to_listing(rulesets, &forks),
     }))
 }
 
@@ -81,6 +162,13 @@ pub async fn get_rulesets(
     )
     .await
     .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Error fetching page"))?;
+    let ids: Vec<uuid::Uuid> = rulesets.iter().map(|rs| rs.ruleset_id).collect();
+    let forks = ruleset::count_forks(state, &ids).await.map_err(|_e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "couldn't get fork counts",
+        )
+    })?;
     //TODO: idk how to extract the query pagination thing
     Ok(Json(RulesetListing {
         total: count,
@@ -88,27 +176,41 @@ pub async fn get_rulesets(
             page: pagination.page,
             per_page: pagination.per_page,
         },
-        contents: rulesets_to_listing(rulesets),
+        contents: rulesets_to_listing(rulesets, &forks),
     }))
 }
 
 pub async fn get_ruleset(
     State(state): State<state::app::AppState>,
     Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
 ) -> Result<Json<RulesetContents>, WebError> {
-    let rs = ruleset::get_ruleset(state, &ruleset_id)
-        .await
-        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "Not found"))?;
-    let config: config::GameConfig = serde_json::from_str(&rs.config)
-        .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Config is unreadable"))?;
+    let rev = viewable_revision(state, jar, &ruleset_id).await?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
 
     Ok(Json(RulesetContents {
-        title: rs.title.to_string(),
-        description: rs.description.to_string(),
+        title: rev.title.to_string(),
+        description: rev.description.to_string(),
         config: config,
     }))
 }
 
+// Same ruleset as text, for diffing versions and copying games to another site
+pub async fn get_ruleset_source(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<String, WebError> {
+    let rev = viewable_revision(state, jar, &ruleset_id).await?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+
+    Ok(printer::print_config(&config))
+}
+
 pub async fn create_ruleset(
     State(state): State<state::app::AppState>,
     jar: CookieJar,
@@ -121,7 +223,7 @@ pub async fn create_ruleset(
     let ruleset_id = ruleset::create_ruleset(
         state.clone(),
         &config,
-        0,
+        CONFIG_VERSION,
         None,
         "New Game",
         "My new game",
@@ -132,6 +234,7 @@ pub async fn create_ruleset(
 
     Ok(Json(RulesetResult {
         ruleset_id: ruleset_id.to_string(),
+        diagnostics: Vec::new(),
     }))
 }
 
@@ -141,7 +244,7 @@ pub async fn edit_ruleset(
     jar: CookieJar,
     Json(req): Json<RulesetContents>,
 ) -> Result<Json<RulesetResult>, WebError> {
-    let session = auth_or_error(state.clone(), jar).await?;
+    let session = auth_or_error(state.clone(), jar.clone()).await?;
     let owner_id = ruleset::ruleset_get_owner(state.clone(), &ruleset_id)
         .await
         .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "ruleset doesn't exist"))?;
@@ -149,20 +252,32 @@ pub async fn edit_ruleset(
     let config = serde_json::to_string(&req.config).map_err(|_e| {
         new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't serialize json")
     })?;
+    // Drafts with errors are still saved, the editor decides what to do with the diagnostics
+    let diagnostics = config_diagnostics(&req.config);
     if owner_id == session.player_id {
-        ruleset::update_ruleset(state, &ruleset_id, &config, &req.title, &req.description)
-            .await
-            .map_err(|_e| {
-                new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't update ruleset")
-            })?;
+        ruleset::update_ruleset(
+            state,
+            &ruleset_id,
+            &config,
+            CONFIG_VERSION,
+            &req.title,
+            &req.description,
+        )
+        .await
+        .map_err(|_e| {
+            new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't update ruleset")
+        })?;
         return Ok(Json(RulesetResult {
             ruleset_id: ruleset_id.to_string(),
+            diagnostics,
         }));
     } else {
+        // Saving someone else's ruleset forks it, which needs the same access as fork_ruleset
+        viewable_revision(state.clone(), jar, &ruleset_id).await?;
         let new_id = ruleset::create_ruleset(
             state,
             &config,
-            0,
+            CONFIG_VERSION,
             Some(&ruleset_id),
             &req.title,
             &req.description,
@@ -177,6 +292,339 @@ pub async fn edit_ruleset(
         })?;
         return Ok(Json(RulesetResult {
             ruleset_id: new_id.to_string(),
+            diagnostics,
         }));
     }
 }
+
+// Revision history includes unpublished drafts, so it's only for the author
+pub async fn get_revisions(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+    pagination: Query<common::Pagination>,
+) -> Result<Json<RevisionListing>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "see its history").await?;
+    let count = revision::count_revisions(state.clone(), &ruleset_id)
+        .await
+        .map_err(|_e| {
+            new_web_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "couldn't get revision count",
+            )
+        })?;
+    let revisions = revision::get_revisions(
+        state.clone(),
+        &ruleset_id,
+        pagination.per_page,
+        pagination.page * pagination.per_page,
+    )
+    .await
+    .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Error fetching page"))?;
+
+    Ok(Json(RevisionListing {
+        total: count,
+        pagination: common::Pagination {
+            page: pagination.page,
+            per_page: pagination.per_page,
+        },
+        contents: revisions
+            .iter()
+            .map(|rev| RevisionPreview {
+                revision: rev.revision,
+                created_at: rev.created_at.to_rfc3339(),
+                title: rev.title.clone(),
+            })
+            .collect(),
+    }))
+}
+
+pub async fn get_revision(
+    State(state): State<state::app::AppState>,
+    Path((ruleset_id, revision)): Path<(uuid::Uuid, i32)>,
+    jar: CookieJar,
+) -> Result<Json<RevisionContents>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "see its history").await?;
+    let rev = revision::get_revision(state, &ruleset_id, revision)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "Not found"))?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+
+    Ok(Json(RevisionContents {
+        revision: rev.revision,
+        created_at: rev.created_at.to_rfc3339(),
+        config,
+        title: rev.title,
+        description: rev.description,
+    }))
+}
+
+pub async fn diff_revisions(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<Json<diff::ConfigDiff>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "see its history").await?;
+    let mut configs = Vec::new();
+    for number in [query.from, query.to] {
+        let rev = revision::get_revision(state.clone(), &ruleset_id, number)
+            .await
+            .map_err(|_e| {
+                new_web_error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Revision {number} not found"),
+                )
+            })?;
+        configs.push(
+            rev.config()
+                .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?,
+        );
+    }
+
+    Ok(Json(diff::diff_configs(&configs[0], &configs[1])))
+}
+
+// Saves an old revision over the ruleset, the history before it is kept and it becomes the newest
+pub async fn rollback_ruleset(
+    State(state): State<state::app::AppState>,
+    Path((ruleset_id, revision)): Path<(uuid::Uuid, i32)>,
+    jar: CookieJar,
+) -> Result<Json<RulesetResult>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "roll it back").await?;
+
+    let rev = revision::get_revision(state.clone(), &ruleset_id, revision)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "revision doesn't exist"))?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let diagnostics = config_diagnostics(&config);
+    let text = serde_json::to_string(&config).map_err(|_e| {
+        new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't serialize json")
+    })?;
+
+    ruleset::update_ruleset(
+        state,
+        &ruleset_id,
+        &text,
+        CONFIG_VERSION,
+        &rev.title,
+        &rev.description,
+    )
+    .await
+    .map_err(|_e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Couldn't roll back ruleset",
+        )
+    })?;
+    info!("Rolled ruleset {ruleset_id} back to revision {revision}");
+
+    Ok(Json(RulesetResult {
+        ruleset_id: ruleset_id.to_string(),
+        diagnostics,
+    }))
+}
+
+// Snapshots the latest revision as the public version, the author can keep editing after
+pub async fn publish_ruleset(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<PublishResult>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "publish it").await?;
+
+    let rev = revision::get_latest_revision(state.clone(), &ruleset_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "ruleset doesn't exist"))?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    // Published rulesets have to be playable, so errors block publishing but warnings don't
+    let diagnostics = config_diagnostics(&config);
+    if let Some(problem) = diagnostics
+        .iter()
+        .find(|d| matches!(d.severity, DiagnosticSeverity::Error))
+    {
+        return Err(new_web_error(
+            StatusCode::BAD_REQUEST,
+            &format!("Ruleset has errors: {}", problem.message),
+        ));
+    }
+
+    ruleset::publish_ruleset(state, &ruleset_id, rev.revision)
+        .await
+        .map_err(|_e| {
+            new_web_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't publish ruleset",
+            )
+        })?;
+    info!(
+        "Published revision {} of ruleset {ruleset_id}",
+        rev.revision
+    );
+
+    Ok(Json(PublishResult {
+        published: true,
+        revision: Some(rev.revision),
+        diagnostics,
+    }))
+}
+
+pub async fn unpublish_ruleset(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<PublishResult>, WebError> {
+    require_owner(state.clone(), jar, &ruleset_id, "unpublish it").await?;
+
+    ruleset::unpublish_ruleset(state, &ruleset_id)
+        .await
+        .map_err(|_e| {
+            new_web_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't unpublish ruleset",
+            )
+        })?;
+
+    Ok(Json(PublishResult {
+        published: false,
+        revision: None,
+        diagnostics: Vec::new(),
+    }))
+}
+
+fn lineage_nodes(
+    entries: Vec<ruleset::LineageEntry>,
+    viewer: Option<uuid::Uuid>,
+) -> Vec<LineageNode> {
+    entries
+        .into_iter()
+        .map(|entry| LineageNode {
+            ruleset_id: entry.ruleset_id.to_string(),
+            based_on: entry.based_on.map(|id| id.to_string()),
+            author_id: entry.owner.to_string(),
+            author_name: entry.owner_name,
+            title: if viewer == Some(entry.owner) {
+                Some(entry.title)
+            } else {
+                entry.published_title
+            },
+            depth: entry.depth,
+        })
+        .collect()
+}
+
+pub async fn get_forks(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<RulesetLineage>, WebError> {
+    let viewer = user_auth(state.clone(), jar)
+        .await
+        .ok()
+        .map(|session| session.player_id);
+    let forks = ruleset::get_forks(state, &ruleset_id, viewer.as_ref())
+        .await
+        .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "couldn't get forks"))?;
+
+    Ok(Json(RulesetLineage {
+        ruleset_id: ruleset_id.to_string(),
+        nodes: lineage_nodes(forks, viewer),
+    }))
+}
+
+pub async fn get_ancestry(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<RulesetLineage>, WebError> {
+    let viewer = user_auth(state.clone(), jar)
+        .await
+        .ok()
+        .map(|session| session.player_id);
+    let ancestry = ruleset::get_ancestry(state, &ruleset_id, viewer.as_ref())
+        .await
+        .map_err(|_e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "couldn't get ancestry"))?;
+
+    Ok(Json(RulesetLineage {
+        ruleset_id: ruleset_id.to_string(),
+        nodes: lineage_nodes(ancestry, viewer),
+    }))
+}
+
+// Copies whatever version of the ruleset the caller can see into a new ruleset of their own
+pub async fn fork_ruleset(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<RulesetResult>, WebError> {
+    let session = auth_or_error(state.clone(), jar.clone()).await?;
+    let rev = viewable_revision(state.clone(), jar, &ruleset_id).await?;
+    let config = rev
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let diagnostics = config_diagnostics(&config);
+    let text = serde_json::to_string(&config).map_err(|_e| {
+        new_web_error(StatusCode::INTERNAL_SERVER_ERROR, "Couldn't serialize json")
+    })?;
+
+    let new_id = ruleset::create_ruleset(
+        state,
+        &text,
+        CONFIG_VERSION,
+        Some(&ruleset_id),
+        &rev.title,
+        &rev.description,
+        &session.player_id,
+    )
+    .await
+    .map_err(|_e| {
+        new_web_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Couldn't create new ruleset",
+        )
+    })?;
+    info!(
+        "Forked revision {} of ruleset {ruleset_id} into {new_id}",
+        rev.revision
+    );
+
+    Ok(Json(RulesetResult {
+        ruleset_id: new_id.to_string(),
+        diagnostics,
+    }))
+}
+
+// Compares a fork with its parent so the author can see what they'd pull in, nothing is changed
+pub async fn get_upstream_changes(
+    State(state): State<state::app::AppState>,
+    Path(ruleset_id): Path<uuid::Uuid>,
+    jar: CookieJar,
+) -> Result<Json<UpstreamChanges>, WebError> {
+    let rs = ruleset::get_ruleset(state.clone(), &ruleset_id)
+        .await
+        .map_err(|_e| new_web_error(StatusCode::BAD_REQUEST, "Not found"))?;
+    let parent_id = rs
+        .based_on
+        .ok_or_else(|| new_web_error(StatusCode::BAD_REQUEST, "ruleset isn't a fork"))?;
+
+    let fork = viewable_revision(state.clone(), jar.clone(), &ruleset_id).await?;
+    let parent = viewable_revision(state, jar, &parent_id).await?;
+    let fork_config = fork
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let parent_config = parent
+        .config()
+        .map_err(|e| new_web_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+
+    Ok(Json(UpstreamChanges {
+        based_on: parent_id.to_string(),
+        upstream_revision: parent.revision,
+        diff: diff::diff_configs(&fork_config, &parent_config),
+    }))
+}
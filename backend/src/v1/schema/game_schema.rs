@@ -1,3 +1,6 @@
+use super::common;
+use crate::engine::core::interpreter::state::event_log::GameEvent;
+use crate::engine::core::types::cards;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
@@ -5,6 +8,18 @@ use ts_rs::TS;
 #[ts(export)]
 pub struct RoomExistance {
     pub exists: bool,
+    pub room: Option<RoomInfo>,
+}
+
+// Which version of the ruleset a room is playing, edits made after it started don't reach it
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RoomInfo {
+    pub name: String,
+    pub ruleset_id: String,
+    pub revision: Option<i32>,
+    pub latest_revision: Option<i32>,
+    pub config_hash: String,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -18,3 +33,58 @@ pub struct NewGame {
 pub struct GameInfo {
     pub code: u32,
 }
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct GameLogPreview {
+    pub game_log_id: String,
+    pub played_at: String,
+    pub revision: Option<i32>,
+    pub config_hash: String,
+    pub seed: String,          // u64 doesn't survive a trip through JSON numbers
+    pub error: Option<String>, // Set if the game crashed
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct GameLogListing {
+    pub total: u32,
+    pub pagination: common::Pagination,
+    pub contents: Vec<GameLogPreview>,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct ReplayCard {
+    pub card_id: u64,
+    pub card: cards::Card,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct ReplayZone {
+    pub zone_id: u64,
+    pub owner: Option<u64>,
+    pub display_name: Option<String>,
+    pub cards: Vec<ReplayCard>, // Bottom to top, everything is face up in a replay
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct ReplayStep {
+    pub events: Vec<GameEvent>, // The recorded input first, then whatever it caused
+    pub zones: Vec<ReplayZone>,
+    pub error: Option<String>,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct GameReplay {
+    pub game_log_id: String,
+    pub ruleset_id: String,
+    pub revision: Option<i32>,
+    pub config_hash: String,
+    pub seed: String,
+    pub error: Option<String>, // Set if the game crashed
+    pub steps: Vec<ReplayStep>,
+}
@@ -1,5 +1,5 @@
 use super::common;
-use crate::engine::core::interpreter::config;
+use crate::engine::core::{dsl::diff, interpreter::config};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
@@ -26,6 +26,8 @@ pub struct RulesetPreview {
     pub author_name: String,
     pub based_on: Option<String>,
     pub ruleset_id: String,
+    pub published: bool,
+    pub forks: u32,
 }
 
 #[derive(TS, Debug, Serialize, Deserialize, Clone)]
@@ -46,4 +48,90 @@ pub struct GameCreateRequest {
 #[ts(export)]
 pub struct RulesetResult {
     pub ruleset_id: String,
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct ConfigDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub location: Vec<String>, // Path into the config, phases/<phase>/evaluate/<statement index>/...
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RevisionPreview {
+    pub revision: i32,
+    pub created_at: String,
+    pub title: String,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RevisionListing {
+    pub total: u32,
+    pub pagination: common::Pagination,
+    pub contents: Vec<RevisionPreview>,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RevisionContents {
+    pub revision: i32,
+    pub created_at: String,
+    pub config: config::GameConfig,
+    pub title: String,
+    pub description: String,
+}
+
+// Revisions to compare, the diff reads as the changes that turn from into to
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct PublishResult {
+    pub published: bool,
+    pub revision: Option<i32>, // Revision everyone else now sees
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+// Title is missing for other people's unpublished rulesets, they still show up to keep the tree whole
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct LineageNode {
+    pub ruleset_id: String,
+    pub based_on: Option<String>,
+    pub author_id: String,
+    pub author_name: String,
+    pub title: Option<String>,
+    pub depth: i32,
+}
+
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct RulesetLineage {
+    pub ruleset_id: String,
+    pub nodes: Vec<LineageNode>,
+}
+
+// What the parent has that the fork doesn't, read as the changes pulling from upstream would make
+#[derive(TS, Debug, Serialize, Deserialize, Clone)]
+#[ts(export)]
+pub struct UpstreamChanges {
+    pub based_on: String,
+    pub upstream_revision: i32,
+    pub diff: diff::ConfigDiff,
 }
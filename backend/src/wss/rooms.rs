@@ -2,7 +2,7 @@ use uuid::Uuid;
 
 use axum::{
     extract::{
-        Path, State, WebSocketUpgrade,
+        Path, Query, State, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
     http::StatusCode,
@@ -17,24 +17,34 @@ use tokio::{
 };
 use tracing::{info, instrument};
 
+use serde::Deserialize;
 use serde_json;
 
-#[instrument]
+#[derive(Debug, Deserialize)]
+pub struct RoomJoin {
+    pub player: Option<Uuid>, // Seat to take back after a disconnect or a server restart
+    pub token: Option<String>, // The seat's reconnect token, the player id alone isn't enough
+}
+
+// Join carries the reconnect token, keep it out of the logs
+#[instrument(skip(join))]
 pub async fn join_handler(
     Path(room): Path<u64>,
+    Query(join): Query<RoomJoin>,
     ws: WebSocketUpgrade,
     State(state): State<state::app::AppState>,
 ) -> impl IntoResponse {
     let rooms_map = state.rooms.lock().unwrap();
-    let game_tx_option = rooms_map.get(&room).cloned();
+    let game_tx_option = rooms_map.get(&room).map(|handle| handle.tx.clone());
     // Drop lock
     drop(rooms_map);
 
     if let Some(game_tx) = game_tx_option {
         info!("Room {room} found, upgrading connection");
 
-        return ws.on_upgrade(|websocket| async move {
-            match WebgameClient::join(websocket, game_tx) {
+        let seat = join.player.zip(join.token);
+        return ws.on_upgrade(move |websocket| async move {
+            match WebgameClient::join(websocket, game_tx, seat) {
                 Ok(client) => {
                     info!("Spawning a client");
                     tokio::spawn(client.handle_connection());
@@ -60,9 +70,13 @@ impl WebgameClient {
     pub fn join(
         ws: WebSocket,
         tx: mpsc::UnboundedSender<interface::WebgameRequest>,
+        seat: Option<(Uuid, String)>,
     ) -> Result<Self, String> {
         //TODO: W/db UUID can be gleaned from a user
-        let uuid = Uuid::new_v4();
+        let (uuid, reconnect_token) = match seat {
+            Some((player, token)) => (player, Some(token)),
+            None => (Uuid::new_v4(), None),
+        };
 
         let (tx_self, rx) = mpsc::unbounded_channel::<wrapper::GameSnapshot>();
         let new_client = Self {
@@ -76,6 +90,7 @@ impl WebgameClient {
         let join_request = interface::WebgameRequest {
             body: interface::WebgameRequestBody::Join(interface::WebgameJoin {
                 nickname: None, //TODO
+                reconnect_token,
                 tx: tx_self,
             }),
             player_id: uuid.into(),
@@ -55,6 +55,8 @@ export const V_RANK_FROM_CARD = "socs_rank_from_card"
 export const V_SUIT_FROM_CARD = "socs_suit_from_card"
 export const V_CARDS_MATCHING_RANK = "socs_cards_matching_rank"
 export const V_CARDS_MATCHING_SUIT = "socs_cards_matching_suit"
+export const V_CARDS_MATCHING_PATTERN = "socs_cards_matching_pattern"
+export const V_CARDS_MATCH_PATTERN = "socs_cards_match_pattern"
 
 export const V_PLAYER_CURRENT = "socs_player_current"
 export const V_PLAYER_OF_TYPE = "socs_player_of_type"
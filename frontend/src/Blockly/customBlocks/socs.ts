@@ -109,6 +109,35 @@ export default function generateBlockDefinitions() {
 		}
 	}
 
+	Blockly.Blocks[Defs.V_CARDS_MATCHING_PATTERN] = {
+		init: function(this: Blockly.Block) {
+			const currentBlock = this;
+			this.appendValueInput("ZONE")
+				.setCheck("socs_t_zone")
+				.appendField("cards in");
+			this.appendDummyInput()
+				.appendField("matching pattern")
+				.appendField(new Blockly.FieldDropdown(() => getVarOfTypeOptions(currentBlock, 'socs_v_pattern', true)), "PATTERN");
+			this.setInputsInline(true);
+			this.setColour(TYPE_TO_HUE['socs_t_card_sel']);
+			this.setOutput(true, "socs_t_card_sel");
+		}
+	}
+
+	Blockly.Blocks[Defs.V_CARDS_MATCH_PATTERN] = {
+		init: function(this: Blockly.Block) {
+			const currentBlock = this;
+			this.appendValueInput("CARDS")
+				.setCheck(["socs_t_card", "socs_t_card_sel"]);
+			this.appendDummyInput()
+				.appendField("match pattern")
+				.appendField(new Blockly.FieldDropdown(() => getVarOfTypeOptions(currentBlock, 'socs_v_pattern', true)), "PATTERN");
+			this.setInputsInline(true);
+			this.setColour(210);
+			this.setOutput(true, "Boolean");
+		}
+	}
+
 	Blockly.Blocks[Defs.B_SET_NUMBER] = {
 		init: function(this: Blockly.Block) {
 			const currentBlock = this;
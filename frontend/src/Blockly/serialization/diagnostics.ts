@@ -0,0 +1,115 @@
+import * as Blockly from 'blockly/core';
+
+import * as Defs from '@Blockly/customBlocks/defs';
+
+import type { ConfigDiagnostic } from '@client/types/schema/ruleset'
+
+import { blockToStatement } from '@Blockly/serialization/toGameConfig';
+
+// Warnings we set get this id so they never clear anything else on the block
+const DIAGNOSTIC_WARNING_ID = 'socs_diagnostic';
+
+/**
+ * Shows diagnostics from saving a ruleset as warnings on the blocks they point at.
+ * Diagnostics outside the phases have no block and are left to the caller to display.
+ * @param workspace The Blockly workspace the config was built from.
+ * @param diagnostics The diagnostics returned by the server.
+ * @returns The diagnostics that could not be placed on a block.
+ */
+export function showDiagnostics(workspace: Blockly.WorkspaceSvg, diagnostics: Array<ConfigDiagnostic>): Array<ConfigDiagnostic> {
+	for (const block of workspace.getAllBlocks(false)) {
+		block.setWarningText(null, DIAGNOSTIC_WARNING_ID);
+	}
+
+	const messages = new Map<Blockly.BlockSvg, Array<string>>();
+	const unplaced: Array<ConfigDiagnostic> = [];
+	for (const diagnostic of diagnostics) {
+		const block = blockForLocation(workspace, diagnostic.location) as Blockly.BlockSvg | null;
+		if (!block) {
+			unplaced.push(diagnostic);
+			continue;
+		}
+		const text = `${diagnostic.severity}: ${diagnostic.message}`;
+		messages.set(block, [...(messages.get(block) ?? []), text]);
+	}
+
+	for (const [block, text] of messages) {
+		block.setWarningText(text.join('\n'), DIAGNOSTIC_WARNING_ID);
+	}
+	return unplaced;
+}
+
+/**
+ * Finds the block a diagnostic location points at, e.g. ["phases", "main", "evaluate", "2", "go_true"].
+ * Mirrors how workspaceToGameConfig builds statements, so the indexes line up with the saved config.
+ * Stops at the closest statement block when the location goes into an expression.
+ * @param workspace The Blockly workspace.
+ * @param location The location path of the diagnostic.
+ * @returns The block or null if the location is not inside a phase.
+ */
+export function blockForLocation(workspace: Blockly.Workspace, location: Array<string>): Blockly.Block | null {
+	if (location[0] !== 'phases' || location.length < 2) return null;
+
+	const phaseBlock = workspace.getAllBlocks(false)
+		.find(block => block.type === Defs.B_PHASE && block.getFieldValue('PHASE') === location[1]);
+	if (!phaseBlock) return null;
+	if (location[2] !== 'evaluate') return phaseBlock;
+
+	return statementBlock(statementChain(phaseBlock.getNextBlock()), location.slice(3)) ?? phaseBlock;
+}
+
+// The blocks in a chain that become statements, a chain of one is a single statement and not a Block
+function statementChain(firstBlock: Blockly.Block | null): Array<Blockly.Block> {
+	const blocks: Array<Blockly.Block> = [];
+	for (let block = firstBlock; block; block = block.getNextBlock()) {
+		if (blockToStatement(block)) {
+			blocks.push(block);
+		}
+	}
+	return blocks;
+}
+
+function caseChain(firstBlock: Blockly.Block | null): Array<Blockly.Block> {
+	const blocks: Array<Blockly.Block> = [];
+	for (let block = firstBlock; block; block = block.getNextBlock()) {
+		if (!block.isShadow() && (block.type === Defs.B_OFFER_CASE || block.type === Defs.B_OFFER_CASE_ANY)) {
+			blocks.push(block);
+		}
+	}
+	return blocks;
+}
+
+function inputChain(block: Blockly.Block, input: string): Array<Blockly.Block> {
+	return statementChain(block.getInput(input)?.connection?.targetBlock() ?? null);
+}
+
+function statementBlock(chain: Array<Blockly.Block>, path: Array<string>): Blockly.Block | null {
+	if (chain.length === 0) return null;
+	if (chain.length === 1) return insideStatement(chain[0], path);
+
+	if (path.length === 0) return null;
+	const block = chain[Number(path[0])];
+	return block ? insideStatement(block, path.slice(1)) : null;
+}
+
+function insideStatement(block: Blockly.Block, path: Array<string>): Blockly.Block {
+	const [segment, ...rest] = path;
+	let inner: Blockly.Block | null = null;
+
+	if (block.type === Defs.B_IF_ELSE && segment === 'go_true') {
+		inner = statementBlock(inputChain(block, 'GO_TRUE'), rest);
+	} else if (block.type === Defs.B_IF_ELSE && segment === 'go_false') {
+		inner = statementBlock(inputChain(block, 'GO_FALSE'), rest);
+	} else if (block.type === Defs.B_WHILE && segment === 'do') {
+		inner = statementBlock(inputChain(block, 'DO'), rest);
+	} else if ((block.type === Defs.B_OFFER || block.type === Defs.B_OFFER_DECLARELESS) && segment === 'cases') {
+		const caseBlock = caseChain(block.getInput('CASES')?.connection?.targetBlock() ?? null)[Number(rest[0])];
+		if (caseBlock && rest[1] === 'handle') {
+			inner = statementBlock(inputChain(caseBlock, 'ACTIONS'), rest.slice(2)) ?? caseBlock;
+		} else {
+			inner = caseBlock ?? null;
+		}
+	}
+
+	return inner ?? block;
+}
@@ -404,6 +404,13 @@ function jsonToExpressionBlock(
 				// block.setFieldValue(boolExpr.PlayerIsType.type_name, 'TYPE_NAME_FIELD'); // If it were a field
 				console.warn("PlayerIsType.type_name mapping needs review. JSON provides string, block expects input.", boolExpr.PlayerIsType);
 
+			} else if (typeof boolExpr === 'object' && 'MatchesPattern' in boolExpr) {
+				block = workspace.newBlock(Defs.V_CARDS_MATCH_PATTERN);
+				block.setFieldValue(boolExpr.MatchesPattern.pattern, 'PATTERN');
+				const cardsInput = jsonToExpressionBlock({ CardCollection: boolExpr.MatchesPattern.cards }, workspace);
+				if (cardsInput && block.getInput('CARDS')?.connection) {
+					block.getInput('CARDS')!.connection!.connect(cardsInput.outputConnection!);
+				}
 			} else {
 				console.warn(`Unhandled BooleanExpression subtype:`, boolExpr);
 			}
@@ -541,6 +548,13 @@ function jsonToExpressionBlock(
 				if (rankInput && block.getInput('RANK')?.connection) {
 					block.getInput('RANK')!.connection!.connect(rankInput.outputConnection!);
 				}
+			} else if (typeof ccExpr === 'object' && 'InZoneMatchingPattern' in ccExpr) {
+				block = workspace.newBlock(Defs.V_CARDS_MATCHING_PATTERN);
+				block.setFieldValue(ccExpr.InZoneMatchingPattern.pattern, 'PATTERN');
+				const zoneInput = jsonToExpressionBlock({ Zone: ccExpr.InZoneMatchingPattern.zone }, workspace);
+				if (zoneInput && block.getInput('ZONE')?.connection) {
+					block.getInput('ZONE')!.connection!.connect(zoneInput.outputConnection!);
+				}
 			} else {
 				console.warn(`Unhandled CardCollectionExpression subtype:`, ccExpr);
 			}
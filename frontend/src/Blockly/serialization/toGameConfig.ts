@@ -361,6 +361,17 @@ export function valueBlockToBooleanExpression(block: Blockly.Block | null): Bool
 			}
 			return { PlayerIsType: { player: playerExpr, type_name: typeName } };
 		}
+		case Defs.V_CARDS_MATCH_PATTERN: {
+			const cardsBlock = block.getInput('CARDS')?.connection?.targetBlock() ?? null;
+			const cardsExpr = valueBlockToCardCollectionExpression(cardsBlock);
+			const pattern = block.getFieldValue('PATTERN');
+
+			if (!cardsExpr || !pattern) {
+				console.warn('socs_cards_match_pattern missing cards or pattern.', block);
+				return null;
+			}
+			return { MatchesPattern: { cards: cardsExpr, pattern: pattern } };
+		}
 		default:
 			console.warn(`BooleanExpression conversion not implemented for block type: ${block.type}.`, block);
 			return null;
@@ -549,6 +560,17 @@ export function valueBlockToCardCollectionExpression(block: Blockly.Block | null
 			}
 			return { InZoneMatchingSuit: { suit: suitExpr, zone: zoneExpr } };
 		}
+		case Defs.V_CARDS_MATCHING_PATTERN: {
+			const zoneBlock = block.getInput('ZONE')?.connection?.targetBlock() ?? null;
+			const zoneExpr = valueBlockToZoneExpression(zoneBlock);
+			const pattern = block.getFieldValue('PATTERN');
+
+			if (!zoneExpr || !pattern) {
+				console.warn('socs_cards_matching_pattern missing zone or pattern.', block);
+				return null;
+			}
+			return { InZoneMatchingPattern: { zone: zoneExpr, pattern: pattern } };
+		}
 		case Defs.V_CARD_SELECTOR: {
 			const zoneInput = block.getInput('ZONE');
 			if (!zoneInput || !zoneInput.connection) {
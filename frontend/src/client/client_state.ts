@@ -63,7 +63,7 @@ export class ClientState implements ClientStateInterface {
 				if (typeof action !== 'string' && 'JoinResult' in action) {
 					const joinResult = action.JoinResult;
 					if ('Ok' in joinResult) {
-						playerId = joinResult.Ok;
+						playerId = joinResult.Ok.player_id;
 					} else {
 						throw `Error while joining: {joinResult.Err}`;
 					}
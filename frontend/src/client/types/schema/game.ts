@@ -1,3 +1,11 @@
 export type { GameInfo } from '@bindings/GameInfo'
 export type { RoomExistance } from '@bindings/RoomExistance'
+export type { RoomInfo } from '@bindings/RoomInfo'
 export type { NewGame } from '@bindings/NewGame'
+export type { GameLogPreview } from '@bindings/GameLogPreview'
+export type { GameLogListing } from '@bindings/GameLogListing'
+export type { GameReplay } from '@bindings/GameReplay'
+export type { ReplayStep } from '@bindings/ReplayStep'
+export type { ReplayZone } from '@bindings/ReplayZone'
+export type { ReplayCard } from '@bindings/ReplayCard'
+export type { GameEvent } from '@bindings/GameEvent'
@@ -1,5 +1,19 @@
 export type { RulesetContents } from '@bindings/RulesetContents'
 export type { RulesetResult } from '@bindings/RulesetResult'
+export type { ConfigDiagnostic } from '@bindings/ConfigDiagnostic'
 
 export type { RulesetPreview } from '@bindings/RulesetPreview'
 export type { RulesetListing } from '@bindings/RulesetListing'
+
+export type { RevisionPreview } from '@bindings/RevisionPreview'
+export type { RevisionListing } from '@bindings/RevisionListing'
+export type { RevisionContents } from '@bindings/RevisionContents'
+export type { ConfigDiff } from '@bindings/ConfigDiff'
+export type { DeclarationChange } from '@bindings/DeclarationChange'
+export type { PhaseDiff } from '@bindings/PhaseDiff'
+export type { StatementChange } from '@bindings/StatementChange'
+export type { Change } from '@bindings/Change'
+export type { PublishResult } from '@bindings/PublishResult'
+export type { LineageNode } from '@bindings/LineageNode'
+export type { RulesetLineage } from '@bindings/RulesetLineage'
+export type { UpstreamChanges } from '@bindings/UpstreamChanges'
@@ -1,8 +1,15 @@
 export type { PlayerCommand } from '@bindings/PlayerCommand'
 export type { GameAction } from '@bindings/GameAction';
+export type { JoinAccepted } from '@bindings/JoinAccepted';
 export type { GameStatus } from '@bindings/GameStatus';
 export type { PlayerSnapshot } from '@bindings/PlayerSnapshot';
 export type { GameChat } from '@bindings/GameChat';
 export type { GameSnapshot } from '@bindings/GameSnapshot';
+export type { ZoneSnapshot } from '@bindings/ZoneSnapshot';
+export type { OfferSnapshot } from '@bindings/OfferSnapshot';
+export type { OfferCaseSnapshot } from '@bindings/OfferCaseSnapshot';
+export type { OfferChoiceSnapshot } from '@bindings/OfferChoiceSnapshot';
+export type { OfferAnswer } from '@bindings/OfferAnswer';
+export type { OfferChoiceAnswer } from '@bindings/OfferChoiceAnswer';
 
 export type { UserInfo } from '@bindings/UserInfo';
@@ -1,4 +1,4 @@
-import type { GameSnapshot } from './types/wss'
+import type { GameSnapshot, JoinAccepted } from './types/wss'
 
 export function connectToGame(
 	roomId: number,
@@ -9,7 +9,14 @@ export function connectToGame(
 	// TODO: Have server pass back an upgrade url when GETing /v1/rooms/{roomId}
 	const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
 	const host = window.location.host;
-	const wsUrl = `${protocol}//${host}/v1/rooms/${roomId}`;
+	// Rooms remember seats by player ID, sending it back with the seat's token takes the seat again
+	const seatKey = `socs_player_${roomId}`;
+	const stored = sessionStorage.getItem(seatKey);
+	const seat: JoinAccepted | null = stored ? JSON.parse(stored) : null;
+	const query = seat?.reconnect_token
+		? `?player=${encodeURIComponent(seat.player_id)}&token=${encodeURIComponent(seat.reconnect_token)}`
+		: '';
+	const wsUrl = `${protocol}//${host}/v1/rooms/${roomId}${query}`;
 
 	console.log(`Connecting to ${wsUrl}`);
 
@@ -24,6 +31,16 @@ export function connectToGame(
 			const rawData = JSON.parse(event.data as string);
 			const snapshot = rawData as GameSnapshot;
 
+			snapshot?.private_actions?.forEach((action) => {
+				if (typeof action !== 'string' && 'JoinResult' in action) {
+					if ('Ok' in action.JoinResult) {
+						sessionStorage.setItem(seatKey, JSON.stringify(action.JoinResult.Ok));
+					} else {
+						// Seat can't be taken back, the next connection joins as someone new
+						sessionStorage.removeItem(seatKey);
+					}
+				}
+			});
 			snapshot ? onSnapshot(snapshot) : console.warn("Received invalid Game Snapshot");
 		}
 		catch (e) {
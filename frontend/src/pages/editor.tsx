@@ -1,9 +1,9 @@
 import type { GameConfig } from '@client/types/engine/config'
-import type { RulesetContents } from '@client/types/schema/ruleset'
+import type { RulesetContents, ConfigDiagnostic } from '@client/types/schema/ruleset'
 
 import ConfigDisplay from '@components/editor/config_display'
 
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import styles from './editor.module.css';
 
 import BlocklyComponent from '@Blockly/index';
@@ -12,12 +12,16 @@ import * as Blockly from 'blockly/core';
 import { useRef } from 'react';
 
 import { workspaceToGameConfig } from '@Blockly/serialization/toGameConfig';
+import { showDiagnostics } from '@Blockly/serialization/diagnostics';
 
 import utilityStyles from '@styles/utility.module.css'
 
 type DisplaySetting = "Block" | "Settings" | "Information";
 
-function Editor({ ruleset, message = null, saveRuleset = null }: { ruleset: RulesetContents, message: string | null, saveRuleset: ((rule: RulesetContents) => void) | null }) {
+// Shared so the default doesn't change identity on every render
+const NO_DIAGNOSTICS: ConfigDiagnostic[] = [];
+
+function Editor({ ruleset, message = null, diagnostics = NO_DIAGNOSTICS, saveRuleset = null }: { ruleset: RulesetContents, message: string | null, diagnostics?: ConfigDiagnostic[], saveRuleset: ((rule: RulesetContents) => void) | null }) {
 	const [currentDisplay, setCurrentDisplay] = useState<DisplaySetting>("Block");
 
 	const [currentConfig, setCurrentConfig] = useState<GameConfig>(ruleset.config);
@@ -27,8 +31,23 @@ function Editor({ ruleset, message = null, saveRuleset = null }: { ruleset: Rule
 
 	const primaryWorkspace = useRef<Blockly.WorkspaceSvg | null>(null);
 
+	// Diagnostics outside the phases have no block to sit on, so they're listed above the editor instead
+	const [unplacedDiagnostics, setUnplacedDiagnostics] = useState<ConfigDiagnostic[]>(diagnostics);
+
+	useEffect(() => {
+		if (primaryWorkspace.current) {
+			setUnplacedDiagnostics(showDiagnostics(primaryWorkspace.current, diagnostics));
+		} else {
+			setUnplacedDiagnostics(diagnostics);
+		}
+	}, [diagnostics]);
+
 	function handleSetWorkspace(workspace: Blockly.WorkspaceSvg | null) {
 		primaryWorkspace.current = workspace;
+		// The workspace is rebuilt when switching views, put the warnings back on the new blocks
+		if (workspace) {
+			showDiagnostics(workspace, diagnostics);
+		}
 	}
 
 	function handleSwitchDisplay(option: DisplaySetting) {
@@ -63,6 +82,9 @@ function Editor({ ruleset, message = null, saveRuleset = null }: { ruleset: Rule
 			<div>
 				<div className={utilityStyles.centerHor}>
 					<div> {message} </div>
+					{unplacedDiagnostics.map((diagnostic, idx) =>
+						<div key={idx}> {diagnostic.severity}: {diagnostic.location.join("/")}: {diagnostic.message} </div>
+					)}
 					<div className={styles.bar}>
 						<div className={styles.title} > {currentTitle} </div>
 						<SwitchMenu options={["Settings", "Block", "Information"]} setOption={handleSwitchDisplay} />
@@ -142,6 +164,8 @@ function Blocks({ config, setWorkspace }: {
 			<Block type="socs_cards_move" />
 			<Block type="socs_cards_matching_rank" />
 			<Block type="socs_cards_matching_suit" />
+			<Block type="socs_cards_matching_pattern" />
+			<Block type="socs_cards_match_pattern" />
 
 			<Block type="socs_offer" />
 			<Block type="socs_offer_declareless" />
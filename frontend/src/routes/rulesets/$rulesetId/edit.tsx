@@ -78,7 +78,7 @@ function RouteComponent() {
 		<>
 			<div className={styles.pageWrapper}>
 				<Header />
-				<Editor ruleset={rulesetInfo.data} message={message} saveRuleset={handleEditRuleset} />
+				<Editor ruleset={rulesetInfo.data} message={message} diagnostics={editMutation.data?.diagnostics} saveRuleset={handleEditRuleset} />
 				<Footer />
 			</div>
 		</>